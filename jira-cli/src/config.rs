@@ -0,0 +1,95 @@
+use crate::models::{ProjectKey, UserId, Workflow};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// User-level settings for IronJira, read from `config.yaml` in the configuration directory
+/// of the underlying operating system (see [persistence::load_config](crate::persistence::load_config)).
+///
+/// Every setting is optional: a missing file is equivalent to an empty one.
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+pub struct Config {
+    /// The handle of the person using the CLI.
+    /// It is recorded as the reporter of new tickets and it is used to resolve `--mine`.
+    #[serde(default)]
+    pub current_user: Option<UserId>,
+    /// The project new tickets are filed in when `--project` is not specified.
+    #[serde(default)]
+    pub default_project: Option<ProjectKey>,
+    /// The allowed status transitions, see [Workflow].
+    #[serde(default)]
+    pub workflow: Workflow,
+    /// How tickets are saved on disk, see [Storage].
+    #[serde(default)]
+    pub storage: Storage,
+    /// Where notifications are sent, besides the inbox of their recipient, see [Notifications].
+    #[serde(default)]
+    pub notifications: Notifications,
+}
+
+/// How the [TicketStore](crate::store::TicketStore) is saved on disk.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Storage {
+    #[serde(default)]
+    pub mode: StorageMode,
+    /// In [event-log](StorageMode::EventLog) mode, the number of events after which a snapshot is
+    /// saved and the log is compacted.
+    #[serde(default = "default_snapshot_every")]
+    pub snapshot_every: usize,
+}
+
+fn default_snapshot_every() -> usize {
+    100
+}
+
+impl Default for Storage {
+    fn default() -> Self {
+        Storage {
+            mode: StorageMode::default(),
+            snapshot_every: default_snapshot_every(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Copy, Clone, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum StorageMode {
+    /// The whole store is saved after every command.
+    #[default]
+    Snapshot,
+    /// Changes to tickets are appended to an event log, next to the latest snapshot of the store.
+    EventLog,
+}
+
+/// How notifications leave IronJira, e.g. to be picked up by mail tooling.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Notifications {
+    /// The domain of the e-mail addresses of users, e.g. `example.com` for `alice@example.com`.
+    #[serde(default = "default_domain")]
+    pub domain: String,
+    /// Every notification is written to each of these sinks.
+    #[serde(default)]
+    pub sinks: Vec<Sink>,
+}
+
+fn default_domain() -> String {
+    "localhost".to_string()
+}
+
+impl Default for Notifications {
+    fn default() -> Self {
+        Notifications {
+            domain: default_domain(),
+            sinks: Vec::new(),
+        }
+    }
+}
+
+/// A destination for notifications, see [outbox](crate::outbox).
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Sink {
+    /// A maildir: every notification is an RFC 5322 message file in its `new` sub-directory.
+    Maildir { path: PathBuf },
+    /// A file every notification is appended to, as a line of JSON.
+    Ndjson { path: PathBuf },
+}
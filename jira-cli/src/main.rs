@@ -1,11 +1,27 @@
 #![allow(clippy::new_without_default)]
 
-use crate::models::{Comment, Status, TicketDraft, TicketPatch, Title};
+use crate::config::Config;
+use crate::models::{
+    mentions, Age, CarryOver, Comment, CommentId, CustomField, CustomFieldKind, CustomFieldName,
+    FilterName, Label, LinkKind, NotificationId, Priority, Project, ProjectKey, SavedFilter,
+    SprintDraft, SprintId, Status, Ticket, TicketDraft, TicketId, TicketKind, TicketPatch,
+    TicketSelection, Title, User, UserId, WorkDuration, Worklog,
+};
+use crate::query::{Expression, Field, Operator, OrderBy, Query, Value};
+use crate::store::TicketStore;
+use chrono::{Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use std::collections::{BTreeMap, HashSet};
 use std::error::Error;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
 use std::str::FromStr;
 
+pub mod config;
 pub mod models;
+pub mod outbox;
 pub mod persistence;
+pub mod query;
+pub mod search;
 pub mod store;
 
 #[derive(structopt::StructOpt)]
@@ -19,127 +35,1859 @@ pub enum Command {
         /// Title of your ticket - it cannot be empty!
         #[structopt(long)]
         title: String,
+        /// Handle of the user who should work on the ticket.
+        #[structopt(long)]
+        assignee: Option<UserId>,
+        /// How urgent the ticket is: lowest, low, medium, high or highest. Defaults to medium.
+        #[structopt(long)]
+        priority: Option<Priority>,
+        /// epic, story, task, subtask or bug. Defaults to task.
+        #[structopt(long)]
+        kind: Option<TicketKind>,
+        /// Id of the ticket this one is a part of, e.g. its epic.
+        #[structopt(long)]
+        parent: Option<TicketId>,
+        /// Key of the project to file the ticket in. Defaults to the `default_project` in the
+        /// configuration file, or to the only project if there is just one.
+        #[structopt(long)]
+        project: Option<ProjectKey>,
+        /// The value of a custom field, e.g. `--field story-points=3` - can be repeated.
+        #[structopt(long = "field")]
+        fields: Vec<CustomFieldValue>,
     },
     /// Edit the details of an existing ticket.
     Edit {
         #[structopt(long)]
-        ticket_id: u64,
+        ticket_id: TicketId,
         #[structopt(long)]
         title: Option<String>,
         #[structopt(long)]
         description: Option<String>,
+        #[structopt(long)]
+        priority: Option<Priority>,
+        /// Handle of the user who should work on the ticket.
+        #[structopt(long)]
+        assignee: Option<UserId>,
+        /// Remove the current assignee of the ticket.
+        #[structopt(long, conflicts_with = "assignee")]
+        unassign: bool,
+        /// Handle of the user who filed the ticket.
+        #[structopt(long)]
+        reporter: Option<UserId>,
+        /// Id of the ticket this one is a part of, e.g. its epic.
+        #[structopt(long)]
+        parent: Option<TicketId>,
+        /// Detach the ticket from its current parent.
+        #[structopt(long, conflicts_with = "parent")]
+        no_parent: bool,
+        /// How long the ticket is expected to take, e.g. `2d 4h`.
+        #[structopt(long)]
+        estimate: Option<WorkDuration>,
+        /// How much work is left on the ticket, e.g. `3h`.
+        #[structopt(long)]
+        remaining: Option<WorkDuration>,
+        /// The value of a custom field, e.g. `--field story-points=3` - can be repeated.
+        /// An empty value, e.g. `--field customer=`, clears the field.
+        #[structopt(long = "field")]
+        fields: Vec<CustomFieldValue>,
     },
     /// Delete a ticket from the store passing the ticket id.
     Delete {
         #[structopt(long)]
-        ticket_id: u64,
+        ticket_id: TicketId,
     },
-    /// List all existing tickets.
-    List,
-    /// Move a ticket to a new status.
+    /// List all existing tickets, the most urgent first.
+    List {
+        /// Only show tickets of this project.
+        #[structopt(long)]
+        project: Option<ProjectKey>,
+        /// Only show tickets with this priority - can be repeated to show several priorities.
+        #[structopt(long)]
+        priority: Vec<Priority>,
+        /// Only show tickets assigned to this user.
+        #[structopt(long)]
+        assignee: Option<UserId>,
+        /// Only show tickets assigned to you - requires `current_user` to be set in the configuration file.
+        #[structopt(long, conflicts_with = "assignee")]
+        mine: bool,
+        /// Only show tickets matching a query, e.g.
+        /// `status = InProgress AND assignee = me AND label in (backend) ORDER BY priority DESC`.
+        #[structopt(long)]
+        query: Option<String>,
+        /// Only show tickets matching a saved filter (see `filter save`).
+        #[structopt(long, conflicts_with = "query")]
+        filter: Option<FilterName>,
+        /// Sort tickets by these fields, e.g. `--sort-by status,priority:desc`, instead of the most urgent first.
+        /// It replaces the `ORDER BY` clause of the query, if any.
+        #[structopt(long, use_delimiter = true)]
+        sort_by: Vec<String>,
+        /// Show at most this many tickets, at least one.
+        #[structopt(long)]
+        limit: Option<NonZeroUsize>,
+        /// Skip this many tickets, e.g. `--offset 20 --limit 20` to show the second page of 20 tickets.
+        #[structopt(long, default_value = "0")]
+        offset: usize,
+        /// Show tickets as a table with these columns, e.g. `--columns key,title,status`.
+        #[structopt(long, use_delimiter = true, conflicts_with = "tree")]
+        columns: Vec<String>,
+        /// Show tickets as a tree, following their parents, with the progress of each epic.
+        #[structopt(long)]
+        tree: bool,
+    },
+    /// Move a ticket to a new status - omit the status to see the valid next statuses.
     Move {
         #[structopt(long)]
-        ticket_id: u64,
+        ticket_id: TicketId,
+        /// One of the statuses of your workflow, e.g. `inprogress` or `in-review`.
+        #[structopt(long)]
+        status: Option<String>,
+        /// Comment explaining the move, e.g. a resolution - some statuses require it.
         #[structopt(long)]
-        status: Status,
+        comment: Option<String>,
+    },
+    /// Revert the last command that changed the board, e.g. tickets, sprints, users or filters.
+    Undo,
+    /// Apply again the last undone command.
+    Redo,
+    /// Show a ticket, with its comments grouped in threads.
+    Show {
+        #[structopt(long)]
+        ticket_id: TicketId,
+    },
+    /// Find tickets by the words of their title, description or comments, the most relevant first.
+    Search {
+        /// The words to look for, e.g. `"connection timeout"`.
+        text: String,
+        /// Show at most this many tickets.
+        #[structopt(long, default_value = "10")]
+        limit: usize,
+    },
+    /// Show who changed what on a ticket, the oldest change first.
+    History {
+        #[structopt(long)]
+        ticket_id: TicketId,
     },
     /// Add a comment to a ticket
     Comment {
         #[structopt(long)]
-        ticket_id: u64,
+        ticket_id: TicketId,
         /// Add a comment on the ticket - cannot be empty!
         #[structopt(long)]
         comment: String,
+        /// The id of the comment to reply to, as shown next to it, e.g. 2 for [2].
+        #[structopt(long)]
+        reply_to: Option<CommentId>,
+    },
+    /// Fix the text of a comment. The previous version is kept in its edit history.
+    EditComment {
+        #[structopt(long)]
+        ticket_id: TicketId,
+        /// The id of the comment, as shown next to it, e.g. 2 for [2].
+        #[structopt(long)]
+        comment_id: CommentId,
+        /// The new text of the comment - cannot be empty!
+        #[structopt(long)]
+        comment: String,
+    },
+    /// Remove a comment from a ticket.
+    DeleteComment {
+        #[structopt(long)]
+        ticket_id: TicketId,
+        #[structopt(long)]
+        comment_id: CommentId,
+    },
+    /// Record time spent working on a ticket.
+    LogWork {
+        #[structopt(long)]
+        ticket_id: TicketId,
+        /// Time spent, e.g. `1h30m`, `2d` or `1w 4h` - a day is 8 hours, a week is 5 days.
+        #[structopt(long)]
+        time: WorkDuration,
+        /// When the work started, e.g. 2020-03-02T09:30:00. Defaults to now.
+        #[structopt(long)]
+        started: Option<NaiveDateTime>,
+        /// What was done.
+        #[structopt(long)]
+        note: Option<String>,
+        /// New estimate of the work left. Defaults to the previous one minus the time spent.
+        #[structopt(long)]
+        remaining: Option<WorkDuration>,
+        /// Handle of the user who did the work. Defaults to `current_user` in the configuration file.
+        #[structopt(long)]
+        author: Option<UserId>,
+    },
+    /// Show the time logged by each user, day by day.
+    Timesheet {
+        /// First day of the report, e.g. 2020-03-02. Defaults to a week ago.
+        #[structopt(long)]
+        from: Option<NaiveDate>,
+        /// Last day of the report. Defaults to today.
+        #[structopt(long)]
+        to: Option<NaiveDate>,
+    },
+    /// Link a ticket to another one, e.g. to record that it blocks it.
+    Link {
+        #[structopt(long)]
+        ticket_id: TicketId,
+        /// blocks, is-blocked-by, duplicates, is-duplicated-by or relates-to.
+        #[structopt(long)]
+        kind: LinkKind,
+        /// Id of the ticket on the other end of the link.
+        #[structopt(long)]
+        target: TicketId,
+    },
+    /// Remove a link between two tickets.
+    Unlink {
+        #[structopt(long)]
+        ticket_id: TicketId,
+        #[structopt(long)]
+        kind: LinkKind,
+        #[structopt(long)]
+        target: TicketId,
+    },
+    /// Get notified when a ticket is moved, edited or commented.
+    Watch {
+        #[structopt(long)]
+        ticket_id: TicketId,
+        /// Handle of the watcher. Defaults to the current user.
+        #[structopt(long)]
+        user: Option<UserId>,
+    },
+    /// Stop getting notified of the changes to a ticket.
+    Unwatch {
+        #[structopt(long)]
+        ticket_id: TicketId,
+        /// Handle of the watcher. Defaults to the current user.
+        #[structopt(long)]
+        user: Option<UserId>,
+    },
+    /// Attach a file to a ticket, e.g. a log or a screenshot.
+    Attach {
+        #[structopt(long)]
+        ticket_id: TicketId,
+        /// The file to attach. A file with the same name attached to the ticket is replaced.
+        #[structopt(long, parse(from_os_str))]
+        file: PathBuf,
+    },
+    /// List, extract and clean up attached files.
+    Attachment(AttachmentCommand),
+    /// Manage the projects tickets are filed in.
+    Project(ProjectCommand),
+    /// Manage the users who can file and work on tickets.
+    User(UserCommand),
+    /// Tag tickets with labels and find tickets by label.
+    Label(LabelCommand),
+    /// Plan, run and review sprints.
+    Sprint(SprintCommand),
+    /// Browse, restore and purge deleted tickets.
+    Trash(TrashCommand),
+    /// Read the notifications of the current user, e.g. when they are mentioned in a comment.
+    Inbox(InboxCommand),
+    /// Save, share and reuse the queries used to list tickets.
+    Filter(FilterCommand),
+    /// Change many tickets at once, e.g. to close the tickets of a release.
+    Bulk(BulkCommand),
+    /// Define the extra fields tickets can have, e.g. `story-points` or `customer`.
+    CustomField(CustomFieldCommand),
+}
+
+#[derive(structopt::StructOpt)]
+pub enum ProjectCommand {
+    /// Create a new project.
+    Create {
+        /// The prefix of the ticket ids of the project, e.g. `CORE` for CORE-1, CORE-2, ...
+        #[structopt(long)]
+        key: ProjectKey,
+        /// The full name of the project, e.g. "Core platform".
+        #[structopt(long)]
+        name: String,
+        /// Handle of the user responsible for the project.
+        #[structopt(long)]
+        lead: Option<UserId>,
+    },
+    /// List all projects.
+    List,
+}
+
+#[derive(structopt::StructOpt)]
+pub enum UserCommand {
+    /// Register a new user, or update the display name of an existing one.
+    Add {
+        /// The handle of the user, e.g. `alice` - letters, digits, '.', '-' and '_' only.
+        #[structopt(long)]
+        handle: UserId,
+        /// The full name of the user, e.g. "Alice Liddell".
+        #[structopt(long)]
+        name: String,
+    },
+    /// List all registered users.
+    List,
+}
+
+#[derive(structopt::StructOpt)]
+pub enum SprintCommand {
+    /// Plan a new sprint.
+    Create {
+        #[structopt(long)]
+        name: String,
+        /// What the team wants to achieve by the end of the sprint.
+        #[structopt(long)]
+        goal: Option<String>,
+        /// First day of the sprint, e.g. 2020-03-02. Defaults to today.
+        #[structopt(long)]
+        start: Option<NaiveDate>,
+        /// Last day of the sprint. Defaults to two weeks after its start.
+        #[structopt(long)]
+        end: Option<NaiveDate>,
+    },
+    /// Add a ticket to a planned or active sprint.
+    Add {
+        #[structopt(long)]
+        ticket_id: TicketId,
+        #[structopt(long)]
+        sprint: SprintId,
+    },
+    /// Move a ticket back to the backlog.
+    Remove {
+        #[structopt(long)]
+        ticket_id: TicketId,
+    },
+    /// Start a planned sprint.
+    Start {
+        #[structopt(long)]
+        sprint: SprintId,
+    },
+    /// Complete the active sprint, carrying its unfinished tickets over.
+    Complete {
+        #[structopt(long)]
+        sprint: SprintId,
+        /// The sprint unfinished tickets are moved to. They go back to the backlog if not specified.
+        #[structopt(long)]
+        to: Option<SprintId>,
+    },
+    /// List all sprints.
+    List,
+    /// Show the progress and the tickets of a sprint.
+    Show {
+        #[structopt(long)]
+        sprint: SprintId,
+    },
+    /// List the tickets that are not part of any sprint.
+    Backlog,
+}
+
+#[derive(structopt::StructOpt)]
+pub enum AttachmentCommand {
+    /// List the files attached to a ticket.
+    List {
+        #[structopt(long)]
+        ticket_id: TicketId,
+    },
+    /// Copy an attached file out of IronJira.
+    Extract {
+        #[structopt(long)]
+        ticket_id: TicketId,
+        /// The name of the attached file, e.g. `server.log`.
+        #[structopt(long)]
+        name: String,
+        /// Where to copy the file. Defaults to its name, in the current directory.
+        #[structopt(long, parse(from_os_str))]
+        to: Option<PathBuf>,
+    },
+    /// Delete the saved files no ticket refers to anymore.
+    Gc,
+}
+
+#[derive(structopt::StructOpt)]
+pub enum InboxCommand {
+    /// List the unread notifications, the most recent first.
+    List {
+        /// List the notifications that were already read as well.
+        #[structopt(long)]
+        all: bool,
+    },
+    /// Mark a notification, or all of them, as read.
+    Read {
+        /// The id of the notification, as shown next to it, e.g. 3 for [3].
+        #[structopt(long, required_unless = "all")]
+        notification_id: Option<NotificationId>,
+        #[structopt(long)]
+        all: bool,
+    },
+}
+
+#[derive(structopt::StructOpt)]
+pub enum TrashCommand {
+    /// List the deleted tickets, the most recently deleted first.
+    List,
+    /// Bring a deleted ticket back, with its original id.
+    Restore {
+        #[structopt(long)]
+        ticket_id: TicketId,
+    },
+    /// Delete for good the tickets that have been in the trash for a while.
+    Purge {
+        /// How long tickets stay in the trash, e.g. 30d or 2w.
+        #[structopt(long)]
+        older_than: Age,
     },
 }
 
-impl FromStr for Status {
+#[derive(structopt::StructOpt)]
+pub enum CustomFieldCommand {
+    /// Add a field to the schema, e.g. `custom-field add --name environment --type enum
+    /// --allowed-value staging,production`.
+    Add {
+        /// Used in `--field name=value` and in queries, e.g. `story-points`.
+        #[structopt(long)]
+        name: CustomFieldName,
+        /// text, number, date (YYYY-MM-DD), enum or user.
+        #[structopt(long = "type")]
+        kind: CustomFieldKind,
+        /// Require a value on every new ticket.
+        #[structopt(long)]
+        required: bool,
+        /// The values of an enum field - can be repeated, or separated by commas.
+        #[structopt(long, use_delimiter = true)]
+        allowed_value: Vec<String>,
+    },
+    /// List the custom fields.
+    List,
+    /// Remove a field from the schema, and its values from all tickets.
+    Remove {
+        #[structopt(long)]
+        name: CustomFieldName,
+    },
+}
+
+/// The tickets a `bulk` command applies to.
+#[derive(structopt::StructOpt)]
+pub struct BulkTarget {
+    /// Can be repeated, or separated by commas, e.g. `--ticket-id CORE-1,CORE-2`.
+    #[structopt(long, use_delimiter = true, conflicts_with_all = &["filter", "query"])]
+    ticket_id: Vec<TicketId>,
+    /// The tickets matching a saved filter (see `filter save`).
+    #[structopt(long, conflicts_with = "query")]
+    filter: Option<FilterName>,
+    /// The tickets matching a query, as accepted by `list --query`.
+    #[structopt(long)]
+    query: Option<String>,
+    /// Show what would happen to each ticket, without saving anything.
+    #[structopt(long)]
+    dry_run: bool,
+}
+
+#[derive(structopt::StructOpt)]
+pub enum BulkCommand {
+    /// Move tickets to a new status.
+    Move {
+        #[structopt(flatten)]
+        target: BulkTarget,
+        /// One of the statuses of your workflow, e.g. `done`.
+        #[structopt(long)]
+        status: String,
+        /// Comment posted on every ticket, e.g. a resolution - some statuses require it.
+        #[structopt(long)]
+        comment: Option<String>,
+    },
+    /// Attach a label to tickets, or detach it with `--remove`.
+    Label {
+        #[structopt(flatten)]
+        target: BulkTarget,
+        #[structopt(long)]
+        label: Label,
+        #[structopt(long)]
+        remove: bool,
+    },
+    /// Assign tickets to a user, or unassign them with `--unassign`.
+    Assign {
+        #[structopt(flatten)]
+        target: BulkTarget,
+        #[structopt(long, required_unless = "unassign", conflicts_with = "unassign")]
+        assignee: Option<UserId>,
+        #[structopt(long)]
+        unassign: bool,
+    },
+    /// Move tickets to the trash.
+    Delete {
+        #[structopt(flatten)]
+        target: BulkTarget,
+    },
+}
+
+#[derive(structopt::StructOpt)]
+pub enum FilterCommand {
+    /// Save a filter under a name, e.g. `filter save mine-open --status inprogress --assignee me`.
+    /// All the criteria must be met; use `--query` for anything more elaborate.
+    Save {
+        #[structopt(long)]
+        name: FilterName,
+        /// What the filter is for.
+        #[structopt(long)]
+        description: Option<String>,
+        /// A query, as accepted by `list --query`.
+        #[structopt(long)]
+        query: Option<String>,
+        #[structopt(long)]
+        project: Option<ProjectKey>,
+        /// One of the statuses of your workflow, e.g. `inprogress`.
+        #[structopt(long)]
+        status: Option<String>,
+        /// Can be repeated to match any of several priorities.
+        #[structopt(long)]
+        priority: Vec<Priority>,
+        /// A user handle, or `me` for whoever uses the filter.
+        #[structopt(long)]
+        assignee: Option<String>,
+        /// Can be repeated to require several labels.
+        #[structopt(long)]
+        label: Vec<Label>,
+        /// Sort tickets by these fields, e.g. `priority:desc`.
+        #[structopt(long, use_delimiter = true)]
+        sort_by: Vec<String>,
+        /// Replace the filter with the same name, if there is one.
+        #[structopt(long)]
+        replace: bool,
+    },
+    /// List the saved filters.
+    List,
+    /// Delete a saved filter.
+    Delete {
+        #[structopt(long)]
+        name: FilterName,
+    },
+    /// Write filters to a file, to share them - all of them if no name is specified.
+    Export {
+        #[structopt(long, parse(from_os_str))]
+        to: PathBuf,
+        /// Can be repeated to export several filters.
+        #[structopt(long)]
+        name: Vec<FilterName>,
+    },
+    /// Save the filters of a file written by `filter export`.
+    Import {
+        #[structopt(long, parse(from_os_str))]
+        from: PathBuf,
+        /// Replace the filters that have the same name as an imported one.
+        #[structopt(long)]
+        replace: bool,
+    },
+}
+
+#[derive(structopt::StructOpt)]
+pub enum LabelCommand {
+    /// Attach a label to a ticket.
+    Add {
+        #[structopt(long)]
+        ticket_id: TicketId,
+        /// A single word, e.g. `backend` - labels are case-insensitive.
+        #[structopt(long)]
+        label: Label,
+    },
+    /// Detach a label from a ticket.
+    Remove {
+        #[structopt(long)]
+        ticket_id: TicketId,
+        #[structopt(long)]
+        label: Label,
+    },
+    /// List the tickets carrying a label or, if no label is specified, all labels in use.
+    List {
+        #[structopt(long)]
+        label: Option<Label>,
+    },
+}
+
+impl FromStr for Priority {
     type Err = Box<dyn Error>;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let s = s.to_lowercase();
-        let status = match s.as_str() {
-            "todo" | "to-do" => Status::ToDo,
-            "inprogress" | "in-progress" => Status::InProgress,
-            "blocked" => Status::Blocked,
-            "done" => Status::Done,
-            _ => panic!("The status you specified is not valid. Valid values: todo, inprogress, blocked and done.")
+        let priority = match s.as_str() {
+            "lowest" => Priority::Lowest,
+            "low" => Priority::Low,
+            "medium" => Priority::Medium,
+            "high" => Priority::High,
+            "highest" => Priority::Highest,
+            _ => return Err("The priority you specified is not valid. Valid values: lowest, low, medium, high and highest.".into()),
+        };
+        Ok(priority)
+    }
+}
+
+impl FromStr for TicketKind {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_lowercase();
+        let kind = match s.as_str() {
+            "epic" => TicketKind::Epic,
+            "story" => TicketKind::Story,
+            "task" => TicketKind::Task,
+            "subtask" | "sub-task" => TicketKind::SubTask,
+            "bug" => TicketKind::Bug,
+            _ => return Err("The kind you specified is not valid. Valid values: epic, story, task, subtask and bug.".into()),
+        };
+        Ok(kind)
+    }
+}
+
+impl FromStr for LinkKind {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_lowercase();
+        let kind = match s.as_str() {
+            "blocks" => LinkKind::Blocks,
+            "is-blocked-by" | "blocked-by" => LinkKind::IsBlockedBy,
+            "duplicates" => LinkKind::Duplicates,
+            "is-duplicated-by" | "duplicated-by" => LinkKind::IsDuplicatedBy,
+            "relates-to" | "relates" => LinkKind::RelatesTo,
+            _ => return Err("The link kind you specified is not valid. Valid values: blocks, is-blocked-by, duplicates, is-duplicated-by and relates-to.".into()),
+        };
+        Ok(kind)
+    }
+}
+
+impl FromStr for CustomFieldName {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(CustomFieldName::new(s.to_string())?)
+    }
+}
+
+impl FromStr for CustomFieldKind {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let kind = match s.to_lowercase().as_str() {
+            "text" => CustomFieldKind::Text,
+            "number" => CustomFieldKind::Number,
+            "date" => CustomFieldKind::Date,
+            "enum" => CustomFieldKind::Enum,
+            "user" => CustomFieldKind::User,
+            _ => return Err("The field type you specified is not valid. Valid values: text, number, date, enum and user.".into()),
+        };
+        Ok(kind)
+    }
+}
+
+/// The value of a custom field on the command line, e.g. `story-points=3`.
+pub struct CustomFieldValue {
+    name: CustomFieldName,
+    value: String,
+}
+
+impl FromStr for CustomFieldValue {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((name, value)) => Ok(CustomFieldValue {
+                name: name.trim().parse()?,
+                value: value.to_string(),
+            }),
+            None => Err(format!("Invalid field value {} - use name=value.", s).into()),
+        }
+    }
+}
+
+impl FromStr for FilterName {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(FilterName::new(s.to_string())?)
+    }
+}
+
+impl FromStr for ProjectKey {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(ProjectKey::new(s.to_string())?)
+    }
+}
+
+impl FromStr for UserId {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(UserId::new(s.to_string())?)
+    }
+}
+
+impl FromStr for Label {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Label::new(s.to_string())?)
+    }
+}
+
+/// Returns the handle of the user configured as `current_user`, failing if there is none.
+fn current_user(config: &Config) -> Result<UserId, Box<dyn Error>> {
+    config.current_user.clone().ok_or_else(|| {
+        "No current user configured - set `current_user` in the IronJira configuration file.".into()
+    })
+}
+
+/// The project new tickets are filed in: the one specified on the command line, the `default_project`
+/// in the configuration file or, if there is only one project, that project.
+fn resolve_project(
+    ticket_store: &TicketStore,
+    config: &Config,
+    project: Option<ProjectKey>,
+) -> Result<ProjectKey, Box<dyn Error>> {
+    if let Some(project) = project.or_else(|| config.default_project.clone()) {
+        return Ok(project);
+    }
+    match ticket_store.list_projects().as_slice() {
+        [project] => Ok(project.key.clone()),
+        [] => Err("There are no projects yet - create one with `project create`.".into()),
+        _ => Err("There are several projects - specify one with `--project`.".into()),
+    }
+}
+
+/// Find the status of the workflow the user is referring to, failing if there is none.
+fn resolve_status(ticket_store: &TicketStore, input: &str) -> Result<Status, Box<dyn Error>> {
+    ticket_store.resolve_status(input).ok_or_else(|| {
+        format!(
+            "The status you specified is not valid. Valid values: {}.",
+            ticket_store
+                .statuses()
+                .iter()
+                .map(|s| s.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        )
+        .into()
+    })
+}
+
+/// Fails if nobody is registered in the store with the specified handle.
+fn ensure_user_exists(ticket_store: &TicketStore, user: &UserId) -> Result<(), Box<dyn Error>> {
+    match ticket_store.get_user(user) {
+        Some(_) => Ok(()),
+        None => Err(format!(
+            "There is no user with handle {} - register them with `user add`.",
+            user
+        )
+        .into()),
+    }
+}
+
+/// Warn about the `@mentions` of `text` that do not match any registered user: nobody is notified.
+fn warn_about_unknown_mentions(ticket_store: &TicketStore, text: &str) {
+    for user in mentions(text) {
+        if ticket_store.get_user(&user).is_none() {
+            println!(
+                "There is no user with handle {} - nobody was notified of the mention.",
+                user
+            );
+        }
+    }
+}
+
+/// The built-in or custom field with this name, failing if there is none.
+fn resolve_field(ticket_store: &TicketStore, name: &str) -> Result<Field, Box<dyn Error>> {
+    let custom_fields = ticket_store.custom_fields();
+    Field::resolve(name, &custom_fields).ok_or_else(|| {
+        format!(
+            "Unknown field {} - valid fields: {}.",
+            name,
+            Field::names(&custom_fields)
+        )
+        .into()
+    })
+}
+
+/// Sort keys typed on the command line, e.g. `priority:desc`. Keys are ascending by default.
+fn resolve_sort_keys(
+    ticket_store: &TicketStore,
+    keys: &[String],
+) -> Result<Vec<OrderBy>, Box<dyn Error>> {
+    let mut order_by = Vec::new();
+    for key in keys {
+        let (field, direction) = match key.split_once(':') {
+            Some((field, direction)) => (field, direction.to_lowercase()),
+            None => (key.as_str(), "asc".to_string()),
         };
-        Ok(status)
+        let descending = match direction.as_str() {
+            "asc" => false,
+            "desc" => true,
+            _ => return Err(format!("Invalid sort order {} - use asc or desc.", direction).into()),
+        };
+        order_by.push(OrderBy {
+            field: resolve_field(ticket_store, field)?,
+            descending,
+        });
+    }
+    Ok(order_by)
+}
+
+/// Validate the custom field values typed on the command line.
+fn custom_field_values(
+    ticket_store: &TicketStore,
+    values: Vec<CustomFieldValue>,
+) -> Result<BTreeMap<CustomFieldName, Option<String>>, Box<dyn Error>> {
+    let values = values.into_iter().map(|v| (v.name, v.value)).collect();
+    Ok(ticket_store.custom_field_values(values)?)
+}
+
+/// The query of a saved filter, failing if there is no filter with such a name.
+fn saved_query(ticket_store: &TicketStore, name: &FilterName) -> Result<String, Box<dyn Error>> {
+    match ticket_store.get_filter(name) {
+        Some(filter) => Ok(filter.query.clone()),
+        None => Err(format!("There is no filter named {}", name).into()),
+    }
+}
+
+/// The tickets a `bulk` command applies to, as typed on the command line.
+fn selection(target: &BulkTarget) -> Result<TicketSelection, Box<dyn Error>> {
+    match (&target.filter, &target.query) {
+        (Some(name), _) => Ok(TicketSelection::Filter(name.clone())),
+        (None, Some(query)) => Ok(TicketSelection::Query(query.clone())),
+        (None, None) if target.ticket_id.is_empty() => {
+            Err("Select tickets with --ticket-id, --filter or --query.".into())
+        }
+        (None, None) => Ok(TicketSelection::Ids(target.ticket_id.clone())),
+    }
+}
+
+/// What happened to the tickets of a `bulk` command.
+struct BulkReport {
+    dry_run: bool,
+    /// The number of tickets the operation failed for.
+    failed: usize,
+}
+
+/// Apply `operation` to each selected ticket, carrying on when it fails for one of them,
+/// and print what happened to each ticket.
+fn run_bulk(
+    ticket_store: &mut TicketStore,
+    config: &Config,
+    target: &BulkTarget,
+    mut operation: impl FnMut(&mut TicketStore, &TicketId) -> Result<String, String>,
+) -> Result<BulkReport, Box<dyn Error>> {
+    let ids = ticket_store.select(&selection(target)?, config.current_user.as_ref())?;
+    if target.dry_run {
+        println!("Dry run - the changes below will not be saved.");
+    }
+    let mut failed = 0;
+    for id in ids.iter() {
+        match operation(ticket_store, id) {
+            Ok(outcome) => println!("{}: {}", id, outcome),
+            Err(e) => {
+                failed += 1;
+                println!("{}: FAILED - {}", id, e);
+            }
+        }
+    }
+    println!(
+        "{} ticket(s) selected: {} succeeded, {} failed.",
+        ids.len(),
+        ids.len() - failed,
+        failed
+    );
+    Ok(BulkReport {
+        dry_run: target.dry_run,
+        failed,
+    })
+}
+
+/// The error a `bulk` command exits with when it failed for some tickets.
+fn bulk_failure(failed: usize) -> Box<dyn Error> {
+    format!("The bulk operation failed for {} ticket(s).", failed).into()
+}
+
+fn no_such_ticket() -> String {
+    "there is no such ticket".to_string()
+}
+
+/// The criteria of `filter save`, as a query. All of them must be met.
+struct FilterCriteria {
+    query: Option<String>,
+    project: Option<ProjectKey>,
+    status: Option<Status>,
+    priority: Vec<Priority>,
+    assignee: Option<String>,
+    label: Vec<Label>,
+    sort_by: Vec<OrderBy>,
+}
+
+impl FilterCriteria {
+    fn to_query(&self, ticket_store: &TicketStore) -> Result<Query, Box<dyn Error>> {
+        let compare = |field, value| Expression::Comparison {
+            field,
+            operator: Operator::Equals,
+            value,
+        };
+        let mut query = match &self.query {
+            Some(query) => ticket_store.parse_query(query)?,
+            None => Query {
+                filter: None,
+                order_by: Vec::new(),
+            },
+        };
+        let mut clauses: Vec<Expression> = query.filter.take().into_iter().collect();
+        if let Some(project) = &self.project {
+            clauses.push(compare(Field::Project, Value::Text(project.to_string())));
+        }
+        if let Some(status) = &self.status {
+            clauses.push(compare(Field::Status, Value::Text(status.to_string())));
+        }
+        if !self.priority.is_empty() {
+            clauses.push(Expression::In {
+                field: Field::Priority,
+                values: self
+                    .priority
+                    .iter()
+                    .map(|p| Value::Text(format!("{:?}", p)))
+                    .collect(),
+                negated: false,
+            });
+        }
+        if let Some(assignee) = &self.assignee {
+            let value = if assignee.eq_ignore_ascii_case("me") {
+                Value::CurrentUser
+            } else {
+                Value::Text(UserId::new(assignee.clone())?.to_string())
+            };
+            clauses.push(compare(Field::Assignee, value));
+        }
+        for label in self.label.iter() {
+            clauses.push(compare(Field::Labels, Value::Text(label.to_string())));
+        }
+        query.filter = clauses
+            .into_iter()
+            .reduce(|all, clause| Expression::And(Box::new(all), Box::new(clause)));
+        if !self.sort_by.is_empty() {
+            query.order_by = self.sort_by.clone();
+        }
+        if query.filter.is_none() && query.order_by.is_empty() {
+            return Err("A filter needs at least one criterion, e.g. --status or --query.".into());
+        }
+        Ok(query)
+    }
+}
+
+/// Print a ticket and, indented below it, those of its descendants that are part of `shown`.
+fn print_tree(
+    ticket_store: &TicketStore,
+    ticket: &Ticket,
+    shown: &HashSet<TicketId>,
+    depth: usize,
+) {
+    print!(
+        "{}{} [{:?}] {} ({})",
+        "    ".repeat(depth),
+        ticket.id,
+        ticket.kind,
+        ticket.title,
+        ticket.status
+    );
+    if ticket.kind == TicketKind::Epic {
+        if let Some((done, total)) = ticket_store.progress(&ticket.id) {
+            print!(" - {}/{} done", done, total);
+        }
+    }
+    println!();
+    for child in ticket_store.children(&ticket.id) {
+        if shown.contains(&child.id) {
+            print_tree(ticket_store, child, shown, depth + 1);
+        }
+    }
+}
+
+/// Print tickets as a table, one line per ticket, with a header naming the columns.
+/// Long values are cut so that lines stay readable.
+fn print_table(tickets: &[&Ticket], columns: &[Field]) {
+    const MAX_WIDTH: usize = 50;
+    let cut = |value: String| {
+        let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+        if value.chars().count() > MAX_WIDTH {
+            let cut: String = value.chars().take(MAX_WIDTH - 1).collect();
+            format!("{}…", cut)
+        } else {
+            value
+        }
+    };
+    let header: Vec<String> = columns.iter().map(|c| c.name().to_uppercase()).collect();
+    let rows: Vec<Vec<String>> = tickets
+        .iter()
+        .map(|t| columns.iter().map(|c| cut(c.value(t))).collect())
+        .collect();
+    let widths: Vec<usize> = (0..columns.len())
+        .map(|i| {
+            std::iter::once(&header)
+                .chain(rows.iter())
+                .map(|row| row[i].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    for row in std::iter::once(&header).chain(rows.iter()) {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect();
+        println!("{}", cells.join("  ").trim_end());
     }
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     // Parse the command-line arguments.
     let command = <Command as paw::ParseArgs>::parse_args()?;
+    let config = persistence::load_config();
     // Load the store from disk. If missing, a brand new one will be created.
     let mut ticket_store = persistence::load();
+    ticket_store.set_workflow(config.workflow.clone());
+    ticket_store.set_actor(config.current_user.clone());
+    // Remember the state of the store, to be able to undo the command.
+    let undoable = !matches!(command, Command::Undo | Command::Redo);
+    let checkpoint = ticket_store.checkpoint();
+    // Remember the last notification, to deliver the ones sent by the command.
+    let last_notification = ticket_store.last_notification_id();
+    let mut bulk_failed = 0;
     match command {
-        Command::Create { description, title } => {
+        Command::Create {
+            description,
+            title,
+            assignee,
+            priority,
+            kind,
+            parent,
+            project,
+            fields,
+        } => {
+            if let Some(assignee) = &assignee {
+                ensure_user_exists(&ticket_store, assignee)?;
+            }
+            let custom_fields = custom_field_values(&ticket_store, fields)?
+                .into_iter()
+                .filter_map(|(name, value)| Some((name, value?)))
+                .collect();
+            warn_about_unknown_mentions(&ticket_store, &description);
             let draft = TicketDraft {
+                project: resolve_project(&ticket_store, &config, project)?,
                 title: Title::new(title)?,
                 description,
+                priority: priority.unwrap_or_default(),
+                kind: kind.unwrap_or_default(),
+                parent,
+                reporter: config.current_user.clone(),
+                assignee,
+                custom_fields,
             };
-            ticket_store.create(draft);
+            let ticket_id = ticket_store.create(draft)?;
+            println!("Ticket {} was created.", ticket_id);
         }
         Command::Edit {
             ticket_id,
             title,
             description,
+            priority,
+            assignee,
+            unassign,
+            reporter,
+            parent,
+            no_parent,
+            estimate,
+            remaining,
+            fields,
         } => {
+            if let Some(assignee) = &assignee {
+                ensure_user_exists(&ticket_store, assignee)?;
+            }
+            if let Some(reporter) = &reporter {
+                ensure_user_exists(&ticket_store, reporter)?;
+            }
+            let custom_fields = custom_field_values(&ticket_store, fields)?;
+            if let Some(description) = &description {
+                warn_about_unknown_mentions(&ticket_store, description);
+            }
             let title = title.map(Title::new).transpose()?;
-            let ticket_patch = TicketPatch { title, description };
-            match ticket_store.update_ticket(ticket_id, ticket_patch) {
-                Some(_) => println!("Ticket {:?} was updated.", ticket_id),
+            let ticket_patch = TicketPatch {
+                title,
+                description,
+                priority,
+                reporter,
+                assignee,
+                estimate,
+                remaining,
+                custom_fields,
+            };
+            let updated = ticket_store.update_ticket(&ticket_id, ticket_patch);
+            if unassign {
+                ticket_store.unassign(&ticket_id);
+            }
+            if parent.is_some() || no_parent {
+                ticket_store.set_parent(&ticket_id, parent)?;
+            }
+            match updated {
+                Some(_) => println!("Ticket {} was updated.", ticket_id),
                 None => println!(
-                    "There was no ticket associated to the ticket id {:?}",
+                    "There was no ticket associated to the ticket id {}",
                     ticket_id
                 ),
             }
         }
-        Command::Delete { ticket_id } => match ticket_store.delete(ticket_id) {
+        Command::Delete { ticket_id } => match ticket_store.delete(&ticket_id) {
             Some(deleted_ticket) => println!(
-                "The following ticket has been deleted:\n{:?}",
-                deleted_ticket
+                "Ticket {} has been moved to the trash. Run `trash restore --ticket-id {}` to bring it back.",
+                deleted_ticket.ticket.id, deleted_ticket.ticket.id
+            ),
+            None => println!(
+                "There was no ticket associated to the ticket id {}",
+                ticket_id
+            ),
+        },
+        Command::List {
+            project,
+            priority,
+            assignee,
+            mine,
+            query,
+            filter,
+            sort_by,
+            limit,
+            offset,
+            columns,
+            tree,
+        } => {
+            let assignee = if mine {
+                Some(current_user(&config)?)
+            } else {
+                assignee
+            };
+            let query = match filter {
+                Some(name) => Some(saved_query(&ticket_store, &name)?),
+                None => query,
+            };
+            let mut tickets = match (&query, &assignee) {
+                (Some(query), _) => {
+                    let query = ticket_store.parse_query(query)?;
+                    ticket_store.search(&query, config.current_user.as_ref())
+                }
+                (None, Some(assignee)) => ticket_store.list_by_assignee(assignee),
+                (None, None) => ticket_store.list(),
+            };
+            if query.is_some() {
+                if let Some(assignee) = &assignee {
+                    tickets.retain(|t| t.assignee.as_ref() == Some(assignee));
+                }
+            }
+            if let Some(project) = &project {
+                tickets.retain(|t| &t.id.project == project);
+            }
+            if !priority.is_empty() {
+                tickets.retain(|t| priority.contains(&t.priority));
+            }
+            let columns = columns
+                .iter()
+                .map(|name| resolve_field(&ticket_store, name))
+                .collect::<Result<Vec<Field>, _>>()?;
+            if !sort_by.is_empty() {
+                tickets = TicketStore::sort_by(tickets, &resolve_sort_keys(&ticket_store, &sort_by)?);
+            }
+            let total = tickets.len();
+            let tickets: Vec<&Ticket> = tickets
+                .into_iter()
+                .skip(offset)
+                .take(limit.map_or(usize::MAX, NonZeroUsize::get))
+                .collect();
+            let listed = tickets.len();
+            if tree {
+                let shown: HashSet<TicketId> = tickets.iter().map(|t| t.id.clone()).collect();
+                let roots = tickets.iter().filter(|t| {
+                    t.parent
+                        .as_ref()
+                        .filter(|parent| shown.contains(parent))
+                        .is_none()
+                });
+                for root in roots {
+                    print_tree(&ticket_store, root, &shown, 0);
+                }
+            } else if !columns.is_empty() {
+                print_table(&tickets, &columns);
+            } else {
+                let ticket_list = tickets
+                    .into_iter()
+                    .map(|t| format!("{}", t))
+                    .collect::<Vec<String>>()
+                    .join("\n\n");
+                println!("{}", ticket_list);
+            }
+            if offset + listed < total {
+                println!(
+                    "\nTickets {} to {} of {} - use --offset {} to see the next ones.",
+                    offset + 1,
+                    offset + listed,
+                    total,
+                    offset + listed
+                );
+            }
+        }
+        Command::Move {
+            ticket_id,
+            status: None,
+            ..
+        } => match ticket_store.next_statuses(&ticket_id) {
+            Some(statuses) => println!(
+                "Ticket {} can be moved to: {}",
+                ticket_id,
+                statuses
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            None => println!(
+                "There was no ticket associated to the ticket id {}",
+                ticket_id
+            ),
+        },
+        Command::Move {
+            ticket_id,
+            status: Some(status),
+            comment,
+        } => {
+            let status = resolve_status(&ticket_store, &status)?;
+            let comment = comment.map(Comment::new).transpose()?;
+            ticket_store.update_ticket_status(&ticket_id, status.clone(), comment)?;
+            println!("Status of ticket {} was updated to {}", ticket_id, status);
+        }
+        Command::Undo => {
+            let mut undo_stack = persistence::load_undo_stack();
+            match undo_stack.undo() {
+                Some(step) => {
+                    ticket_store.undo(step);
+                    println!("Undid `{}`", step.command);
+                }
+                None => println!("There is nothing to undo"),
+            }
+            persistence::save_undo_stack(&undo_stack);
+        }
+        Command::Redo => {
+            let mut undo_stack = persistence::load_undo_stack();
+            match undo_stack.redo() {
+                Some(step) => {
+                    ticket_store.redo(step);
+                    println!("Redid `{}`", step.command);
+                }
+                None => println!("There is nothing to redo"),
+            }
+            persistence::save_undo_stack(&undo_stack);
+        }
+        Command::Show { ticket_id } => match ticket_store.get(&ticket_id) {
+            Some(ticket) => println!("{}", ticket),
+            None => println!(
+                "There was no ticket associated to the ticket id {}",
+                ticket_id
             ),
+        },
+        Command::Search { text, limit } => {
+            let hits = ticket_store.search_text(&text);
+            if hits.is_empty() {
+                println!("No ticket matches {:?}", text);
+            }
+            for hit in hits.into_iter().take(limit) {
+                println!("{}", hit);
+            }
+        }
+        Command::History { ticket_id } => match ticket_store.get(&ticket_id) {
+            Some(ticket) => {
+                for change in ticket.history.iter() {
+                    println!("{}", change);
+                }
+            }
             None => println!(
-                "There was no ticket associated to the ticket id {:?}",
+                "There was no ticket associated to the ticket id {}",
                 ticket_id
             ),
         },
-        Command::List => {
+        Command::Comment {
+            ticket_id,
+            comment,
+            reply_to,
+        } => {
+            let new_comment = Comment::new(comment)?;
+            warn_about_unknown_mentions(&ticket_store, &new_comment.to_string());
+            match ticket_store.add_comment_to_ticket(&ticket_id, new_comment, reply_to) {
+                Ok(comment_id) => println!(
+                    "Comment {} has been added to ticket {}",
+                    comment_id, ticket_id
+                ),
+                Err(e) => println!("{}", e),
+            }
+        }
+        Command::EditComment {
+            ticket_id,
+            comment_id,
+            comment,
+        } => {
+            let body = Comment::new(comment)?;
+            warn_about_unknown_mentions(&ticket_store, &body.to_string());
+            match ticket_store.edit_comment(&ticket_id, comment_id, body) {
+                Some(()) => println!("Comment {} of ticket {} was updated.", comment_id, ticket_id),
+                None => println!(
+                    "There was no comment {} on the ticket {}",
+                    comment_id, ticket_id
+                ),
+            }
+        }
+        Command::DeleteComment {
+            ticket_id,
+            comment_id,
+        } => match ticket_store.delete_comment(&ticket_id, comment_id) {
+            Some(comment) => println!(
+                "The following comment has been deleted from ticket {}:\n{}",
+                ticket_id, comment
+            ),
+            None => println!(
+                "There was no comment {} on the ticket {}",
+                comment_id, ticket_id
+            ),
+        },
+        Command::LogWork {
+            ticket_id,
+            time,
+            started,
+            note,
+            remaining,
+            author,
+        } => {
+            let author = match author {
+                Some(author) => author,
+                None => current_user(&config)?,
+            };
+            ensure_user_exists(&ticket_store, &author)?;
+            let started_at = match started {
+                Some(started) => Local
+                    .from_local_datetime(&started)
+                    .earliest()
+                    .ok_or("The start of the work is not a valid local time")?
+                    .with_timezone(&Utc),
+                None => Utc::now(),
+            };
+            let worklog = Worklog {
+                author,
+                time_spent: time,
+                started_at,
+                note,
+            };
+            ticket_store.log_work(&ticket_id, worklog, remaining)?;
+            println!("Logged {} on ticket {}", time, ticket_id);
+        }
+        Command::Timesheet { from, to } => {
+            let to = to.unwrap_or_else(|| Local::today().naive_local());
+            let from = from.unwrap_or_else(|| to - Duration::days(6));
+            for (author, days) in ticket_store.timesheet(from, to, &Local) {
+                println!("{}", author);
+                let mut total = WorkDuration::default();
+                for (day, time_spent) in days {
+                    println!("    {}  {}", day, time_spent);
+                    total += time_spent;
+                }
+                println!("    Total       {}", total);
+            }
+        }
+        Command::Link {
+            ticket_id,
+            kind,
+            target,
+        } => {
+            ticket_store.link(&ticket_id, kind, &target)?;
+            println!("Ticket {} {} ticket {}", ticket_id, kind, target);
+        }
+        Command::Unlink {
+            ticket_id,
+            kind,
+            target,
+        } => match ticket_store.unlink(&ticket_id, kind, &target) {
+            Some(_) => println!("Ticket {} no longer {} ticket {}", ticket_id, kind, target),
+            None => println!(
+                "There was no link \"{} {}\" on ticket {}",
+                kind, target, ticket_id
+            ),
+        },
+        Command::Project(ProjectCommand::Create { key, name, lead }) => {
+            if let Some(lead) = &lead {
+                ensure_user_exists(&ticket_store, lead)?;
+            }
+            let project = Project { key, name, lead };
+            let description = project.to_string();
+            match ticket_store.create_project(project) {
+                Some(_) => println!("Project {} has been created.", description),
+                None => println!("There is already a project with key {}", description),
+            }
+        }
+        Command::Project(ProjectCommand::List) => {
+            for project in ticket_store.list_projects() {
+                println!("{}", project);
+            }
+        }
+        Command::User(UserCommand::Add { handle, name }) => {
+            let user = User {
+                id: handle,
+                display_name: name,
+            };
+            let message = format!("{}", user);
+            match ticket_store.add_user(user) {
+                None => println!("User {} has been registered.", message),
+                Some(_) => println!("User {} has been updated.", message),
+            }
+        }
+        Command::User(UserCommand::List) => {
+            for user in ticket_store.list_users() {
+                println!("{}", user);
+            }
+        }
+        Command::Label(LabelCommand::Add { ticket_id, label }) => {
+            match ticket_store.add_label(&ticket_id, label.clone()) {
+                Some(true) => println!("Label {} has been added to ticket {}", label, ticket_id),
+                Some(false) => println!("Ticket {} is already labelled {}", ticket_id, label),
+                None => println!(
+                    "There was no ticket associated to the ticket id {}",
+                    ticket_id
+                ),
+            }
+        }
+        Command::Label(LabelCommand::Remove { ticket_id, label }) => {
+            match ticket_store.remove_label(&ticket_id, &label) {
+                Some(true) => {
+                    println!("Label {} has been removed from ticket {}", label, ticket_id)
+                }
+                Some(false) => println!("Ticket {} is not labelled {}", ticket_id, label),
+                None => println!(
+                    "There was no ticket associated to the ticket id {}",
+                    ticket_id
+                ),
+            }
+        }
+        Command::Label(LabelCommand::List { label: Some(label) }) => {
             let ticket_list = ticket_store
-                .list()
+                .tickets_with_label(&label)
                 .into_iter()
                 .map(|t| format!("{}", t))
                 .collect::<Vec<String>>()
                 .join("\n\n");
             println!("{}", ticket_list);
         }
-        Command::Move { ticket_id, status } => {
-            match ticket_store.update_ticket_status(ticket_id, status) {
-                Some(_) => println!(
-                    "Status of ticket {:?} was updated to {:?}",
-                    ticket_id, status
+        Command::Label(LabelCommand::List { label: None }) => {
+            for (label, n_tickets) in ticket_store.labels() {
+                println!("{} ({} tickets)", label, n_tickets);
+            }
+        }
+        Command::Sprint(SprintCommand::Create {
+            name,
+            goal,
+            start,
+            end,
+        }) => {
+            let start = start.unwrap_or_else(|| Local::today().naive_local());
+            let draft = SprintDraft {
+                name,
+                goal,
+                start,
+                end: end.unwrap_or_else(|| start + Duration::days(13)),
+            };
+            let sprint = ticket_store.create_sprint(draft)?;
+            println!("Sprint {} was created.", sprint);
+        }
+        Command::Sprint(SprintCommand::Add { ticket_id, sprint }) => {
+            ticket_store.add_to_sprint(&ticket_id, sprint)?;
+            println!("Ticket {} has been added to sprint {}", ticket_id, sprint);
+        }
+        Command::Sprint(SprintCommand::Remove { ticket_id }) => {
+            match ticket_store.remove_from_sprint(&ticket_id) {
+                Some(_) => println!("Ticket {} has been moved to the backlog", ticket_id),
+                None => println!(
+                    "There was no ticket associated to the ticket id {}",
+                    ticket_id
                 ),
+            }
+        }
+        Command::Sprint(SprintCommand::Start { sprint }) => {
+            ticket_store.start_sprint(sprint)?;
+            println!("Sprint {} has been started.", sprint);
+        }
+        Command::Sprint(SprintCommand::Complete { sprint, to }) => {
+            let carry_over = match to {
+                Some(next) => CarryOver::Sprint(next),
+                None => CarryOver::Backlog,
+            };
+            let unfinished = ticket_store.complete_sprint(sprint, carry_over)?;
+            println!("Sprint {} has been completed.", sprint);
+            if !unfinished.is_empty() {
+                let unfinished: Vec<String> = unfinished.iter().map(|t| t.to_string()).collect();
+                match to {
+                    Some(next) => print!("Carried over to sprint {}: ", next),
+                    None => print!("Moved to the backlog: "),
+                }
+                println!("{}", unfinished.join(", "));
+            }
+        }
+        Command::Sprint(SprintCommand::List) => {
+            for sprint in ticket_store.list_sprints() {
+                println!("{}", sprint);
+            }
+        }
+        Command::Sprint(SprintCommand::Show { sprint: sprint_id }) => {
+            match (
+                ticket_store.get_sprint(sprint_id),
+                ticket_store.sprint_summary(sprint_id),
+            ) {
+                (Some(sprint), Some(summary)) => {
+                    println!("{}\n{}\nTickets:", sprint, summary);
+                    for ticket in ticket_store.sprint_tickets(sprint_id) {
+                        print_tree(&ticket_store, ticket, &HashSet::new(), 1);
+                    }
+                }
+                _ => println!(
+                    "There was no sprint associated to the sprint id {}",
+                    sprint_id
+                ),
+            }
+        }
+        Command::Sprint(SprintCommand::Backlog) => {
+            for ticket in ticket_store.backlog() {
+                print_tree(&ticket_store, ticket, &HashSet::new(), 0);
+            }
+        }
+        Command::Watch { ticket_id, user } => {
+            let user = match user {
+                Some(user) => user,
+                None => current_user(&config)?,
+            };
+            ensure_user_exists(&ticket_store, &user)?;
+            match ticket_store.watch(&ticket_id, user.clone()) {
+                Some(()) => println!("{} is watching ticket {}", user, ticket_id),
                 None => println!(
-                    "There was no ticket associated to the ticket id {:?}",
+                    "There was no ticket associated to the ticket id {}",
                     ticket_id
                 ),
             }
         }
-        Command::Comment { ticket_id, comment } => {
-            let new_comment = Comment::new(comment)?;
-            match ticket_store.add_comment_to_ticket(ticket_id, new_comment) {
-                Some(_) => println!("Comment has been added to ticket {:?}", ticket_id),
+        Command::Unwatch { ticket_id, user } => {
+            let user = match user {
+                Some(user) => user,
+                None => current_user(&config)?,
+            };
+            match ticket_store.unwatch(&ticket_id, &user) {
+                Some(()) => println!("{} is not watching ticket {} anymore", user, ticket_id),
+                None => println!("{} was not watching ticket {}", user, ticket_id),
+            }
+        }
+        Command::Attach { ticket_id, file } => {
+            let name = file
+                .file_name()
+                .and_then(|name| name.to_str())
+                .ok_or("The file to attach must have a valid name")?
+                .to_string();
+            if ticket_store.get(&ticket_id).is_none() {
+                println!(
+                    "There was no ticket associated to the ticket id {}",
+                    ticket_id
+                );
+            } else {
+                let blob = persistence::save_blob(&file)?;
+                ticket_store.attach(&ticket_id, name.clone(), blob);
+                println!("{} was attached to ticket {}", name, ticket_id);
+            }
+        }
+        Command::Attachment(AttachmentCommand::List { ticket_id }) => {
+            match ticket_store.get(&ticket_id) {
+                Some(ticket) => {
+                    for attachment in ticket.attachments.iter() {
+                        println!("{}", attachment);
+                    }
+                }
                 None => println!(
-                    "There was no ticket associated to the ticket id {:?}",
+                    "There was no ticket associated to the ticket id {}",
                     ticket_id
                 ),
             }
         }
+        Command::Attachment(AttachmentCommand::Extract {
+            ticket_id,
+            name,
+            to,
+        }) => {
+            let attachment = ticket_store
+                .get(&ticket_id)
+                .and_then(|t| t.attachments.iter().find(|a| a.name == name));
+            match attachment {
+                Some(attachment) => {
+                    let destination = to.unwrap_or_else(|| PathBuf::from(&attachment.name));
+                    persistence::extract_blob(&attachment.blob, &destination)?;
+                    println!("{} was extracted to {:?}", name, destination);
+                }
+                None => println!("There is no attachment {} on ticket {}", name, ticket_id),
+            }
+        }
+        Command::Attachment(AttachmentCommand::Gc) => {
+            // Files referred to by a command that can still be undone or redone are kept too.
+            let undo_stack = persistence::load_undo_stack();
+            let mut referenced = ticket_store.attachment_hashes();
+            referenced.extend(
+                undo_stack
+                    .tickets()
+                    .flat_map(|t| t.attachments.iter())
+                    .map(|a| a.blob.hash.as_str()),
+            );
+            let removed = persistence::collect_garbage(&referenced)?;
+            println!("{} unused attachment file(s) were deleted.", removed.len());
+        }
+        Command::Inbox(InboxCommand::List { all }) => {
+            let user = current_user(&config)?;
+            for notification in ticket_store.inbox(&user) {
+                if all || !notification.read {
+                    println!("{}", notification);
+                }
+            }
+        }
+        Command::Inbox(InboxCommand::Read {
+            notification_id: Some(notification_id),
+            all: false,
+        }) => {
+            let user = current_user(&config)?;
+            match ticket_store.mark_as_read(&user, notification_id) {
+                Some(()) => println!("Notification {} was marked as read.", notification_id),
+                None => println!("There is no notification {} in your inbox", notification_id),
+            }
+        }
+        Command::Inbox(InboxCommand::Read { .. }) => {
+            let user = current_user(&config)?;
+            let read = ticket_store.mark_all_as_read(&user);
+            println!("{} notifications were marked as read.", read);
+        }
+        Command::Filter(FilterCommand::Save {
+            name,
+            description,
+            query,
+            project,
+            status,
+            priority,
+            assignee,
+            label,
+            sort_by,
+            replace,
+        }) => {
+            if !replace && ticket_store.get_filter(&name).is_some() {
+                return Err(format!(
+                    "There is already a filter named {} - use --replace to replace it.",
+                    name
+                )
+                .into());
+            }
+            let status = match status {
+                Some(status) => Some(resolve_status(&ticket_store, &status)?),
+                None => None,
+            };
+            let criteria = FilterCriteria {
+                query,
+                project,
+                status,
+                priority,
+                assignee,
+                label,
+                sort_by: resolve_sort_keys(&ticket_store, &sort_by)?,
+            };
+            let filter = SavedFilter {
+                name: name.clone(),
+                description,
+                owner: config.current_user.clone(),
+                query: criteria.to_query(&ticket_store)?.to_string(),
+                saved_at: Utc::now(),
+            };
+            println!("Filter {} has been saved: {}", name, filter.query);
+            ticket_store.save_filter(filter);
+        }
+        Command::Filter(FilterCommand::List) => {
+            for filter in ticket_store.list_filters() {
+                println!("{}", filter);
+            }
+        }
+        Command::Filter(FilterCommand::Delete { name }) => {
+            match ticket_store.delete_filter(&name) {
+                Some(_) => println!("Filter {} has been deleted.", name),
+                None => println!("There is no filter named {}", name),
+            }
+        }
+        Command::Filter(FilterCommand::Export { to, name }) => {
+            let mut filters = Vec::new();
+            for name in name.iter() {
+                match ticket_store.get_filter(name) {
+                    Some(filter) => filters.push(filter),
+                    None => return Err(format!("There is no filter named {}", name).into()),
+                }
+            }
+            if name.is_empty() {
+                filters = ticket_store.list_filters();
+            }
+            persistence::export_filters(&filters, &to)?;
+            println!("{} filter(s) were exported to {:?}", filters.len(), to);
+        }
+        Command::Filter(FilterCommand::Import { from, replace }) => {
+            let mut imported = 0;
+            for filter in persistence::import_filters(&from)? {
+                if let Err(e) = ticket_store.parse_query(&filter.query) {
+                    println!("Filter {} was skipped: {}", filter.name, e);
+                } else if !replace && ticket_store.get_filter(&filter.name).is_some() {
+                    println!(
+                        "Filter {} was skipped: there is already a filter with this name.",
+                        filter.name
+                    );
+                } else {
+                    ticket_store.save_filter(filter);
+                    imported += 1;
+                }
+            }
+            println!("{} filter(s) were imported.", imported);
+        }
+        Command::Bulk(bulk) => {
+            let report = match bulk {
+                BulkCommand::Move {
+                    target,
+                    status,
+                    comment,
+                } => {
+                    let status = resolve_status(&ticket_store, &status)?;
+                    let comment = comment.map(Comment::new).transpose()?;
+                    run_bulk(&mut ticket_store, &config, &target, |store, id| {
+                        store
+                            .update_ticket_status(id, status.clone(), comment.clone())
+                            .map(|()| format!("moved to {}", status))
+                            .map_err(|e| e.to_string())
+                    })?
+                }
+                BulkCommand::Label {
+                    target,
+                    label,
+                    remove: false,
+                } => run_bulk(&mut ticket_store, &config, &target, |store, id| {
+                    match store.add_label(id, label.clone()) {
+                        Some(true) => Ok(format!("labelled {}", label)),
+                        Some(false) => Ok(format!("already labelled {}", label)),
+                        None => Err(no_such_ticket()),
+                    }
+                })?,
+                BulkCommand::Label {
+                    target,
+                    label,
+                    remove: true,
+                } => run_bulk(&mut ticket_store, &config, &target, |store, id| {
+                    match store.remove_label(id, &label) {
+                        Some(true) => Ok(format!("label {} removed", label)),
+                        Some(false) => Ok(format!("not labelled {}", label)),
+                        None => Err(no_such_ticket()),
+                    }
+                })?,
+                BulkCommand::Assign {
+                    target,
+                    assignee: Some(assignee),
+                    ..
+                } => {
+                    ensure_user_exists(&ticket_store, &assignee)?;
+                    run_bulk(&mut ticket_store, &config, &target, |store, id| {
+                        store
+                            .assign(id, assignee.clone())
+                            .map(|()| format!("assigned to {}", assignee))
+                            .ok_or_else(no_such_ticket)
+                    })?
+                }
+                BulkCommand::Assign { target, .. } => {
+                    run_bulk(&mut ticket_store, &config, &target, |store, id| {
+                        store
+                            .unassign(id)
+                            .map(|()| "unassigned".to_string())
+                            .ok_or_else(no_such_ticket)
+                    })?
+                }
+                BulkCommand::Delete { target } => {
+                    run_bulk(&mut ticket_store, &config, &target, |store, id| {
+                        store
+                            .delete(id)
+                            .map(|_| "moved to the trash".to_string())
+                            .ok_or_else(no_such_ticket)
+                    })?
+                }
+            };
+            // The changes were made in memory, to check they are valid: leave without saving them.
+            if report.dry_run {
+                return match report.failed {
+                    0 => Ok(()),
+                    failed => Err(bulk_failure(failed)),
+                };
+            }
+            // The changes that succeeded are saved, but the command still fails.
+            bulk_failed = report.failed;
+        }
+        Command::CustomField(CustomFieldCommand::Add {
+            name,
+            kind,
+            required,
+            allowed_value,
+        }) => {
+            let field = CustomField {
+                name,
+                kind,
+                required,
+                allowed_values: allowed_value,
+            };
+            let description = field.to_string();
+            ticket_store.define_custom_field(field)?;
+            println!("Custom field {} has been added.", description);
+        }
+        Command::CustomField(CustomFieldCommand::List) => {
+            for field in ticket_store.custom_fields() {
+                println!("{}", field);
+            }
+        }
+        Command::CustomField(CustomFieldCommand::Remove { name }) => {
+            match ticket_store.remove_custom_field(&name) {
+                Some(_) => println!("Custom field {} has been removed.", name),
+                None => println!("There is no custom field named {}", name),
+            }
+        }
+        Command::Trash(TrashCommand::List) => {
+            for deleted_ticket in ticket_store.trash() {
+                println!("{}", deleted_ticket);
+            }
+        }
+        Command::Trash(TrashCommand::Restore { ticket_id }) => {
+            match ticket_store.restore(&ticket_id) {
+                Some(()) => println!("Ticket {} was restored.", ticket_id),
+                None => println!("There was no ticket {} in the trash", ticket_id),
+            }
+        }
+        Command::Trash(TrashCommand::Purge { older_than }) => {
+            let purged = ticket_store.purge(older_than);
+            println!("{} tickets were deleted for good.", purged.len());
+            for ticket_id in purged {
+                println!("{}", ticket_id);
+            }
+        }
+    }
+    if undoable {
+        let command_line: Vec<String> = std::env::args().skip(1).collect();
+        if let Some(step) = ticket_store.changes_since(&checkpoint, command_line.join(" ")) {
+            let mut undo_stack = persistence::load_undo_stack();
+            undo_stack.push(step);
+            persistence::save_undo_stack(&undo_stack);
+        }
     }
     // Save the store state to disk after we have completed our action.
-    persistence::save(&ticket_store);
+    persistence::save(&mut ticket_store, &config.storage);
+    // The command succeeded even if notifications cannot be delivered: they are in the inboxes anyway.
+    if let Err(e) = outbox::deliver(&ticket_store, last_notification, &config.notifications) {
+        println!("Failed to deliver notifications: {}", e);
+    }
+    if bulk_failed > 0 {
+        return Err(bulk_failure(bulk_failed));
+    }
     Ok(())
 }
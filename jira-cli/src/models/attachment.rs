@@ -0,0 +1,97 @@
+use crate::models::UserId;
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fmt::Formatter;
+
+#[derive(PartialEq, Debug, Clone, Hash, Eq, Serialize, Deserialize)]
+/// The content of a file saved in the attachment directory, named after its hash:
+/// files with the same content are only saved once.
+pub struct Blob {
+    /// The SHA-256 hash of the content, in hexadecimal.
+    pub hash: String,
+    /// The size of the content, in bytes.
+    pub size: u64,
+}
+
+#[derive(PartialEq, Debug, Clone, Hash, Eq, Serialize, Deserialize)]
+/// A file attached to a [Ticket](crate::models::Ticket), e.g. a log or a screenshot.
+pub struct Attachment {
+    /// The name of the file, unique within the ticket.
+    pub name: String,
+    #[serde(flatten)]
+    pub blob: Blob,
+    /// The type of the content, guessed from the name of the file, e.g. `image/png`.
+    pub mime_type: String,
+    pub attached_at: DateTime<Utc>,
+    /// Who attached the file, if known.
+    pub attached_by: Option<UserId>,
+}
+
+impl std::fmt::Display for Attachment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(
+            f,
+            "{} ({}, {} bytes) attached {} by {}",
+            self.name,
+            self.mime_type,
+            self.blob.size,
+            self.attached_at
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M"),
+            self.attached_by
+                .as_ref()
+                .map_or("someone".to_string(), |u| u.to_string())
+        )
+    }
+}
+
+/// Guess the type of the content of a file from its extension,
+/// falling back to `application/octet-stream` for unknown extensions.
+pub fn mime_type(name: &str) -> &'static str {
+    let extension = match name.rsplit_once('.') {
+        Some((_, extension)) => extension.to_lowercase(),
+        None => return "application/octet-stream",
+    };
+    match extension.as_str() {
+        "txt" | "log" => "text/plain",
+        "md" => "text/markdown",
+        "csv" => "text/csv",
+        "html" | "htm" => "text/html",
+        "json" => "application/json",
+        "yaml" | "yml" => "application/yaml",
+        "xml" => "application/xml",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "mp4" => "video/mp4",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod attachment_tests {
+    use crate::models::mime_type;
+
+    #[test]
+    fn mime_types_are_guessed_from_the_extension() {
+        //arrange
+        //act
+        let types = vec![
+            mime_type("server.log"),
+            mime_type("Screenshot.PNG"),
+            mime_type("core"),
+        ];
+
+        //assert
+        assert_eq!(
+            types,
+            vec!["text/plain", "image/png", "application/octet-stream"]
+        )
+    }
+}
@@ -1,3 +1,5 @@
+use crate::models::{TicketId, UserId};
+use chrono::{DateTime, Local, TimeZone, Utc};
 use serde::{Deserialize, Serialize};
 use std::error::Error;
 use std::fmt;
@@ -56,9 +58,107 @@ impl std::fmt::Display for Comment {
     }
 }
 
+/// The id of a [TicketComment], unique within its [Ticket](crate::models::Ticket).
+pub type CommentId = u64;
+
+#[derive(PartialEq, Debug, Clone, Hash, Eq, Serialize, Deserialize)]
+/// A [Comment] posted on a [Ticket](crate::models::Ticket), with who wrote it and when.
+///
+/// Comments saved before they had an author and timestamps are read with an id of 0
+/// and are numbered by [upgrade](crate::store::TicketStore::upgrade).
+pub struct TicketComment {
+    #[serde(default)]
+    pub id: CommentId,
+    #[serde(flatten)]
+    pub body: Comment,
+    /// The [User](crate::models::User) who posted the comment, if known.
+    #[serde(default)]
+    pub author: Option<UserId>,
+    /// The comment of the same ticket this one replies to, `None` if it starts a new thread.
+    #[serde(default)]
+    pub reply_to: Option<CommentId>,
+    #[serde(default = "unknown_time")]
+    pub created_at: DateTime<Utc>,
+    /// When the comment was last edited, `None` if it never was.
+    #[serde(default)]
+    pub edited_at: Option<DateTime<Utc>>,
+    /// The previous versions of the comment, the oldest first.
+    #[serde(default)]
+    pub revisions: Vec<CommentRevision>,
+}
+
+#[derive(PartialEq, Debug, Clone, Hash, Eq, Serialize, Deserialize)]
+/// A version of a [TicketComment] that was replaced by an edit.
+pub struct CommentRevision {
+    pub body: Comment,
+    pub replaced_at: DateTime<Utc>,
+    /// Who edited the comment, if known.
+    pub replaced_by: Option<UserId>,
+}
+
+/// Error if a comment cannot be posted on a ticket
+#[derive(PartialEq, Debug, Clone)]
+pub enum ReplyError {
+    /// There is no ticket with this id in the store.
+    TicketNotFound(TicketId),
+    /// The comment replied to is not on the ticket.
+    CommentNotFound {
+        ticket: TicketId,
+        comment: CommentId,
+    },
+}
+
+impl fmt::Display for ReplyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplyError::TicketNotFound(id) => {
+                write!(f, "There was no ticket associated to the ticket id {}", id)
+            }
+            ReplyError::CommentNotFound { ticket, comment } => {
+                write!(
+                    f,
+                    "There was no comment {} on the ticket {}",
+                    comment, ticket
+                )
+            }
+        }
+    }
+}
+
+impl Error for ReplyError {}
+
+/// The creation time of comments saved before it was recorded.
+fn unknown_time() -> DateTime<Utc> {
+    Utc.timestamp(0, 0)
+}
+
+impl std::fmt::Display for TicketComment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(
+            f,
+            "[{}] {} {}",
+            self.id,
+            self.author
+                .as_ref()
+                .map_or("someone".to_string(), |a| a.to_string()),
+            self.created_at
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M")
+        )?;
+        if let Some(edited_at) = self.edited_at {
+            write!(
+                f,
+                " (edited {})",
+                edited_at.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+            )?;
+        }
+        write!(f, ": {}", self.body)
+    }
+}
+
 #[cfg(test)]
 mod comment_tests {
-    use crate::models::Comment;
+    use crate::models::{Comment, TicketComment};
 
     #[test]
     fn creating_empty_comment_should_fail() {
@@ -68,4 +168,15 @@ mod comment_tests {
         // assert
         assert!(new_comment.is_err());
     }
+
+    #[test]
+    fn comments_saved_as_text_can_be_read() {
+        // arrange
+        let saved = "comment: Looks good to me";
+        // act
+        let comment: TicketComment = serde_yaml::from_str(saved).unwrap();
+        // assert
+        assert_eq!(comment.id, 0);
+        assert_eq!(comment.body.to_string(), "Looks good to me");
+    }
 }
@@ -0,0 +1,311 @@
+use crate::models::UserId;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::fmt::Formatter;
+
+#[derive(PartialEq, Debug, Clone, Hash, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+/// The name of a [CustomField](CustomField), e.g. `customer` or `story-points`.
+/// Wraps a string and checks that it is a valid name when set, deserialization included.
+pub struct CustomFieldName {
+    name: String,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+/// Error if a custom field name cannot be created
+pub struct CustomFieldNameError {
+    details: String,
+}
+
+/// Sets the error message for a custom field name if it cannot be created
+impl CustomFieldNameError {
+    fn new(msg: &str) -> CustomFieldNameError {
+        CustomFieldNameError {
+            details: msg.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for CustomFieldNameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.details)
+    }
+}
+
+impl Error for CustomFieldNameError {
+    fn description(&self) -> &str {
+        &self.details
+    }
+}
+
+impl CustomFieldName {
+    /// Creates a name for a [CustomField](CustomField)
+    /// results in a [CustomFieldNameError](CustomFieldNameError) if the string passed in is empty,
+    /// does not start with a letter or contains characters other than letters, digits, `-` and `_`.
+    /// Names are case-insensitive.
+    pub fn new(name: String) -> Result<CustomFieldName, CustomFieldNameError> {
+        if !name.chars().next().is_some_and(char::is_alphabetic) {
+            Err(CustomFieldNameError::new(
+                "Custom field name must start with a letter",
+            ))
+        } else if !name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        {
+            Err(CustomFieldNameError::new(
+                "Custom field name can only contain letters, digits, '-' and '_'",
+            ))
+        } else {
+            Ok(CustomFieldName {
+                name: name.to_lowercase(),
+            })
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.name
+    }
+}
+
+impl TryFrom<String> for CustomFieldName {
+    type Error = CustomFieldNameError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        CustomFieldName::new(value)
+    }
+}
+
+impl From<CustomFieldName> for String {
+    fn from(value: CustomFieldName) -> Self {
+        value.name
+    }
+}
+
+impl std::fmt::Display for CustomFieldName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// The type of the values of a [CustomField](CustomField).
+#[derive(PartialEq, Debug, Copy, Clone, Hash, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CustomFieldKind {
+    /// Free text, e.g. a customer name.
+    Text,
+    /// A decimal number, e.g. story points.
+    Number,
+    /// A calendar date, written `YYYY-MM-DD`.
+    Date,
+    /// One of the [allowed values](CustomField::allowed_values) of the field, e.g. an environment.
+    Enum,
+    /// The handle of a registered [User](crate::models::User).
+    User,
+}
+
+impl CustomFieldKind {
+    /// Compare two valid values of this kind: numbers and dates in their natural order,
+    /// other values alphabetically, ignoring case.
+    pub fn compare(self, a: &str, b: &str) -> Ordering {
+        match self {
+            CustomFieldKind::Number => match (a.parse::<f64>(), b.parse::<f64>()) {
+                (Ok(a), Ok(b)) => a.total_cmp(&b),
+                _ => a.cmp(b),
+            },
+            // Dates are saved as `YYYY-MM-DD`: their alphabetical order is chronological.
+            CustomFieldKind::Date => a.cmp(b),
+            _ => a.to_lowercase().cmp(&b.to_lowercase()),
+        }
+    }
+
+    /// Whether values of this kind can be compared with `<` and `>` in queries.
+    pub fn is_ordered(self) -> bool {
+        matches!(self, CustomFieldKind::Number | CustomFieldKind::Date)
+    }
+}
+
+impl std::fmt::Display for CustomFieldKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        let kind = match self {
+            CustomFieldKind::Text => "text",
+            CustomFieldKind::Number => "number",
+            CustomFieldKind::Date => "date",
+            CustomFieldKind::Enum => "enum",
+            CustomFieldKind::User => "user",
+        };
+        write!(f, "{}", kind)
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+/// An extra field teams can set on tickets, on top of the built-in ones, e.g. `story-points`.
+pub struct CustomField {
+    pub name: CustomFieldName,
+    pub kind: CustomFieldKind,
+    /// Whether new tickets must have a value for the field.
+    #[serde(default)]
+    pub required: bool,
+    /// The values an [enum](CustomFieldKind::Enum) field can take, e.g. `staging` and `production`.
+    #[serde(default)]
+    pub allowed_values: Vec<String>,
+}
+
+impl CustomField {
+    /// Check that `input` is a valid value for the field, and return it in its canonical form:
+    /// numbers without superfluous digits, dates as `YYYY-MM-DD`, enum values spelt as they are allowed.
+    /// Whether users exist is left to the [TicketStore](crate::store::TicketStore).
+    pub fn validate(&self, input: &str) -> Result<String, CustomFieldError> {
+        let invalid = |reason: String| CustomFieldError::InvalidValue {
+            field: self.name.clone(),
+            value: input.to_string(),
+            reason,
+        };
+        let input = input.trim();
+        match self.kind {
+            CustomFieldKind::Text => Ok(input.to_string()),
+            CustomFieldKind::Number => match input.parse::<f64>() {
+                Ok(number) if number.is_finite() => Ok(number.to_string()),
+                _ => Err(invalid("it is not a number".to_string())),
+            },
+            CustomFieldKind::Date => NaiveDate::parse_from_str(input, "%Y-%m-%d")
+                .map(|date| date.format("%Y-%m-%d").to_string())
+                .map_err(|_| invalid("dates are written YYYY-MM-DD".to_string())),
+            CustomFieldKind::Enum => self
+                .allowed_values
+                .iter()
+                .find(|allowed| allowed.eq_ignore_ascii_case(input))
+                .cloned()
+                .ok_or_else(|| {
+                    invalid(format!(
+                        "the allowed values are {}",
+                        self.allowed_values.join(", ")
+                    ))
+                }),
+            CustomFieldKind::User => UserId::new(input.to_string())
+                .map(|user| user.to_string())
+                .map_err(|e| invalid(e.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for CustomField {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "{} ({}", self.name, self.kind)?;
+        if self.required {
+            write!(f, ", required")?;
+        }
+        write!(f, ")")?;
+        if !self.allowed_values.is_empty() {
+            write!(f, ": {}", self.allowed_values.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Error if a custom field cannot be defined, or a ticket cannot be given a custom field value.
+#[derive(PartialEq, Debug, Clone)]
+pub enum CustomFieldError {
+    /// There is no custom field with this name.
+    UnknownField(CustomFieldName),
+    /// There is already a field, custom or built-in, with this name.
+    AlreadyDefined(CustomFieldName),
+    /// An enum field must have allowed values, and only enum fields can have them.
+    /// Allowed values cannot be blank, nor repeated ignoring case.
+    InvalidAllowedValues(CustomFieldName),
+    InvalidValue {
+        field: CustomFieldName,
+        value: String,
+        reason: String,
+    },
+    /// A new ticket has no value for a required field, or the value of a required field is cleared.
+    MissingValue(CustomFieldName),
+}
+
+impl fmt::Display for CustomFieldError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CustomFieldError::UnknownField(name) => write!(
+                f,
+                "There is no custom field named {} - define it with `field add`.",
+                name
+            ),
+            CustomFieldError::AlreadyDefined(name) => {
+                write!(f, "There is already a field named {}.", name)
+            }
+            CustomFieldError::InvalidAllowedValues(name) => write!(
+                f,
+                "Field {}: enum fields need distinct, non-empty allowed values, and other fields cannot have them.",
+                name
+            ),
+            CustomFieldError::InvalidValue {
+                field,
+                value,
+                reason,
+            } => write!(f, "{:?} is not a valid {}: {}.", value, field, reason),
+            CustomFieldError::MissingValue(name) => write!(f, "Field {} is required.", name),
+        }
+    }
+}
+
+impl Error for CustomFieldError {}
+
+#[cfg(test)]
+mod custom_field_tests {
+    use crate::models::{CustomField, CustomFieldKind, CustomFieldName};
+    use std::cmp::Ordering;
+
+    fn field(kind: CustomFieldKind, allowed_values: &[&str]) -> CustomField {
+        CustomField {
+            name: CustomFieldName::new("field".to_string()).unwrap(),
+            kind,
+            required: false,
+            allowed_values: allowed_values.iter().map(|v| v.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn values_are_validated_and_made_canonical() {
+        //arrange
+        let number = field(CustomFieldKind::Number, &[]);
+        let date = field(CustomFieldKind::Date, &[]);
+        let environment = field(CustomFieldKind::Enum, &["staging", "Production"]);
+
+        //act
+        //assert
+        assert_eq!(number.validate(" 3.50 "), Ok("3.5".to_string()));
+        assert!(number.validate("three").is_err());
+        assert_eq!(date.validate("2020-02-29"), Ok("2020-02-29".to_string()));
+        assert!(date.validate("2021-02-29").is_err());
+        assert_eq!(
+            environment.validate("production"),
+            Ok("Production".to_string())
+        );
+        assert!(environment.validate("dev").is_err());
+    }
+
+    #[test]
+    fn numbers_are_compared_by_value() {
+        //arrange
+        //act
+        let ordering = CustomFieldKind::Number.compare("8", "13");
+
+        //assert
+        assert_eq!(ordering, Ordering::Less)
+    }
+
+    #[test]
+    fn deserialized_names_are_validated_and_lowercased() {
+        //arrange
+        //act
+        let mixed_case: CustomFieldName = serde_yaml::from_str("Story-Points").unwrap();
+        let invalid: Result<CustomFieldName, _> = serde_yaml::from_str("''");
+
+        //assert
+        assert_eq!(mixed_case.as_str(), "story-points");
+        assert!(invalid.is_err())
+    }
+}
@@ -0,0 +1,56 @@
+use crate::models::{Comment, CommentId, Status, TicketDraft, TicketId, TicketPatch, UserId};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// An operation on the tickets of a [TicketStore](crate::store::TicketStore).
+///
+/// Operations are recorded once they have been validated: replaying them on the state they were
+/// applied to rebuilds the store, without checking the rules (e.g. the workflow) a second time.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "operation", rename_all = "kebab-case")]
+pub enum Operation {
+    Create {
+        draft: TicketDraft,
+        /// The initial status of the ticket, which depends on the workflow in use at the time.
+        status: Status,
+    },
+    UpdateTicket {
+        id: TicketId,
+        patch: TicketPatch,
+    },
+    UpdateTicketStatus {
+        id: TicketId,
+        status: Status,
+        comment: Option<Comment>,
+    },
+    AddComment {
+        id: TicketId,
+        comment: Comment,
+        /// The comment replied to, if any.
+        #[serde(default)]
+        reply_to: Option<CommentId>,
+    },
+    EditComment {
+        id: TicketId,
+        comment_id: CommentId,
+        body: Comment,
+    },
+    DeleteComment {
+        id: TicketId,
+        comment_id: CommentId,
+    },
+    Delete {
+        id: TicketId,
+    },
+}
+
+/// An [Operation] applied to the store, as saved in the event log.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Event {
+    /// The position of the event in the log, starting from 1.
+    pub sequence: u64,
+    pub at: DateTime<Utc>,
+    pub actor: Option<UserId>,
+    #[serde(flatten)]
+    pub operation: Operation,
+}
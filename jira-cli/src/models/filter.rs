@@ -0,0 +1,176 @@
+use crate::models::{TicketId, UserId};
+use crate::query::QueryError;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::fmt::Formatter;
+
+#[derive(PartialEq, Debug, Clone, Hash, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+/// The name of a [SavedFilter](SavedFilter), e.g. `mine-open`.
+/// Wraps a string and checks that it is a valid name when set, deserialization included:
+/// filters imported from a file shared by somebody else are checked too.
+pub struct FilterName {
+    name: String,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+/// Error if a filter name cannot be created
+pub struct FilterNameError {
+    details: String,
+}
+
+/// Sets the error message for a filter name if it cannot be created
+impl FilterNameError {
+    fn new(msg: &str) -> FilterNameError {
+        FilterNameError {
+            details: msg.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for FilterNameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.details)
+    }
+}
+
+impl Error for FilterNameError {
+    fn description(&self) -> &str {
+        &self.details
+    }
+}
+
+impl FilterName {
+    /// Creates a name for a [SavedFilter](SavedFilter)
+    /// results in a [FilterNameError](FilterNameError) if the string passed in is empty
+    /// or contains characters other than letters, digits, `.`, `-` and `_`.
+    pub fn new(name: String) -> Result<FilterName, FilterNameError> {
+        if name.is_empty() {
+            Err(FilterNameError::new("Filter name cannot be empty"))
+        } else if !name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '.' || c == '-' || c == '_')
+        {
+            Err(FilterNameError::new(
+                "Filter name can only contain letters, digits, '.', '-' and '_'",
+            ))
+        } else {
+            Ok(FilterName { name })
+        }
+    }
+}
+
+impl TryFrom<String> for FilterName {
+    type Error = FilterNameError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        FilterName::new(value)
+    }
+}
+
+impl From<FilterName> for String {
+    fn from(value: FilterName) -> Self {
+        value.name
+    }
+}
+
+impl std::fmt::Display for FilterName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "{}", self.name)
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+/// A named query, saved to list the same tickets again without typing it,
+/// e.g. `mine-open` for `status = InProgress AND assignee = me`.
+///
+/// Filters are shared by everybody using the store, and can be exported to a file for other teams.
+pub struct SavedFilter {
+    pub name: FilterName,
+    /// What the filter is for, e.g. `Tickets I am working on`.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Who saved the filter, if known.
+    #[serde(default)]
+    pub owner: Option<UserId>,
+    /// The [query](crate::query) selecting and sorting the tickets. `me` refers to whoever uses the filter.
+    pub query: String,
+    pub saved_at: DateTime<Utc>,
+}
+
+impl std::fmt::Display for SavedFilter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "{}", self.name)?;
+        if let Some(owner) = &self.owner {
+            write!(f, " (by {})", owner)?;
+        }
+        write!(f, ": {}", self.query)?;
+        if let Some(description) = &self.description {
+            write!(f, "\n    {}", description)?;
+        }
+        Ok(())
+    }
+}
+
+/// The tickets a bulk operation applies to, see [select](crate::store::TicketStore::select).
+#[derive(PartialEq, Debug, Clone)]
+pub enum TicketSelection {
+    /// These tickets, in this order.
+    Ids(Vec<TicketId>),
+    /// The tickets matching a [SavedFilter](SavedFilter).
+    Filter(FilterName),
+    /// The tickets matching a [query](crate::query).
+    Query(String),
+}
+
+/// Error if the tickets of a [TicketSelection](TicketSelection) cannot be found
+#[derive(PartialEq, Debug, Clone)]
+pub enum SelectionError {
+    /// There is no filter with this name in the store.
+    FilterNotFound(FilterName),
+    /// The query, or the query of the filter, is not valid.
+    InvalidQuery(QueryError),
+}
+
+impl fmt::Display for SelectionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SelectionError::FilterNotFound(name) => write!(f, "There is no filter named {}", name),
+            SelectionError::InvalidQuery(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for SelectionError {}
+
+#[cfg(test)]
+mod filter_tests {
+    use crate::models::{FilterName, SavedFilter};
+
+    #[test]
+    fn filter_names_are_single_words() {
+        //arrange
+        //act
+        let valid = FilterName::new("mine-open".to_string());
+        let invalid = FilterName::new("mine open".to_string());
+
+        //assert
+        assert!(valid.is_ok());
+        assert!(invalid.is_err())
+    }
+
+    #[test]
+    fn imported_filters_with_invalid_names_are_rejected() {
+        //arrange
+        let shared = "- name: a b/c\n  query: status = Done\n  saved_at: 2020-03-02T09:00:00Z\n";
+
+        //act
+        let imported: Result<Vec<SavedFilter>, _> = serde_yaml::from_str(shared);
+
+        //assert
+        assert!(imported.is_err())
+    }
+}
@@ -0,0 +1,54 @@
+use crate::models::UserId;
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fmt::Formatter;
+
+#[derive(PartialEq, Debug, Clone, Hash, Eq, Serialize, Deserialize)]
+/// A change made to a [Ticket](crate::models::Ticket), recorded by the
+/// [TicketStore](crate::store::TicketStore) every time a ticket is modified.
+///
+/// Values are saved as text, the way they are displayed: `old` is `None` when a value is set
+/// for the first time or added to a collection (e.g. a label), `new` is `None` when it is cleared
+/// or removed from a collection.
+pub struct ChangeRecord {
+    pub at: DateTime<Utc>,
+    /// Who made the change, if known - see [set_actor](crate::store::TicketStore::set_actor).
+    pub actor: Option<UserId>,
+    /// The name of the changed field, e.g. `status` or `labels`.
+    pub field: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+impl std::fmt::Display for ChangeRecord {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        let or_dash = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".to_string());
+        write!(
+            f,
+            "{} {} {}: {} -> {}",
+            self.at.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"),
+            self.actor
+                .as_ref()
+                .map_or("someone".to_string(), |a| a.to_string()),
+            self.field,
+            or_dash(&self.old),
+            or_dash(&self.new)
+        )
+    }
+}
+
+/// Who makes the changes recorded in the history of tickets, and when.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct ChangeContext {
+    pub actor: Option<UserId>,
+    /// The time of the changes, set when replaying [events](crate::models::Event):
+    /// the current time is used if it is `None`.
+    pub at: Option<DateTime<Utc>>,
+}
+
+impl ChangeContext {
+    pub fn now(&self) -> DateTime<Utc> {
+        self.at.unwrap_or_else(Utc::now)
+    }
+}
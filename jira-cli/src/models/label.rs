@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::fmt::Formatter;
+
+#[derive(PartialEq, Debug, Clone, Hash, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+/// A tag attached to a [Ticket](Ticket), e.g. `backend` or `docs`.
+/// Wraps a string and checks that it's a single, non-empty word when set, deserialization included.
+pub struct Label {
+    label: String,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+/// Error if a label cannot be created
+pub struct LabelError {
+    details: String,
+}
+
+/// Sets the error message for a label if it cannot be created
+impl LabelError {
+    fn new(msg: &str) -> LabelError {
+        LabelError {
+            details: msg.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for LabelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.details)
+    }
+}
+
+impl Error for LabelError {
+    fn description(&self) -> &str {
+        &self.details
+    }
+}
+
+impl Label {
+    /// Creates a Label for a [Ticket](Ticket)
+    /// results in a [LabelError](LabelError) if the string passed in is empty or contains whitespace.
+    /// Labels are case-insensitive: they are stored in lowercase.
+    pub fn new(label: String) -> Result<Label, LabelError> {
+        if label.is_empty() {
+            Err(LabelError::new("Label cannot be empty"))
+        } else if label.chars().any(char::is_whitespace) {
+            Err(LabelError::new("Label cannot contain whitespace"))
+        } else {
+            Ok(Label {
+                label: label.to_lowercase(),
+            })
+        }
+    }
+}
+
+impl TryFrom<String> for Label {
+    type Error = LabelError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Label::new(value)
+    }
+}
+
+impl From<Label> for String {
+    fn from(value: Label) -> Self {
+        value.label
+    }
+}
+
+impl std::fmt::Display for Label {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "{}", self.label)
+    }
+}
+
+#[cfg(test)]
+mod label_tests {
+    use crate::models::Label;
+
+    #[test]
+    fn creating_an_empty_label_should_fail() {
+        //arrange
+        //act
+        let new_label = Label::new("".to_string());
+
+        //assert
+        assert!(new_label.is_err())
+    }
+
+    #[test]
+    fn labels_with_whitespace_should_fail() {
+        //arrange
+        //act
+        let new_label = Label::new("needs review".to_string());
+
+        //assert
+        assert!(new_label.is_err())
+    }
+
+    #[test]
+    fn deserialized_labels_are_validated_and_lowercased() {
+        //arrange
+        //act
+        let mixed_case: Label = serde_yaml::from_str("Backend").unwrap();
+        let invalid: Result<Label, _> = serde_yaml::from_str("needs review");
+
+        //assert
+        assert_eq!(mixed_case, Label::new("backend".to_string()).unwrap());
+        assert!(invalid.is_err())
+    }
+}
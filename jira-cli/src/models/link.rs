@@ -0,0 +1,101 @@
+use crate::models::TicketId;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::fmt::Formatter;
+
+/// The kind of relation between two [Ticket](Ticket)s.
+///
+/// Every kind has an [inverse](LinkKind::inverse): if ticket 1 blocks ticket 2,
+/// then ticket 2 is blocked by ticket 1.
+#[derive(PartialEq, Debug, Copy, Clone, Hash, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum LinkKind {
+    Blocks,
+    IsBlockedBy,
+    Duplicates,
+    IsDuplicatedBy,
+    RelatesTo,
+}
+
+impl LinkKind {
+    /// The kind of the link going in the opposite direction.
+    pub fn inverse(self) -> LinkKind {
+        match self {
+            LinkKind::Blocks => LinkKind::IsBlockedBy,
+            LinkKind::IsBlockedBy => LinkKind::Blocks,
+            LinkKind::Duplicates => LinkKind::IsDuplicatedBy,
+            LinkKind::IsDuplicatedBy => LinkKind::Duplicates,
+            LinkKind::RelatesTo => LinkKind::RelatesTo,
+        }
+    }
+}
+
+impl std::fmt::Display for LinkKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        let kind = match self {
+            LinkKind::Blocks => "blocks",
+            LinkKind::IsBlockedBy => "is blocked by",
+            LinkKind::Duplicates => "duplicates",
+            LinkKind::IsDuplicatedBy => "is duplicated by",
+            LinkKind::RelatesTo => "relates to",
+        };
+        write!(f, "{}", kind)
+    }
+}
+
+/// A relation from a [Ticket](Ticket) to another ticket, the `target`.
+///
+/// Links are stored on the ticket they start from: the [TicketStore](crate::store::TicketStore)
+/// takes care of keeping the inverse link on the target ticket in sync.
+#[derive(PartialEq, Debug, Clone, Hash, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct TicketLink {
+    pub kind: LinkKind,
+    pub target: TicketId,
+}
+
+impl std::fmt::Display for TicketLink {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "{} {}", self.kind, self.target)
+    }
+}
+
+/// Error if two tickets cannot be linked
+#[derive(PartialEq, Debug, Clone)]
+pub enum LinkError {
+    /// There is no ticket with this id in the store.
+    TicketNotFound(TicketId),
+    /// A ticket cannot be linked to itself.
+    SelfLink(TicketId),
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LinkError::TicketNotFound(id) => {
+                write!(f, "There was no ticket associated to the ticket id {}", id)
+            }
+            LinkError::SelfLink(id) => write!(f, "Ticket {} cannot be linked to itself", id),
+        }
+    }
+}
+
+impl Error for LinkError {}
+
+#[cfg(test)]
+mod link_tests {
+    use crate::models::LinkKind;
+
+    #[test]
+    fn the_inverse_of_the_inverse_is_the_original_kind() {
+        let kinds = vec![
+            LinkKind::Blocks,
+            LinkKind::IsBlockedBy,
+            LinkKind::Duplicates,
+            LinkKind::IsDuplicatedBy,
+            LinkKind::RelatesTo,
+        ];
+        for kind in kinds {
+            assert_eq!(kind.inverse().inverse(), kind);
+        }
+    }
+}
@@ -1,11 +1,41 @@
+mod attachment;
 mod comment;
+mod custom_field;
+mod event;
+mod filter;
+mod history;
+mod label;
+mod link;
+mod notification;
+mod project;
+mod sprint;
 mod ticket;
 mod ticket_draft;
 mod ticket_patch;
 mod title;
+mod trash;
+mod undo;
+mod user;
+mod workflow;
+mod worklog;
 
+pub use attachment::*;
 pub use comment::*;
+pub use custom_field::*;
+pub use event::*;
+pub use filter::*;
+pub use history::*;
+pub use label::*;
+pub use link::*;
+pub use notification::*;
+pub use project::*;
+pub use sprint::*;
 pub use ticket::*;
 pub use ticket_draft::*;
 pub use ticket_patch::*;
 pub use title::*;
+pub use trash::*;
+pub use undo::*;
+pub use user::*;
+pub use workflow::*;
+pub use worklog::*;
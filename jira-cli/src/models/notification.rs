@@ -0,0 +1,138 @@
+use crate::models::{CommentId, Status, TicketId, UserId};
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fmt::Formatter;
+
+/// The id of a [Notification], generated by the [TicketStore](crate::store::TicketStore).
+pub type NotificationId = u64;
+
+/// Why a [User](crate::models::User) received a [Notification].
+#[derive(PartialEq, Debug, Clone, Hash, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NotificationReason {
+    /// The user was mentioned in a comment of the ticket.
+    MentionedInComment(CommentId),
+    /// The user was mentioned in the description of the ticket.
+    MentionedInDescription,
+    /// A ticket the user watches was moved to a new status.
+    StatusChanged(Status),
+    /// A ticket the user watches was edited, e.g. its priority or description.
+    Edited,
+    /// A comment was posted on a ticket the user watches.
+    Commented(CommentId),
+}
+
+#[derive(PartialEq, Debug, Clone, Hash, Eq, Serialize, Deserialize)]
+/// Something that happened on a ticket a [User](crate::models::User) should know about,
+/// kept in their inbox until they read it.
+pub struct Notification {
+    pub id: NotificationId,
+    pub ticket: TicketId,
+    pub reason: NotificationReason,
+    /// Who caused the notification, if known.
+    pub actor: Option<UserId>,
+    pub at: DateTime<Utc>,
+    #[serde(default)]
+    pub read: bool,
+}
+
+impl Notification {
+    /// What happened, e.g. "alice moved CORE-1 to Done".
+    pub fn summary(&self) -> String {
+        let actor = self
+            .actor
+            .as_ref()
+            .map_or("someone".to_string(), |a| a.to_string());
+        match &self.reason {
+            NotificationReason::MentionedInComment(comment) => format!(
+                "{} mentioned you in comment {} of {}",
+                actor, comment, self.ticket
+            ),
+            NotificationReason::MentionedInDescription => format!(
+                "{} mentioned you in the description of {}",
+                actor, self.ticket
+            ),
+            NotificationReason::StatusChanged(status) => {
+                format!("{} moved {} to {}", actor, self.ticket, status)
+            }
+            NotificationReason::Edited => format!("{} edited {}", actor, self.ticket),
+            NotificationReason::Commented(comment) => format!(
+                "{} commented on {} (comment {})",
+                actor, self.ticket, comment
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for Notification {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(
+            f,
+            "{}[{}] {} {}",
+            if self.read { " " } else { "*" },
+            self.id,
+            self.at.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+            self.summary()
+        )
+    }
+}
+
+/// The users mentioned in a text, e.g. `alice` in "Could @alice have a look?".
+///
+/// A mention is a `@` followed by a valid handle: it must not follow a letter or a digit,
+/// so that e-mail addresses are not mistaken for mentions, and trailing dots are punctuation.
+pub fn mentions(text: &str) -> BTreeSet<UserId> {
+    let is_handle_char = |c: char| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_';
+    let mut mentioned = BTreeSet::new();
+    let mut previous = None;
+    for (i, c) in text.char_indices() {
+        if c == '@' && !previous.is_some_and(|p: char| p.is_alphanumeric()) {
+            let rest = &text[i + 1..];
+            let end = rest.find(|c| !is_handle_char(c)).unwrap_or(rest.len());
+            let handle = rest[..end].trim_end_matches('.');
+            if let Ok(user) = UserId::new(handle.to_string()) {
+                mentioned.insert(user);
+            }
+        }
+        previous = Some(c);
+    }
+    mentioned
+}
+
+#[cfg(test)]
+mod notification_tests {
+    use crate::models::{mentions, UserId};
+
+    fn user(handle: &str) -> UserId {
+        UserId::new(handle.to_string()).unwrap()
+    }
+
+    #[test]
+    fn mentions_are_found_anywhere_in_the_text() {
+        //arrange
+        let text = "@alice can you pair with @Bob.Smith on this? Thanks @alice.";
+
+        //act
+        let mentioned = mentions(text);
+
+        //assert
+        assert_eq!(
+            mentioned.into_iter().collect::<Vec<UserId>>(),
+            vec![user("alice"), user("bob.smith")]
+        )
+    }
+
+    #[test]
+    fn email_addresses_are_not_mentions() {
+        //arrange
+        let text = "Reported by carol@example.com, see @ the logs";
+
+        //act
+        let mentioned = mentions(text);
+
+        //assert
+        assert!(mentioned.is_empty())
+    }
+}
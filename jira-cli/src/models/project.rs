@@ -0,0 +1,158 @@
+use crate::models::UserId;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::fmt::Formatter;
+
+#[derive(PartialEq, Debug, Clone, Hash, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+/// The short key of a [Project](Project), e.g. `CORE`. It prefixes the ids of the project tickets.
+/// Wraps a string and checks that it is a valid key when set, deserialization included.
+pub struct ProjectKey {
+    key: String,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+/// Error if a project key cannot be created
+pub struct ProjectKeyError {
+    details: String,
+}
+
+/// Sets the error message for a project key if it cannot be created
+impl ProjectKeyError {
+    fn new(msg: &str) -> ProjectKeyError {
+        ProjectKeyError {
+            details: msg.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ProjectKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.details)
+    }
+}
+
+impl Error for ProjectKeyError {
+    fn description(&self) -> &str {
+        &self.details
+    }
+}
+
+impl ProjectKey {
+    /// Creates a key for a [Project](Project)
+    /// results in a [ProjectKeyError](ProjectKeyError) if the string passed in is not made of
+    /// 2 to 10 ASCII letters and digits, starting with a letter.
+    /// Keys are case-insensitive: they are stored in uppercase.
+    pub fn new(key: String) -> Result<ProjectKey, ProjectKeyError> {
+        if key.len() < 2 || key.len() > 10 {
+            Err(ProjectKeyError::new(
+                "Project key must be between 2 and 10 characters long",
+            ))
+        } else if !key.starts_with(|c: char| c.is_ascii_alphabetic())
+            || !key.chars().all(|c| c.is_ascii_alphanumeric())
+        {
+            Err(ProjectKeyError::new(
+                "Project key can only contain letters and digits, starting with a letter",
+            ))
+        } else {
+            Ok(ProjectKey {
+                key: key.to_uppercase(),
+            })
+        }
+    }
+
+    /// The project of the tickets created before projects were introduced.
+    pub fn legacy() -> ProjectKey {
+        ProjectKey {
+            key: "JIRA".to_string(),
+        }
+    }
+}
+
+impl TryFrom<String> for ProjectKey {
+    type Error = ProjectKeyError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        ProjectKey::new(value)
+    }
+}
+
+impl From<ProjectKey> for String {
+    fn from(value: ProjectKey) -> Self {
+        value.key
+    }
+}
+
+impl std::fmt::Display for ProjectKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "{}", self.key)
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+/// A group of tickets with their own id sequence, e.g. `CORE-1`, `CORE-2`, ...
+pub struct Project {
+    pub key: ProjectKey,
+    pub name: String,
+    /// The [User](crate::models::User) responsible for the project, if any.
+    pub lead: Option<UserId>,
+}
+
+impl std::fmt::Display for Project {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "{} - {}", self.key, self.name)?;
+        if let Some(lead) = &self.lead {
+            write!(f, " (lead: {})", lead)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod project_tests {
+    use crate::models::ProjectKey;
+
+    #[test]
+    fn keys_must_start_with_a_letter() {
+        //arrange
+        //act
+        let key = ProjectKey::new("1CORE".to_string());
+
+        //assert
+        assert!(key.is_err())
+    }
+
+    #[test]
+    fn keys_cannot_be_too_long() {
+        //arrange
+        //act
+        let key = ProjectKey::new("INFRASTRUCTURE".to_string());
+
+        //assert
+        assert!(key.is_err())
+    }
+
+    #[test]
+    fn keys_are_uppercased() {
+        //arrange
+        //act
+        let key = ProjectKey::new("core".to_string()).unwrap();
+
+        //assert
+        assert_eq!(key.to_string(), "CORE")
+    }
+
+    #[test]
+    fn deserialized_keys_are_validated_and_uppercased() {
+        //arrange
+        //act
+        let lowercase: ProjectKey = serde_yaml::from_str("core").unwrap();
+        let invalid: Result<ProjectKey, _> = serde_yaml::from_str("1CORE");
+
+        //assert
+        assert_eq!(lowercase.to_string(), "CORE");
+        assert!(invalid.is_err())
+    }
+}
@@ -0,0 +1,137 @@
+use crate::models::TicketId;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fmt::Formatter;
+
+/// The id of a [Sprint](Sprint), generated by the [TicketStore](crate::store::TicketStore).
+pub type SprintId = u64;
+
+/// Where a [Sprint](Sprint) is in its lifecycle: sprints are planned, started and then completed.
+#[derive(PartialEq, Debug, Copy, Clone, Hash, Eq, Serialize, Deserialize)]
+pub enum SprintState {
+    Planned,
+    Active,
+    Completed,
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+/// A time-boxed iteration the team commits to a set of tickets for.
+///
+/// Tickets are added to a sprint through their `sprint` field: a ticket that is not in any
+/// sprint is in the backlog.
+pub struct Sprint {
+    pub id: SprintId,
+    pub name: String,
+    /// What the team wants to achieve by the end of the sprint.
+    pub goal: Option<String>,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub state: SprintState,
+    /// The unfinished tickets moved out of the sprint when it was completed.
+    #[serde(default)]
+    pub carried_over: BTreeSet<TicketId>,
+}
+
+impl std::fmt::Display for Sprint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(
+            f,
+            "{} {} [{:?}] {} - {}",
+            self.id, self.name, self.state, self.start, self.end
+        )?;
+        if let Some(goal) = &self.goal {
+            write!(f, ": {}", goal)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+/// The content of a sprint, not yet saved in the [TicketStore](crate::store::TicketStore::create_sprint).
+pub struct SprintDraft {
+    pub name: String,
+    pub goal: Option<String>,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+
+/// Where the unfinished tickets of a sprint go when it is completed.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum CarryOver {
+    Backlog,
+    Sprint(SprintId),
+}
+
+/// How far a [Sprint](Sprint) got, by [category](crate::models::StatusCategory) of the status of its tickets.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct SprintSummary {
+    pub to_do: usize,
+    pub in_progress: usize,
+    pub done: usize,
+    /// Tickets moved out of the sprint when it was completed.
+    pub carried_over: usize,
+}
+
+impl std::fmt::Display for SprintSummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        let committed = self.to_do + self.in_progress + self.done + self.carried_over;
+        writeln!(f, "Done:{}/{}", self.done, committed)?;
+        writeln!(f, "In progress:{}", self.in_progress)?;
+        writeln!(f, "To do:{}", self.to_do)?;
+        write!(f, "Carried over:{}", self.carried_over)
+    }
+}
+
+/// Error if a sprint cannot be planned, started or completed
+#[derive(PartialEq, Debug, Clone)]
+pub enum SprintError {
+    /// There is no sprint with this id in the store.
+    SprintNotFound(SprintId),
+    /// There is no ticket with this id in the store.
+    TicketNotFound(TicketId),
+    /// The sprint would end before it starts.
+    EndBeforeStart,
+    /// Only one sprint can be active at a time.
+    AlreadyActive(SprintId),
+    /// Only planned sprints can be started.
+    NotPlanned(SprintId),
+    /// Only active sprints can be completed.
+    NotActive(SprintId),
+    /// Tickets cannot be added to a completed sprint.
+    Completed(SprintId),
+    /// Unfinished tickets cannot be carried over to the sprint being completed.
+    CarryOverToSelf(SprintId),
+}
+
+impl fmt::Display for SprintError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SprintError::SprintNotFound(id) => {
+                write!(f, "There was no sprint associated to the sprint id {}", id)
+            }
+            SprintError::TicketNotFound(id) => {
+                write!(f, "There was no ticket associated to the ticket id {}", id)
+            }
+            SprintError::EndBeforeStart => write!(f, "A sprint cannot end before it starts"),
+            SprintError::AlreadyActive(id) => write!(
+                f,
+                "Sprint {} is already active - complete it before starting another one",
+                id
+            ),
+            SprintError::NotPlanned(id) => {
+                write!(f, "Sprint {} has already been started", id)
+            }
+            SprintError::NotActive(id) => write!(f, "Sprint {} is not active", id),
+            SprintError::Completed(id) => write!(f, "Sprint {} has been completed", id),
+            SprintError::CarryOverToSelf(id) => write!(
+                f,
+                "Unfinished tickets of sprint {} cannot be carried over to the sprint itself",
+                id
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SprintError {}
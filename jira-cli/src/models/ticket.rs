@@ -1,9 +1,117 @@
-use crate::models::{Comment, Title};
+use crate::models::{
+    Attachment, ChangeContext, ChangeRecord, Comment, CommentId, CustomFieldName, Label,
+    ProjectKey, SprintId, TicketComment, TicketLink, Title, UserId, WorkDuration, Worklog,
+};
+use serde::de::Visitor;
 use serde::export::fmt::Error;
 use serde::export::Formatter;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
 
-pub type TicketId = u64;
+/// The id of a [Ticket](Ticket): the key of its [Project](crate::models::Project) and the number
+/// of the ticket within the project. It is written as `KEY-N`, e.g. `CORE-42`.
+#[derive(PartialEq, Debug, Clone, Hash, Eq, PartialOrd, Ord)]
+pub struct TicketId {
+    pub project: ProjectKey,
+    pub number: u64,
+}
+
+impl TicketId {
+    pub fn new(project: ProjectKey, number: u64) -> TicketId {
+        TicketId { project, number }
+    }
+}
+
+impl std::fmt::Display for TicketId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "{}-{}", self.project, self.number)
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+/// Error if a ticket id cannot be parsed
+pub struct TicketIdError {
+    details: String,
+}
+
+impl fmt::Display for TicketIdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.details)
+    }
+}
+
+impl std::error::Error for TicketIdError {
+    fn description(&self) -> &str {
+        &self.details
+    }
+}
+
+/// Parses `KEY-N` ids, e.g. `CORE-42` or `core-42`.
+impl FromStr for TicketId {
+    type Err = TicketIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |details: String| TicketIdError { details };
+        let separator = s.rfind('-').ok_or_else(|| {
+            invalid(format!(
+                "{} is not a valid ticket id - expected KEY-N, e.g. CORE-42",
+                s
+            ))
+        })?;
+        let project = ProjectKey::new(s[..separator].to_string())
+            .map_err(|e| invalid(format!("{} is not a valid ticket id: {}", s, e)))?;
+        let number = s[separator + 1..].parse().map_err(|_| {
+            invalid(format!(
+                "{} is not a valid ticket id - the ticket number is missing",
+                s
+            ))
+        })?;
+        Ok(TicketId { project, number })
+    }
+}
+
+/// Ticket ids are saved as `KEY-N` strings.
+impl Serialize for TicketId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Ticket ids are read from `KEY-N` strings or, for data saved before projects were introduced,
+/// from bare numbers: those tickets belong to the [legacy project](ProjectKey::legacy).
+impl<'de> Deserialize<'de> for TicketId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct TicketIdVisitor;
+
+        impl<'de> Visitor<'de> for TicketIdVisitor {
+            type Value = TicketId;
+
+            fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+                write!(f, "a ticket id, e.g. CORE-42")
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, number: u64) -> Result<TicketId, E> {
+                Ok(TicketId::new(ProjectKey::legacy(), number))
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, number: i64) -> Result<TicketId, E> {
+                if number < 0 {
+                    return Err(E::custom("a ticket number cannot be negative"));
+                }
+                self.visit_u64(number as u64)
+            }
+
+            fn visit_str<E: serde::de::Error>(self, id: &str) -> Result<TicketId, E> {
+                id.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(TicketIdVisitor)
+    }
+}
 
 #[derive(PartialEq, Debug, Clone, Hash, Eq)]
 /// A ticket saved in the [TicketStore](TicketStore).
@@ -17,34 +125,378 @@ pub struct Ticket {
     pub title: Title,
     pub description: String,
     pub status: Status,
-    pub comments: Vec<Comment>,
+    #[serde(default)]
+    pub priority: Priority,
+    #[serde(default)]
+    pub kind: TicketKind,
+    /// The ticket this one is a part of, e.g. the epic of a story. See [TicketKind](TicketKind)
+    /// for the rules constraining the hierarchy.
+    #[serde(default)]
+    pub parent: Option<TicketId>,
+    pub comments: Vec<TicketComment>,
+    /// The id of the last comment posted on the ticket, used to generate comment ids:
+    /// the ids of deleted comments are not reused.
+    #[serde(default)]
+    pub comment_sequence: CommentId,
+    /// The [User](crate::models::User) who filed the ticket, if known.
+    pub reporter: Option<UserId>,
+    /// The [User](crate::models::User) currently working on the ticket, if any.
+    pub assignee: Option<UserId>,
+    #[serde(default)]
+    pub labels: BTreeSet<Label>,
+    /// Relations to other tickets, kept consistent by the [TicketStore](TicketStore).
+    #[serde(default)]
+    pub links: BTreeSet<TicketLink>,
+    /// The [Sprint](crate::models::Sprint) the ticket is planned in. `None` if it is in the backlog.
+    #[serde(default)]
+    pub sprint: Option<SprintId>,
+    /// How long the ticket was expected to take when it was planned.
+    #[serde(default)]
+    pub estimate: Option<WorkDuration>,
+    /// How much work is left, lowered every time work is logged.
+    #[serde(default)]
+    pub remaining: Option<WorkDuration>,
+    /// The time spent working on the ticket, in the order it was logged.
+    #[serde(default)]
+    pub worklogs: Vec<Worklog>,
+    /// The [users](crate::models::User) notified when the ticket changes.
+    #[serde(default)]
+    pub watchers: BTreeSet<UserId>,
+    /// The files attached to the ticket, the oldest first.
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    /// The values of the [custom fields](crate::models::CustomField) set on the ticket, valid when they were set.
+    #[serde(default)]
+    pub custom_fields: BTreeMap<CustomFieldName, String>,
+    /// Every change made to the ticket since it was created, the oldest first.
+    #[serde(default)]
+    pub history: Vec<ChangeRecord>,
+}
+
+impl Ticket {
+    /// The total time logged on the ticket.
+    pub fn time_spent(&self) -> WorkDuration {
+        self.worklogs
+            .iter()
+            .fold(WorkDuration::default(), |total, w| total + w.time_spent)
+    }
+
+    /// Append a [ChangeRecord] to the history of the ticket, unless the value did not change.
+    pub fn record_change(
+        &mut self,
+        context: &ChangeContext,
+        field: &str,
+        old: Option<String>,
+        new: Option<String>,
+    ) {
+        if old != new {
+            self.history.push(ChangeRecord {
+                at: context.now(),
+                actor: context.actor.clone(),
+                field: field.to_string(),
+                old,
+                new,
+            });
+        }
+    }
+
+    /// Post a new comment on the ticket, written by the actor of `context`, and return its id.
+    /// `reply_to` is expected to be the id of a comment of the ticket.
+    pub fn post_comment(
+        &mut self,
+        context: &ChangeContext,
+        body: Comment,
+        reply_to: Option<CommentId>,
+    ) -> CommentId {
+        self.comment_sequence += 1;
+        self.record_change(context, "comment", None, Some(body.to_string()));
+        self.comments.push(TicketComment {
+            id: self.comment_sequence,
+            body,
+            author: context.actor.clone(),
+            reply_to,
+            created_at: context.now(),
+            edited_at: None,
+            revisions: Vec::new(),
+        });
+        self.comment_sequence
+    }
+
+    /// The replies to a comment, or the comments starting a thread if `comment` is `None`,
+    /// the oldest first. Replies to a comment that is not there anymore start a thread.
+    pub fn replies(&self, comment: Option<CommentId>) -> Vec<&TicketComment> {
+        let exists = |id: CommentId| self.comments.iter().any(|c| c.id == id);
+        self.comments
+            .iter()
+            .filter(|c| match comment {
+                Some(comment) => c.reply_to == Some(comment),
+                None => !c.reply_to.is_some_and(exists),
+            })
+            .collect()
+    }
+
+    /// Write a comment followed by its replies, indented one level deeper.
+    fn write_thread(
+        &self,
+        f: &mut Formatter<'_>,
+        comment: &TicketComment,
+        depth: usize,
+    ) -> Result<(), Error> {
+        writeln!(f, "\t{}- {}", "  ".repeat(depth), comment)?;
+        for reply in self.replies(Some(comment.id)) {
+            self.write_thread(f, reply, depth + 1)?;
+        }
+        Ok(())
+    }
 }
 
 impl std::fmt::Display for Ticket {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
         writeln!(
             f,
-            "Ticket:\n\tId:{:?}\n\tTitle:{}\n\tDescription:{}\n\tStatus:{:?}\n\tComments:",
-            self.id, self.title, self.description, self.status
+            "Ticket:\n\tId:{}\n\tKind:{:?}\n\tTitle:{}\n\tDescription:{}\n\tStatus:{}\n\tPriority:{:?}",
+            self.id, self.kind, self.title, self.description, self.status, self.priority
         )?;
-        for comment in self.comments.iter() {
-            writeln!(f, "\t- {}", comment)?;
+        if let Some(parent) = &self.parent {
+            writeln!(f, "\tParent:{}", parent)?;
+        }
+        if let Some(sprint) = self.sprint {
+            writeln!(f, "\tSprint:{}", sprint)?;
+        }
+        match &self.reporter {
+            Some(reporter) => writeln!(f, "\tReporter:{}", reporter)?,
+            None => writeln!(f, "\tReporter:-")?,
+        }
+        match &self.assignee {
+            Some(assignee) => writeln!(f, "\tAssignee:{}", assignee)?,
+            None => writeln!(f, "\tAssignee:Unassigned")?,
+        }
+        let labels: Vec<String> = self.labels.iter().map(|l| l.to_string()).collect();
+        writeln!(f, "\tLabels:{}", labels.join(", "))?;
+        writeln!(f, "\tLinks:")?;
+        for link in self.links.iter() {
+            writeln!(f, "\t- {}", link)?;
+        }
+        if self.estimate.is_some() || self.remaining.is_some() || !self.worklogs.is_empty() {
+            let or_dash = |d: Option<WorkDuration>| d.map_or("-".to_string(), |d| d.to_string());
+            writeln!(
+                f,
+                "\tEstimate:{}\n\tRemaining:{}\n\tLogged:{}",
+                or_dash(self.estimate),
+                or_dash(self.remaining),
+                self.time_spent()
+            )?;
+            writeln!(f, "\tWorklogs:")?;
+            for worklog in self.worklogs.iter() {
+                writeln!(f, "\t- {}", worklog)?;
+            }
+        }
+        if !self.custom_fields.is_empty() {
+            writeln!(f, "\tCustom fields:")?;
+            for (name, value) in self.custom_fields.iter() {
+                writeln!(f, "\t- {}: {}", name, value)?;
+            }
+        }
+        if !self.watchers.is_empty() {
+            let watchers: Vec<String> = self.watchers.iter().map(|w| w.to_string()).collect();
+            writeln!(f, "\tWatchers:{}", watchers.join(", "))?;
+        }
+        if !self.attachments.is_empty() {
+            writeln!(f, "\tAttachments:")?;
+            for attachment in self.attachments.iter() {
+                writeln!(f, "\t- {}", attachment)?;
+            }
+        }
+        writeln!(f, "\tComments:")?;
+        for comment in self.replies(None) {
+            self.write_thread(f, comment, 0)?;
         }
         Ok(())
     }
 }
 
-/// The status of a [Ticket](Ticket).
+/// The status of a [Ticket](Ticket), i.e. the column it sits in on the board.
+///
+/// The set of valid statuses is not fixed: it is defined by the [Workflow](crate::models::Workflow)
+/// in the configuration file. The default workflow provides `ToDo`, `InProgress`, `Blocked` and `Done`.
+/// Deserialized names are checked like [try_from](Status::try_from) does.
+#[derive(PartialEq, Debug, Clone, Hash, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Status {
+    name: String,
+}
+
+impl Status {
+    /// Creates a status with the given name, e.g. `In Review`.
+    pub fn new(name: &str) -> Status {
+        Status {
+            name: name.to_string(),
+        }
+    }
+
+    /// The status new tickets start from in the default workflow.
+    pub fn to_do() -> Status {
+        Status::new("ToDo")
+    }
+
+    pub fn in_progress() -> Status {
+        Status::new("InProgress")
+    }
+
+    pub fn blocked() -> Status {
+        Status::new("Blocked")
+    }
+
+    pub fn done() -> Status {
+        Status::new("Done")
+    }
+
+    /// Whether `input` refers to this status, ignoring case, whitespace, `-` and `_`:
+    /// `in-progress`, `in progress` and `InProgress` all match `InProgress`.
+    pub fn matches(&self, input: &str) -> bool {
+        fn normalise(s: &str) -> String {
+            s.chars()
+                .filter(|c| c.is_alphanumeric())
+                .flat_map(char::to_lowercase)
+                .collect()
+        }
+        normalise(&self.name) == normalise(input)
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+/// Error if a status name is blank
+pub struct StatusError {
+    details: String,
+}
+
+impl fmt::Display for StatusError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.details)
+    }
+}
+
+impl std::error::Error for StatusError {
+    fn description(&self) -> &str {
+        &self.details
+    }
+}
+
+/// Status names read from a file, e.g. the workflow of the configuration, are trimmed
+/// and cannot be blank.
+impl TryFrom<String> for Status {
+    type Error = StatusError;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        let name = name.trim();
+        if name.is_empty() {
+            Err(StatusError {
+                details: "Status name cannot be empty".to_string(),
+            })
+        } else {
+            Ok(Status::new(name))
+        }
+    }
+}
+
+impl From<Status> for String {
+    fn from(status: Status) -> Self {
+        status.name
+    }
+}
+
+impl std::fmt::Display for Status {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// The broad stage of work a [Status](Status) belongs to.
+///
+/// Every configured status falls into one category: reports (e.g. the progress of an epic)
+/// rely on categories, so they keep working whatever statuses a board uses.
 #[derive(PartialEq, Debug, Copy, Clone, Hash, Eq, Serialize, Deserialize)]
-pub enum Status {
+pub enum StatusCategory {
+    #[serde(rename = "todo", alias = "ToDo")]
     ToDo,
+    #[serde(rename = "in-progress", alias = "InProgress")]
     InProgress,
-    Blocked,
+    #[serde(rename = "done", alias = "Done")]
     Done,
 }
 
-#[derive(PartialEq, Debug)]
-/// A ticket that was deleted from the store.
+/// How urgent a [Ticket](Ticket) is.
+///
+/// Variants are declared from the least to the most urgent: the derived ordering
+/// can be used to sort tickets by priority.
+#[derive(PartialEq, Debug, Copy, Clone, Hash, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Priority {
+    Lowest,
+    Low,
+    Medium,
+    High,
+    Highest,
+}
+
+/// Tickets that predate priorities, or are created without one, are of [Medium](Priority::Medium) priority.
+impl Default for Priority {
+    fn default() -> Self {
+        Priority::Medium
+    }
+}
+
+/// The kind of work a [Ticket](Ticket) tracks.
 ///
-/// Using the new-type pattern to distinguish it from [Ticket](Ticket).
-pub struct DeletedTicket(pub Ticket);
+/// Tickets can be organised in a hierarchy using their `parent`:
+/// - epics are at the top of the hierarchy, they cannot have a parent;
+/// - sub-tasks are at the bottom of the hierarchy, they cannot have children;
+/// - a ticket cannot be (directly or indirectly) its own parent.
+#[derive(PartialEq, Debug, Copy, Clone, Hash, Eq, Serialize, Deserialize)]
+pub enum TicketKind {
+    Epic,
+    Story,
+    Task,
+    SubTask,
+    Bug,
+}
+
+/// Tickets that predate kinds, or are created without one, are [tasks](TicketKind::Task).
+impl Default for TicketKind {
+    fn default() -> Self {
+        TicketKind::Task
+    }
+}
+
+/// Error if a ticket cannot be placed in the hierarchy
+#[derive(PartialEq, Debug, Clone)]
+pub enum HierarchyError {
+    /// There is no ticket with this id in the store.
+    TicketNotFound(TicketId),
+    /// An epic cannot be the child of another ticket.
+    EpicCannotHaveParent,
+    /// The would-be parent is a sub-task.
+    SubTaskCannotHaveChildren(TicketId),
+    /// The would-be parent is the ticket itself or one of its descendants.
+    Cycle { ticket: TicketId, parent: TicketId },
+}
+
+impl fmt::Display for HierarchyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HierarchyError::TicketNotFound(id) => {
+                write!(f, "There was no ticket associated to the ticket id {}", id)
+            }
+            HierarchyError::EpicCannotHaveParent => write!(f, "An epic cannot have a parent"),
+            HierarchyError::SubTaskCannotHaveChildren(id) => {
+                write!(f, "Ticket {} is a sub-task, it cannot have children", id)
+            }
+            HierarchyError::Cycle { ticket, parent } => write!(
+                f,
+                "Ticket {} cannot be the parent of ticket {}: it is one of its descendants",
+                parent, ticket
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HierarchyError {}
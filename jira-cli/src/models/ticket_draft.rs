@@ -1,9 +1,67 @@
-use crate::models::Title;
+use crate::models::{
+    CustomFieldError, CustomFieldName, HierarchyError, Priority, ProjectKey, TicketId, TicketKind,
+    Title, UserId,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
 
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 /// The content of the ticket, not yet saved in the [TicketStore](TicketStore::create).
 pub struct TicketDraft {
+    // The [Project](crate::models::Project) the ticket is filed in
+    pub project: ProjectKey,
     // The [Title](Title) of a ticket
     pub title: Title,
     pub description: String,
+    pub priority: Priority,
+    pub kind: TicketKind,
+    // The ticket the new ticket is a part of, e.g. its epic
+    pub parent: Option<TicketId>,
+    // The [User](crate::models::User) filing the ticket
+    pub reporter: Option<UserId>,
+    pub assignee: Option<UserId>,
+    // The values of [custom fields](crate::models::CustomField), validated by the store
+    #[serde(default)]
+    pub custom_fields: BTreeMap<CustomFieldName, String>,
+}
+
+/// Error if a [TicketDraft](TicketDraft) cannot be saved in the [TicketStore](TicketStore::create)
+#[derive(PartialEq, Debug, Clone)]
+pub enum CreateError {
+    /// There is no project with this key in the store.
+    UnknownProject(ProjectKey),
+    /// The parent of the draft violates the hierarchy rules.
+    Hierarchy(HierarchyError),
+    /// A custom field is unknown or a required one is missing.
+    CustomField(CustomFieldError),
+}
+
+impl From<HierarchyError> for CreateError {
+    fn from(e: HierarchyError) -> Self {
+        CreateError::Hierarchy(e)
+    }
 }
+
+impl From<CustomFieldError> for CreateError {
+    fn from(e: CustomFieldError) -> Self {
+        CreateError::CustomField(e)
+    }
+}
+
+impl fmt::Display for CreateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CreateError::UnknownProject(key) => write!(
+                f,
+                "There is no project with key {} - create it with `project create`.",
+                key
+            ),
+            CreateError::Hierarchy(e) => write!(f, "{}", e),
+            CreateError::CustomField(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for CreateError {}
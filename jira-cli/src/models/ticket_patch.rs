@@ -1,9 +1,21 @@
-use crate::models::Title;
+use crate::models::{CustomFieldName, Priority, Title, UserId, WorkDuration};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 /// The content of the ticket, to be updated in the [TicketStore](TicketStore::create).
 pub struct TicketPatch {
     // The [Title](Title) of a ticket
     pub title: Option<Title>,
     pub description: Option<String>,
+    pub priority: Option<Priority>,
+    pub reporter: Option<UserId>,
+    // Use [TicketStore::unassign](crate::store::TicketStore::unassign) to clear the assignee
+    pub assignee: Option<UserId>,
+    // Setting the estimate of a ticket without a remaining estimate also sets the latter
+    pub estimate: Option<WorkDuration>,
+    pub remaining: Option<WorkDuration>,
+    // New values of [custom fields](crate::models::CustomField), validated by the store: `None` clears a value
+    #[serde(default)]
+    pub custom_fields: BTreeMap<CustomFieldName, Option<String>>,
 }
@@ -0,0 +1,133 @@
+use crate::models::worklog::parse_components;
+use crate::models::{Ticket, UserId};
+use chrono::{DateTime, Duration, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::fmt::Formatter;
+use std::str::FromStr;
+
+const MINUTES_PER_HOUR: u64 = 60;
+const MINUTES_PER_DAY: u64 = 24 * MINUTES_PER_HOUR;
+const MINUTES_PER_WEEK: u64 = 7 * MINUTES_PER_DAY;
+/// The longest age a [Duration] can hold, which counts milliseconds in an `i64`.
+const MAX_MINUTES: u64 = (i64::MAX / 60_000) as u64;
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+/// A ticket that was deleted from the store. It stays in the trash until it is restored or purged.
+pub struct DeletedTicket {
+    pub ticket: Ticket,
+    pub deleted_at: DateTime<Utc>,
+    /// Who deleted the ticket, if known - see [set_actor](crate::store::TicketStore::set_actor).
+    pub deleted_by: Option<UserId>,
+}
+
+impl std::fmt::Display for DeletedTicket {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(
+            f,
+            "{} {} (deleted {} by {})",
+            self.ticket.id,
+            self.ticket.title,
+            self.deleted_at
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M"),
+            self.deleted_by
+                .as_ref()
+                .map_or("someone".to_string(), |u| u.to_string())
+        )
+    }
+}
+
+#[derive(PartialEq, Debug, Copy, Clone, Hash, Eq, PartialOrd, Ord)]
+/// An amount of calendar time, e.g. how long a ticket has been in the trash.
+/// Unlike a [WorkDuration](crate::models::WorkDuration), a day is 24 hours and a week is 7 days.
+pub struct Age {
+    minutes: u64,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+/// Error if an age cannot be parsed
+pub struct AgeError {
+    details: String,
+}
+
+impl fmt::Display for AgeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.details)
+    }
+}
+
+impl Error for AgeError {
+    fn description(&self) -> &str {
+        &self.details
+    }
+}
+
+impl Age {
+    pub fn from_days(days: u64) -> Age {
+        Age {
+            minutes: days * MINUTES_PER_DAY,
+        }
+    }
+
+    pub fn to_duration(self) -> Duration {
+        Duration::minutes(self.minutes as i64)
+    }
+}
+
+/// Parses ages such as `30d`, `2w` or `1d 12h`, with the same units as a
+/// [WorkDuration](crate::models::WorkDuration).
+impl FromStr for Age {
+    type Err = AgeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let minutes = parse_components(
+            s,
+            &[
+                ('w', MINUTES_PER_WEEK),
+                ('d', MINUTES_PER_DAY),
+                ('h', MINUTES_PER_HOUR),
+                ('m', 1),
+            ],
+        )
+        .and_then(|minutes| {
+            if minutes > MAX_MINUTES {
+                Err("it is too long")
+            } else {
+                Ok(minutes)
+            }
+        })
+        .map_err(|reason| AgeError {
+            details: format!("{} is not a valid age - {} (e.g. 30d or 2w)", s, reason),
+        })?;
+        Ok(Age { minutes })
+    }
+}
+
+#[cfg(test)]
+mod trash_tests {
+    use crate::models::Age;
+
+    #[test]
+    fn ages_are_measured_in_calendar_days() {
+        //arrange
+        //act
+        let age: Age = "1w 2d".parse().unwrap();
+
+        //assert
+        assert_eq!(age, Age::from_days(9))
+    }
+
+    #[test]
+    fn ages_longer_than_a_duration_are_rejected() {
+        //arrange
+        //act
+        let too_long = "99999999999999w".parse::<Age>();
+        let overflowing = "99999999999999999w".parse::<Age>();
+
+        //assert
+        assert!(too_long.is_err());
+        assert!(overflowing.is_err());
+    }
+}
@@ -0,0 +1,240 @@
+use crate::models::{
+    CustomField, CustomFieldName, DeletedTicket, FilterName, Notification, Project, ProjectKey,
+    SavedFilter, Sprint, SprintId, Ticket, TicketId, User, UserId,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::hash::Hash;
+
+/// The number of commands that can be undone.
+pub const UNDO_LIMIT: usize = 20;
+
+/// The value of an entry of the store, before and after a command.
+/// `None` if the entry did not exist, e.g. `before` is `None` for a created ticket.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Change<K, V> {
+    pub id: K,
+    pub before: Option<V>,
+    pub after: Option<V>,
+}
+
+impl<K: Ord + Clone, V: PartialEq + Clone> Change<K, V> {
+    /// The entries that differ between two states of a collection, sorted by id.
+    pub fn between<'a>(
+        before: impl IntoIterator<Item = (&'a K, &'a V)>,
+        after: impl IntoIterator<Item = (&'a K, &'a V)>,
+    ) -> Vec<Change<K, V>>
+    where
+        K: 'a,
+        V: 'a,
+    {
+        let before: BTreeMap<&K, &V> = before.into_iter().collect();
+        let after: BTreeMap<&K, &V> = after.into_iter().collect();
+        let ids: BTreeSet<&K> = before.keys().chain(after.keys()).cloned().collect();
+        ids.into_iter()
+            .filter(|id| before.get(id) != after.get(id))
+            .map(|id| Change {
+                id: id.clone(),
+                before: before.get(id).map(|v| (*v).clone()),
+                after: after.get(id).map(|v| (*v).clone()),
+            })
+            .collect()
+    }
+
+    /// The value of the entry after the command if `redo`, before the command otherwise.
+    pub fn version(&self, redo: bool) -> &Option<V> {
+        if redo {
+            &self.after
+        } else {
+            &self.before
+        }
+    }
+
+    /// Put the entry of `entries` back in the state before the command, or after it if `redo`.
+    pub fn reset(&self, entries: &mut impl Entries<K, V>, redo: bool) {
+        entries.reset(self.id.clone(), self.version(redo).clone());
+    }
+}
+
+/// A collection of the store whose entries can be put back in a previous state by a [Change].
+pub trait Entries<K, V> {
+    /// Replace the entry with id `id` by `value`, removing it if `value` is `None`.
+    fn reset(&mut self, id: K, value: Option<V>);
+}
+
+impl<K: Ord, V> Entries<K, V> for BTreeMap<K, V> {
+    fn reset(&mut self, id: K, value: Option<V>) {
+        match value {
+            Some(value) => self.insert(id, value),
+            None => self.remove(&id),
+        };
+    }
+}
+
+impl<K: Hash + Eq, V> Entries<K, V> for HashMap<K, V> {
+    fn reset(&mut self, id: K, value: Option<V>) {
+        match value {
+            Some(value) => self.insert(id, value),
+            None => self.remove(&id),
+        };
+    }
+}
+
+/// A copy of the collections of a [TicketStore](crate::store::TicketStore) that commands can change,
+/// taken before a command to find out what it changed.
+pub struct Checkpoint {
+    pub tickets: HashMap<TicketId, Ticket>,
+    pub sprints: BTreeMap<SprintId, Sprint>,
+    pub trash: BTreeMap<TicketId, DeletedTicket>,
+    pub users: HashMap<UserId, User>,
+    pub projects: BTreeMap<ProjectKey, Project>,
+    pub filters: BTreeMap<FilterName, SavedFilter>,
+    pub custom_fields: BTreeMap<CustomFieldName, CustomField>,
+    pub inboxes: BTreeMap<UserId, Vec<Notification>>,
+}
+
+/// Everything a CLI command changed, with enough information to revert it or to apply it again.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct UndoStep {
+    /// The command line, e.g. `delete --ticket-id CORE-3`.
+    pub command: String,
+    pub at: DateTime<Utc>,
+    #[serde(default)]
+    pub tickets: Vec<Change<TicketId, Ticket>>,
+    #[serde(default)]
+    pub sprints: Vec<Change<SprintId, Sprint>>,
+    #[serde(default)]
+    pub trash: Vec<Change<TicketId, DeletedTicket>>,
+    #[serde(default)]
+    pub users: Vec<Change<UserId, User>>,
+    #[serde(default)]
+    pub projects: Vec<Change<ProjectKey, Project>>,
+    #[serde(default)]
+    pub filters: Vec<Change<FilterName, SavedFilter>>,
+    #[serde(default)]
+    pub custom_fields: Vec<Change<CustomFieldName, CustomField>>,
+    /// The notifications of each user, e.g. sent by a comment or marked as read.
+    #[serde(default)]
+    pub inboxes: Vec<Change<UserId, Vec<Notification>>>,
+}
+
+impl UndoStep {
+    /// Whether the command did not change anything.
+    pub fn is_empty(&self) -> bool {
+        self.tickets.is_empty()
+            && self.sprints.is_empty()
+            && self.trash.is_empty()
+            && self.users.is_empty()
+            && self.projects.is_empty()
+            && self.filters.is_empty()
+            && self.custom_fields.is_empty()
+            && self.inboxes.is_empty()
+    }
+}
+
+/// The commands that can be undone, and those that were undone and can be redone.
+///
+/// It is bounded: only the last [UNDO_LIMIT] commands are kept.
+#[derive(PartialEq, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UndoStack {
+    #[serde(default)]
+    undo: Vec<UndoStep>,
+    #[serde(default)]
+    redo: Vec<UndoStep>,
+}
+
+impl UndoStack {
+    /// Record a new command. The commands that were undone cannot be redone anymore.
+    pub fn push(&mut self, step: UndoStep) {
+        self.undo.push(step);
+        if self.undo.len() > UNDO_LIMIT {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    /// The tickets, as they were before or after the commands that can be undone or redone,
+    /// in the store or in the trash.
+    pub fn tickets(&self) -> impl Iterator<Item = &Ticket> {
+        let steps = self.undo.iter().chain(self.redo.iter());
+        let tickets = steps
+            .clone()
+            .flat_map(|step| step.tickets.iter())
+            .flat_map(|change| change.before.iter().chain(change.after.iter()));
+        let trash = steps
+            .flat_map(|step| step.trash.iter())
+            .flat_map(|change| change.before.iter().chain(change.after.iter()))
+            .map(|deleted| &deleted.ticket);
+        tickets.chain(trash)
+    }
+
+    /// Move the last command to the redo stack and return it, to be reverted.
+    /// Returns `None` if there is nothing to undo.
+    pub fn undo(&mut self) -> Option<&UndoStep> {
+        let step = self.undo.pop()?;
+        self.redo.push(step);
+        self.redo.last()
+    }
+
+    /// Move the last undone command back to the undo stack and return it, to be applied again.
+    /// Returns `None` if there is nothing to redo.
+    pub fn redo(&mut self) -> Option<&UndoStep> {
+        let step = self.redo.pop()?;
+        self.undo.push(step);
+        self.undo.last()
+    }
+}
+
+#[cfg(test)]
+mod undo_tests {
+    use crate::models::{UndoStack, UndoStep, UNDO_LIMIT};
+    use chrono::Utc;
+
+    fn step(command: &str) -> UndoStep {
+        UndoStep {
+            command: command.to_string(),
+            at: Utc::now(),
+            tickets: Vec::new(),
+            sprints: Vec::new(),
+            trash: Vec::new(),
+            users: Vec::new(),
+            projects: Vec::new(),
+            filters: Vec::new(),
+            custom_fields: Vec::new(),
+            inboxes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn only_the_last_commands_can_be_undone() {
+        //arrange
+        let mut stack = UndoStack::default();
+        for i in 0..UNDO_LIMIT + 5 {
+            stack.push(step(&i.to_string()));
+        }
+
+        //act
+        let mut undone = 0;
+        while stack.undo().is_some() {
+            undone += 1;
+        }
+
+        //assert
+        assert_eq!(undone, UNDO_LIMIT)
+    }
+
+    #[test]
+    fn a_new_command_clears_the_redo_stack() {
+        //arrange
+        let mut stack = UndoStack::default();
+        stack.push(step("first"));
+        stack.undo();
+
+        //act
+        stack.push(step("second"));
+
+        //assert
+        assert!(stack.redo().is_none())
+    }
+}
@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::fmt::Formatter;
+
+#[derive(PartialEq, Debug, Clone, Hash, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+/// The handle identifying a [User](User), e.g. `alice` or `bob.smith`.
+/// Wraps a string and checks that it is a valid handle when set, deserialization included.
+pub struct UserId {
+    handle: String,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+/// Error if a user handle cannot be created
+pub struct UserIdError {
+    details: String,
+}
+
+/// Sets the error message for a user handle if it cannot be created
+impl UserIdError {
+    fn new(msg: &str) -> UserIdError {
+        UserIdError {
+            details: msg.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for UserIdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.details)
+    }
+}
+
+impl Error for UserIdError {
+    fn description(&self) -> &str {
+        &self.details
+    }
+}
+
+impl UserId {
+    /// Creates a handle for a [User](User)
+    /// results in a [UserIdError](UserIdError) if the string passed in is empty
+    /// or contains characters other than ASCII letters, digits, `.`, `-` and `_`.
+    pub fn new(handle: String) -> Result<UserId, UserIdError> {
+        if handle.is_empty() {
+            Err(UserIdError::new("User handle cannot be empty"))
+        } else if !handle
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_')
+        {
+            Err(UserIdError::new(
+                "User handle can only contain letters, digits, '.', '-' and '_'",
+            ))
+        } else {
+            Ok(UserId {
+                handle: handle.to_lowercase(),
+            })
+        }
+    }
+}
+
+impl TryFrom<String> for UserId {
+    type Error = UserIdError;
+
+    fn try_from(handle: String) -> Result<Self, Self::Error> {
+        UserId::new(handle)
+    }
+}
+
+impl From<UserId> for String {
+    fn from(id: UserId) -> Self {
+        id.handle
+    }
+}
+
+impl std::fmt::Display for UserId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "{}", self.handle)
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Hash, Eq, Serialize, Deserialize)]
+/// Somebody working on the board: they can file tickets and have tickets assigned to them.
+pub struct User {
+    pub id: UserId,
+    /// The human-friendly name of the user, e.g. `Alice Liddell`.
+    pub display_name: String,
+}
+
+impl std::fmt::Display for User {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "{} ({})", self.display_name, self.id)
+    }
+}
+
+#[cfg(test)]
+mod user_tests {
+    use crate::models::UserId;
+
+    #[test]
+    fn creating_an_empty_handle_should_fail() {
+        //arrange
+        //act
+        let handle = UserId::new("".to_string());
+
+        //assert
+        assert!(handle.is_err())
+    }
+
+    #[test]
+    fn handles_with_whitespace_are_rejected() {
+        //arrange
+        //act
+        let handle = UserId::new("alice liddell".to_string());
+
+        //assert
+        assert!(handle.is_err())
+    }
+
+    #[test]
+    fn handles_are_case_insensitive() {
+        //arrange
+        //act
+        let lowercase = UserId::new("alice".to_string()).unwrap();
+        let mixed_case = UserId::new("Alice".to_string()).unwrap();
+
+        //assert
+        assert_eq!(lowercase, mixed_case)
+    }
+
+    #[test]
+    fn deserialized_handles_are_validated_and_lowercased() {
+        //arrange
+        //act
+        let mixed_case: UserId = serde_yaml::from_str("Alice").unwrap();
+        let invalid: Result<UserId, _> = serde_yaml::from_str("alice liddell");
+
+        //assert
+        assert_eq!(mixed_case, UserId::new("alice".to_string()).unwrap());
+        assert!(invalid.is_err())
+    }
+}
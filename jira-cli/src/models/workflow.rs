@@ -0,0 +1,349 @@
+use crate::models::{Status, StatusCategory, TicketId};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+/// The statuses of a board and the rules governing how a [Ticket](Ticket) moves from one
+/// [Status](Status) to another.
+///
+/// A workflow is loaded from the `workflow` section of the configuration file, e.g.
+/// ```yaml
+/// workflow:
+///   statuses:
+///     - name: ToDo
+///       category: todo
+///     - name: In Progress
+///       category: in-progress
+///     - name: In Review
+///       category: in-progress
+///     - name: Done
+///       category: done
+///   transitions:
+///     ToDo: [In Progress]
+///     In Progress: [In Review]
+///     In Review: [In Progress, Done]
+///     Done: [In Progress]
+///   required:
+///     Done: [Comment]
+/// ```
+///
+/// The default workflow has the `ToDo`, `InProgress`, `Blocked` and `Done` statuses and no transitions:
+/// it allows every move and requires nothing.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Workflow {
+    /// The statuses a ticket can be in, in board order.
+    /// New tickets start in the first one.
+    #[serde(default = "default_statuses")]
+    pub statuses: Vec<StatusDefinition>,
+    /// For each status, the statuses a ticket can be moved to.
+    /// If there are no transitions at all, every move is allowed.
+    #[serde(default)]
+    pub transitions: HashMap<Status, Vec<Status>>,
+    /// For each status, the fields that must be provided when a ticket enters it.
+    #[serde(default)]
+    pub required: HashMap<Status, Vec<RequiredField>>,
+}
+
+/// A [Status](Status) configured in a [Workflow](Workflow).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StatusDefinition {
+    pub name: Status,
+    pub category: StatusCategory,
+}
+
+/// The statuses available before custom statuses were introduced.
+/// They keep existing data files, where tickets are in one of these statuses, valid.
+fn default_statuses() -> Vec<StatusDefinition> {
+    vec![
+        StatusDefinition {
+            name: Status::to_do(),
+            category: StatusCategory::ToDo,
+        },
+        StatusDefinition {
+            name: Status::in_progress(),
+            category: StatusCategory::InProgress,
+        },
+        StatusDefinition {
+            name: Status::blocked(),
+            category: StatusCategory::InProgress,
+        },
+        StatusDefinition {
+            name: Status::done(),
+            category: StatusCategory::Done,
+        },
+    ]
+}
+
+impl Default for Workflow {
+    fn default() -> Self {
+        Workflow {
+            statuses: default_statuses(),
+            transitions: HashMap::new(),
+            required: HashMap::new(),
+        }
+    }
+}
+
+/// Something that must be provided when a [Ticket](Ticket) enters a [Status](Status).
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum RequiredField {
+    /// A comment explaining the transition, e.g. a resolution when entering `Done`.
+    Comment,
+    /// The ticket must be assigned to somebody.
+    Assignee,
+}
+
+impl Workflow {
+    /// The status new tickets start in.
+    pub fn initial_status(&self) -> Status {
+        self.statuses
+            .first()
+            .map(|s| s.name.clone())
+            .unwrap_or_else(Status::to_do)
+    }
+
+    /// Find the configured status `input` refers to, see [Status::matches](Status::matches).
+    pub fn resolve(&self, input: &str) -> Option<Status> {
+        self.statuses
+            .iter()
+            .find(|s| s.name.matches(input))
+            .map(|s| s.name.clone())
+    }
+
+    /// The category of `status`. Returns `None` if `status` is not part of the workflow.
+    pub fn category(&self, status: &Status) -> Option<StatusCategory> {
+        self.statuses
+            .iter()
+            .find(|s| &s.name == status)
+            .map(|s| s.category)
+    }
+
+    /// The statuses a ticket in status `from` can be moved to.
+    pub fn next_statuses(&self, from: &Status) -> Vec<Status> {
+        if self.transitions.is_empty() {
+            self.statuses
+                .iter()
+                .map(|s| s.name.clone())
+                .filter(|s| s != from)
+                .collect()
+        } else {
+            self.transitions.get(from).cloned().unwrap_or_default()
+        }
+    }
+
+    /// Whether a ticket can be moved from `from` to `to`.
+    pub fn allows(&self, from: &Status, to: &Status) -> bool {
+        from == to || self.next_statuses(from).contains(to)
+    }
+
+    /// Check that the transitions and the required fields only refer to configured statuses.
+    pub fn validate(&self) -> Result<(), WorkflowError> {
+        let is_known = |status: &Status| self.statuses.iter().any(|s| &s.name == status);
+        for (from, to) in &self.transitions {
+            if let Some(status) = std::iter::once(from).chain(to).find(|s| !is_known(s)) {
+                return Err(WorkflowError::UnknownStatus {
+                    status: status.clone(),
+                    section: "transitions",
+                });
+            }
+        }
+        if let Some(status) = self.required.keys().find(|s| !is_known(s)) {
+            return Err(WorkflowError::UnknownStatus {
+                status: status.clone(),
+                section: "required",
+            });
+        }
+        Ok(())
+    }
+
+    /// The fields that must be provided to enter `status`.
+    pub fn required_fields(&self, status: &Status) -> &[RequiredField] {
+        self.required
+            .get(status)
+            .map(|fields| fields.as_slice())
+            .unwrap_or(&[])
+    }
+}
+
+/// Error if a ticket cannot be moved to a new status
+#[derive(PartialEq, Debug, Clone)]
+pub enum TransitionError {
+    /// There is no ticket with this id in the store.
+    TicketNotFound(TicketId),
+    /// The status is not part of the [Workflow](Workflow).
+    UnknownStatus(Status),
+    /// The [Workflow](Workflow) does not allow to go from `from` to `to`.
+    NotAllowed {
+        from: Status,
+        to: Status,
+        /// The statuses the ticket can be moved to instead.
+        allowed: Vec<Status>,
+    },
+    /// The [Workflow](Workflow) requires `field` to enter `status`.
+    MissingField {
+        status: Status,
+        field: RequiredField,
+    },
+}
+
+impl fmt::Display for TransitionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TransitionError::TicketNotFound(id) => {
+                write!(f, "There was no ticket associated to the ticket id {}", id)
+            }
+            TransitionError::UnknownStatus(status) => {
+                write!(f, "{} is not a status of the workflow", status)
+            }
+            TransitionError::NotAllowed { from, to, allowed } => {
+                let allowed: Vec<String> = allowed.iter().map(|s| s.to_string()).collect();
+                write!(
+                    f,
+                    "A ticket cannot be moved from {} to {}. Valid next statuses: {}",
+                    from,
+                    to,
+                    allowed.join(", ")
+                )
+            }
+            TransitionError::MissingField { status, field } => write!(
+                f,
+                "A ticket cannot be moved to {} without a {:?}",
+                status, field
+            ),
+        }
+    }
+}
+
+impl Error for TransitionError {}
+
+/// Error if a [Workflow](Workflow) is inconsistent
+#[derive(PartialEq, Debug, Clone)]
+pub enum WorkflowError {
+    /// A section of the workflow refers to a status that is not listed in `statuses`.
+    UnknownStatus {
+        status: Status,
+        section: &'static str,
+    },
+}
+
+impl fmt::Display for WorkflowError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WorkflowError::UnknownStatus { status, section } => write!(
+                f,
+                "The `{}` section of the workflow refers to {}, which is not one of its statuses",
+                section, status
+            ),
+        }
+    }
+}
+
+impl Error for WorkflowError {}
+
+#[cfg(test)]
+mod workflow_tests {
+    use crate::models::{Status, StatusCategory, Workflow, WorkflowError};
+
+    #[test]
+    fn the_default_workflow_allows_every_transition() {
+        //arrange
+        let workflow = Workflow::default();
+
+        //act
+        let next = workflow.next_statuses(&Status::done());
+
+        //assert
+        assert_eq!(
+            next,
+            vec![Status::to_do(), Status::in_progress(), Status::blocked()]
+        );
+    }
+
+    #[test]
+    fn only_configured_transitions_are_allowed() {
+        //arrange
+        let workflow: Workflow =
+            serde_yaml::from_str("transitions:\n  ToDo: [InProgress]\n  InProgress: [Done]")
+                .expect("Failed to parse workflow");
+
+        //act
+        //assert
+        assert!(workflow.allows(&Status::to_do(), &Status::in_progress()));
+        assert!(!workflow.allows(&Status::to_do(), &Status::done()));
+        assert!(workflow.next_statuses(&Status::done()).is_empty());
+    }
+
+    #[test]
+    fn custom_statuses_are_resolved_leniently() {
+        //arrange
+        let workflow: Workflow = serde_yaml::from_str(
+            "statuses:\n  - name: In Review\n    category: in-progress\n  - name: QA\n    category: in-progress",
+        )
+        .expect("Failed to parse workflow");
+
+        //act
+        let status = workflow.resolve("in-review");
+
+        //assert
+        assert_eq!(status, Some(Status::new("In Review")));
+        assert_eq!(
+            workflow.category(&Status::new("QA")),
+            Some(StatusCategory::InProgress)
+        );
+        assert_eq!(workflow.resolve("Done"), None);
+        assert_eq!(workflow.initial_status(), Status::new("In Review"));
+    }
+
+    #[test]
+    fn transitions_to_unknown_statuses_are_rejected() {
+        //arrange
+        let workflow: Workflow =
+            serde_yaml::from_str("transitions:\n  ToDo: [In Review]\n  InProgress: [Done]")
+                .expect("Failed to parse workflow");
+
+        //act
+        let result = workflow.validate();
+
+        //assert
+        assert_eq!(
+            result,
+            Err(WorkflowError::UnknownStatus {
+                status: Status::new("In Review"),
+                section: "transitions",
+            })
+        );
+    }
+
+    #[test]
+    fn required_fields_of_unknown_statuses_are_rejected() {
+        //arrange
+        let workflow: Workflow = serde_yaml::from_str("required:\n  Closed: [Comment]")
+            .expect("Failed to parse workflow");
+
+        //act
+        let result = workflow.validate();
+
+        //assert
+        assert!(matches!(
+            result,
+            Err(WorkflowError::UnknownStatus {
+                section: "required",
+                ..
+            })
+        ));
+        assert!(Workflow::default().validate().is_ok());
+    }
+
+    #[test]
+    fn blank_status_names_are_rejected() {
+        //arrange
+        //act
+        let padded: Status = serde_yaml::from_str("' In Review '").unwrap();
+        let blank: Result<Status, _> = serde_yaml::from_str("' '");
+
+        //assert
+        assert_eq!(padded, Status::new("In Review"));
+        assert!(blank.is_err())
+    }
+}
@@ -0,0 +1,290 @@
+use crate::models::{TicketId, UserId};
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::fmt::Formatter;
+use std::str::FromStr;
+
+const MINUTES_PER_HOUR: u64 = 60;
+/// As in Jira, a day of work is 8 hours...
+const MINUTES_PER_DAY: u64 = 8 * MINUTES_PER_HOUR;
+/// ...and a week of work is 5 days.
+const MINUTES_PER_WEEK: u64 = 5 * MINUTES_PER_DAY;
+
+#[derive(
+    PartialEq, Debug, Copy, Clone, Hash, Eq, PartialOrd, Ord, Default, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+/// An amount of working time, with a precision of one minute.
+/// It is written with weeks, days, hours and minutes, e.g. `1w 2d 3h 30m`,
+/// where a week is 5 days and a day is 8 hours.
+pub struct WorkDuration {
+    minutes: u64,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+/// Error if a duration cannot be parsed
+pub struct WorkDurationError {
+    details: String,
+}
+
+impl fmt::Display for WorkDurationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.details)
+    }
+}
+
+impl Error for WorkDurationError {
+    fn description(&self) -> &str {
+        &self.details
+    }
+}
+
+impl WorkDuration {
+    pub fn from_minutes(minutes: u64) -> WorkDuration {
+        WorkDuration { minutes }
+    }
+
+    pub fn minutes(&self) -> u64 {
+        self.minutes
+    }
+
+    /// The time left after spending `spent`, zero if `spent` is longer than `self`.
+    pub fn saturating_sub(self, spent: WorkDuration) -> WorkDuration {
+        WorkDuration::from_minutes(self.minutes.saturating_sub(spent.minutes))
+    }
+
+    /// The sum of both durations, `None` if it is too long to be represented.
+    pub fn checked_add(self, other: WorkDuration) -> Option<WorkDuration> {
+        self.minutes
+            .checked_add(other.minutes)
+            .map(WorkDuration::from_minutes)
+    }
+}
+
+/// Durations saturate rather than overflow: a sum too long to be represented is the longest duration.
+impl std::ops::Add for WorkDuration {
+    type Output = WorkDuration;
+
+    fn add(self, other: WorkDuration) -> WorkDuration {
+        WorkDuration::from_minutes(self.minutes.saturating_add(other.minutes))
+    }
+}
+
+impl std::ops::AddAssign for WorkDuration {
+    fn add_assign(&mut self, other: WorkDuration) {
+        *self = *self + other;
+    }
+}
+
+/// Parses durations such as `1h30m`, `1h 30m`, `2d` or `1w 4h`.
+/// Each component is a number followed by a unit: `w`, `d`, `h` or `m`.
+impl FromStr for WorkDuration {
+    type Err = WorkDurationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| WorkDurationError {
+            details: format!(
+                "{} is not a valid duration - {} (e.g. 1h30m, 2d or 1w 4h)",
+                s, reason
+            ),
+        };
+        let minutes = parse_components(
+            s,
+            &[
+                ('w', MINUTES_PER_WEEK),
+                ('d', MINUTES_PER_DAY),
+                ('h', MINUTES_PER_HOUR),
+                ('m', 1),
+            ],
+        )
+        .map_err(invalid)?;
+        Ok(WorkDuration { minutes })
+    }
+}
+
+/// Sum the components of a duration such as `1w 4h`, each being a number followed by one of `units`,
+/// which are given with their size. Returns the reason why `s` is not valid otherwise.
+pub(crate) fn parse_components(s: &str, units: &[(char, u64)]) -> Result<u64, &'static str> {
+    let mut total: u64 = 0;
+    let mut number = String::new();
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        if c.is_whitespace() {
+            if number.is_empty() {
+                continue;
+            }
+            return Err("a number is missing its unit");
+        }
+        let unit = units
+            .iter()
+            .find(|(unit, _)| *unit == c.to_ascii_lowercase())
+            .map(|(_, size)| *size)
+            .ok_or("units are w, d, h and m")?;
+        if number.is_empty() {
+            return Err("a unit is missing its number");
+        }
+        let value: u64 = number.parse().map_err(|_| "a number is too large")?;
+        total = value
+            .checked_mul(unit)
+            .and_then(|minutes| total.checked_add(minutes))
+            .ok_or("it is too long")?;
+        number.clear();
+    }
+    if !number.is_empty() {
+        return Err("a number is missing its unit");
+    }
+    if s.trim().is_empty() {
+        return Err("it is empty");
+    }
+    Ok(total)
+}
+
+impl std::fmt::Display for WorkDuration {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        if self.minutes == 0 {
+            return write!(f, "0m");
+        }
+        let mut components = Vec::new();
+        let mut left = self.minutes;
+        for (unit, size) in [
+            ("w", MINUTES_PER_WEEK),
+            ("d", MINUTES_PER_DAY),
+            ("h", MINUTES_PER_HOUR),
+            ("m", 1),
+        ]
+        .iter()
+        {
+            if left >= *size {
+                components.push(format!("{}{}", left / size, unit));
+                left %= size;
+            }
+        }
+        write!(f, "{}", components.join(" "))
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Hash, Eq, Serialize, Deserialize)]
+/// Time spent by somebody working on a [Ticket](crate::models::Ticket).
+pub struct Worklog {
+    pub author: UserId,
+    pub time_spent: WorkDuration,
+    /// When the work started.
+    pub started_at: DateTime<Utc>,
+    /// What was done, e.g. "Reproduced the bug".
+    pub note: Option<String>,
+}
+
+/// Error if time cannot be logged on a [Ticket](crate::models::Ticket)
+#[derive(PartialEq, Debug, Clone)]
+pub enum WorklogError {
+    /// There is no ticket with this id in the store.
+    TicketNotFound(TicketId),
+    /// The total time spent on the ticket would be too long to be represented.
+    TooLong(TicketId),
+}
+
+impl fmt::Display for WorklogError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WorklogError::TicketNotFound(id) => {
+                write!(f, "There was no ticket associated to the ticket id {}", id)
+            }
+            WorklogError::TooLong(id) => write!(
+                f,
+                "The time spent on ticket {} would be too long - the worklog was not recorded",
+                id
+            ),
+        }
+    }
+}
+
+impl Error for WorklogError {}
+
+impl std::fmt::Display for Worklog {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(
+            f,
+            "{} {} logged {}",
+            self.started_at
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M"),
+            self.author,
+            self.time_spent
+        )?;
+        if let Some(note) = &self.note {
+            write!(f, ": {}", note)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod worklog_tests {
+    use crate::models::WorkDuration;
+
+    #[test]
+    fn durations_are_parsed_from_their_components() {
+        //arrange
+        //act
+        let duration: WorkDuration = "1d 2h30m".parse().unwrap();
+
+        //assert
+        assert_eq!(duration.minutes(), 8 * 60 + 2 * 60 + 30)
+    }
+
+    #[test]
+    fn numbers_without_unit_are_rejected() {
+        //arrange
+        //act
+        let duration = "1h 30".parse::<WorkDuration>();
+
+        //assert
+        assert!(duration.is_err())
+    }
+
+    #[test]
+    fn durations_that_overflow_are_rejected() {
+        //arrange
+        //act
+        let too_long = "99999999999999999w".parse::<WorkDuration>();
+        let too_large = "99999999999999999999999m".parse::<WorkDuration>();
+
+        //assert
+        assert!(too_long.unwrap_err().to_string().contains("it is too long"));
+        assert!(too_large
+            .unwrap_err()
+            .to_string()
+            .contains("a number is too large"));
+    }
+
+    #[test]
+    fn sums_of_long_durations_saturate() {
+        //arrange
+        let long: WorkDuration = "4000000000000000w".parse().unwrap();
+
+        //act
+        let checked = long.checked_add(long);
+        let sum = long + long;
+
+        //assert
+        assert_eq!(checked, None);
+        assert_eq!(sum.minutes(), u64::MAX);
+    }
+
+    #[test]
+    fn durations_are_displayed_in_the_largest_units() {
+        //arrange
+        let duration: WorkDuration = "90m".parse().unwrap();
+
+        //act
+        let displayed = duration.to_string();
+
+        //assert
+        assert_eq!(displayed, "1h 30m")
+    }
+}
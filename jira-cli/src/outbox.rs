@@ -0,0 +1,334 @@
+//! Delivery of [notifications](Notification) to the [sinks](Sink) configured by the user.
+use crate::config::{Notifications, Sink};
+use crate::models::{Notification, NotificationId, TicketId, UserId};
+use crate::store::TicketStore;
+use serde::Serialize;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// A notification on its way out of IronJira, with the details a message needs.
+#[derive(Serialize, Debug)]
+pub struct Message<'a> {
+    pub recipient: &'a UserId,
+    /// The display name of the recipient, if known.
+    pub recipient_name: Option<&'a str>,
+    /// The e-mail address of the recipient: their handle at the configured domain.
+    pub address: String,
+    #[serde(flatten)]
+    pub notification: &'a Notification,
+    /// The title of the ticket, `None` if it was deleted since.
+    pub title: Option<String>,
+    /// What happened, see [Notification::summary].
+    pub summary: String,
+}
+
+/// Somewhere notifications can be written to.
+pub trait NotificationSink {
+    fn deliver(&mut self, message: &Message) -> std::io::Result<()>;
+}
+
+/// Writes every notification as an RFC 5322 message file in a maildir.
+pub struct MaildirSink {
+    path: PathBuf,
+    domain: String,
+}
+
+impl NotificationSink for MaildirSink {
+    fn deliver(&mut self, message: &Message) -> std::io::Result<()> {
+        for sub_directory in ["tmp", "new", "cur"].iter() {
+            std::fs::create_dir_all(self.path.join(sub_directory))?;
+        }
+        // As maildir requires, the message is written in `tmp` and then moved to `new` in one step,
+        // so that mail readers never see a partial message.
+        let name = format!(
+            "{}.P{}N{}.ironjira",
+            message.notification.at.timestamp(),
+            std::process::id(),
+            message.notification.id
+        );
+        let temporary = self.path.join("tmp").join(&name);
+        std::fs::write(&temporary, rfc5322(message, &self.domain))?;
+        std::fs::rename(temporary, self.path.join("new").join(name))
+    }
+}
+
+/// Appends every notification to a file, as a line of JSON.
+pub struct NdjsonSink {
+    path: PathBuf,
+}
+
+impl NotificationSink for NdjsonSink {
+    fn deliver(&mut self, message: &Message) -> std::io::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        let line = serde_json::to_string(message).expect("Failed to serialize notification");
+        writeln!(file, "{}", line)
+    }
+}
+
+/// The sinks configured by the user.
+pub fn sinks(notifications: &Notifications) -> Vec<Box<dyn NotificationSink>> {
+    notifications
+        .sinks
+        .iter()
+        .map(|sink| -> Box<dyn NotificationSink> {
+            match sink {
+                Sink::Maildir { path } => Box::new(MaildirSink {
+                    path: path.clone(),
+                    domain: notifications.domain.clone(),
+                }),
+                Sink::Ndjson { path } => Box::new(NdjsonSink { path: path.clone() }),
+            }
+        })
+        .collect()
+}
+
+/// Write the notifications sent after the one with id `after` to every configured sink.
+/// Returns the number of notifications delivered.
+pub fn deliver(
+    ticket_store: &TicketStore,
+    after: NotificationId,
+    notifications: &Notifications,
+) -> std::io::Result<usize> {
+    let mut sinks = sinks(notifications);
+    if sinks.is_empty() {
+        return Ok(0);
+    }
+    let pending = ticket_store.notifications_since(after);
+    for (recipient, notification) in pending.iter() {
+        let message = Message {
+            recipient,
+            recipient_name: ticket_store
+                .get_user(recipient)
+                .map(|u| u.display_name.as_str()),
+            address: address(recipient, &notifications.domain),
+            notification,
+            title: title(ticket_store, &notification.ticket),
+            summary: notification.summary(),
+        };
+        for sink in sinks.iter_mut() {
+            sink.deliver(&message)?;
+        }
+    }
+    Ok(pending.len())
+}
+
+fn address(user: &UserId, domain: &str) -> String {
+    format!("{}@{}", user, domain)
+}
+
+fn title(ticket_store: &TicketStore, id: &TicketId) -> Option<String> {
+    ticket_store.get(id).map(|t| t.title.to_string())
+}
+
+/// Format a message as an Internet Message (RFC 5322), lines ending with CRLF.
+fn rfc5322(message: &Message, domain: &str) -> String {
+    let notification = message.notification;
+    let to = match message.recipient_name {
+        Some(name) => format!("{} <{}>", display_name(name), message.address),
+        None => format!("<{}>", message.address),
+    };
+    let subject = match &message.title {
+        Some(title) => format!("[{}] {}", notification.ticket, title),
+        None => format!("[{}]", notification.ticket),
+    };
+    let headers = [
+        format!("From: IronJira <ironjira@{}>", domain),
+        format!("To: {}", to),
+        format!("Date: {}", notification.at.to_rfc2822()),
+        format!(
+            "Message-ID: <notification-{}.{}@{}>",
+            notification.id,
+            notification.at.timestamp(),
+            domain
+        ),
+        format!("Subject: {}", encode_header(&subject)),
+        "MIME-Version: 1.0".to_string(),
+        "Content-Type: text/plain; charset=utf-8".to_string(),
+        "Content-Transfer-Encoding: 8bit".to_string(),
+    ];
+    format!("{}\r\n\r\n{}\r\n", headers.join("\r\n"), message.summary)
+}
+
+/// The longest an RFC 2047 encoded word can be, delimiters included.
+const ENCODED_WORD_LENGTH: usize = 75;
+const ENCODED_WORD_PREFIX: &str = "=?UTF-8?Q?";
+const ENCODED_WORD_SUFFIX: &str = "?=";
+
+/// Header values cannot span lines: line breaks and other control characters, e.g. in a ticket
+/// title, become spaces so that they cannot start a new header.
+fn unfold(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect()
+}
+
+/// Header values must be ASCII: other text is written as RFC 2047 encoded words, in the `Q` encoding.
+/// Encoded words are at most [ENCODED_WORD_LENGTH] long, each on its own folded line.
+fn encode_header(value: &str) -> String {
+    let value = unfold(value);
+    if value.is_ascii() {
+        return value;
+    }
+    let max_length = ENCODED_WORD_LENGTH - ENCODED_WORD_PREFIX.len() - ENCODED_WORD_SUFFIX.len();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for c in value.chars() {
+        // A character is never split across words, as each word must be valid UTF-8 on its own.
+        let mut buffer = [0; 4];
+        let encoded: String = c
+            .encode_utf8(&mut buffer)
+            .bytes()
+            .map(encode_byte)
+            .collect();
+        if word.len() + encoded.len() > max_length {
+            words.push(std::mem::take(&mut word));
+        }
+        word.push_str(&encoded);
+    }
+    words.push(word);
+    words
+        .iter()
+        .map(|word| format!("{}{}{}", ENCODED_WORD_PREFIX, word, ENCODED_WORD_SUFFIX))
+        .collect::<Vec<String>>()
+        .join("\r\n ")
+}
+
+fn encode_byte(byte: u8) -> String {
+    match byte {
+        b' ' => "_".to_string(),
+        b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' => (byte as char).to_string(),
+        _ => format!("={:02X}", byte),
+    }
+}
+
+/// The display name of an address. It is quoted if it contains a character with a special meaning
+/// in addresses, e.g. the `,` of `Smith, Alice`, which would otherwise separate two addresses.
+fn display_name(name: &str) -> String {
+    let name = unfold(name);
+    if !name.is_ascii() {
+        encode_header(&name)
+    } else if name.chars().any(|c| "()<>[]:;@\\,.\"".contains(c)) {
+        format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        name
+    }
+}
+
+#[cfg(test)]
+mod outbox_tests {
+    use crate::models::{Notification, NotificationReason, TicketId, UserId};
+    use crate::outbox::{display_name, encode_header, rfc5322, Message};
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn non_ascii_headers_are_encoded() {
+        //arrange
+        //act
+        let encoded = encode_header("Crash in café");
+
+        //assert
+        assert_eq!(encoded, "=?UTF-8?Q?Crash_in_caf=C3=A9?=")
+    }
+
+    #[test]
+    fn long_encoded_headers_are_split_into_short_words() {
+        //arrange
+        let value = "é".repeat(15);
+
+        //act
+        let encoded = encode_header(&value);
+
+        //assert
+        let words: Vec<&str> = encoded.split("\r\n ").collect();
+        assert_eq!(words.len(), 2);
+        assert!(words.iter().all(|w| w.len() <= 75));
+        assert_eq!(words[0], format!("=?UTF-8?Q?{}?=", "=C3=A9".repeat(10)));
+    }
+
+    #[test]
+    fn display_names_with_specials_are_quoted() {
+        //arrange
+        //act
+        let quoted = display_name("Smith, \"Ally\" Alice");
+        let plain = display_name("Alice Liddell");
+
+        //assert
+        assert_eq!(quoted, "\"Smith, \\\"Ally\\\" Alice\"");
+        assert_eq!(plain, "Alice Liddell");
+    }
+
+    fn notification() -> Notification {
+        Notification {
+            id: 7,
+            ticket: "CORE-1".parse::<TicketId>().unwrap(),
+            reason: NotificationReason::Edited,
+            actor: None,
+            at: Utc.ymd(2020, 3, 2).and_hms(9, 0, 0),
+            read: false,
+        }
+    }
+
+    fn message<'a>(
+        recipient: &'a UserId,
+        recipient_name: &'a str,
+        notification: &'a Notification,
+        title: &str,
+    ) -> Message<'a> {
+        Message {
+            recipient,
+            recipient_name: Some(recipient_name),
+            address: format!("{}@example.com", recipient),
+            notification,
+            title: Some(title.to_string()),
+            summary: notification.summary(),
+        }
+    }
+
+    #[test]
+    fn messages_have_headers_and_a_body_separated_by_an_empty_line() {
+        //arrange
+        let alice = UserId::new("alice".to_string()).unwrap();
+        let notification = notification();
+        let message = message(&alice, "Alice Liddell", &notification, "Login fails");
+
+        //act
+        let formatted = rfc5322(&message, "example.com");
+
+        //assert
+        let (headers, body) = formatted.split_at(formatted.find("\r\n\r\n").unwrap());
+        assert!(headers.contains("To: Alice Liddell <alice@example.com>\r\n"));
+        assert!(headers.contains("Subject: [CORE-1] Login fails\r\n"));
+        assert!(headers.contains("Date: Mon, 02 Mar 2020 09:00:00 +0000\r\n"));
+        assert_eq!(body, "\r\n\r\nsomeone edited CORE-1\r\n");
+    }
+
+    #[test]
+    fn line_breaks_in_header_values_cannot_inject_headers() {
+        //arrange
+        let alice = UserId::new("alice".to_string()).unwrap();
+        let notification = notification();
+        let message = message(
+            &alice,
+            "Smith, Alice\r\nCc: eve@attacker.example",
+            &notification,
+            "Bad\r\nBcc: evil@attacker.example",
+        );
+
+        //act
+        let formatted = rfc5322(&message, "example.com");
+
+        //assert
+        let headers = &formatted[..formatted.find("\r\n\r\n").unwrap()];
+        assert!(headers
+            .split("\r\n")
+            .all(|line| !line.starts_with("Bcc:") && !line.starts_with("Cc:")));
+        assert!(headers.contains("Subject: [CORE-1] Bad  Bcc: evil@attacker.example\r\n"));
+        assert!(headers
+            .contains("To: \"Smith, Alice  Cc: eve@attacker.example\" <alice@example.com>\r\n"));
+    }
+}
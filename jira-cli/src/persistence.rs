@@ -1,7 +1,15 @@
+use crate::config::{Config, Storage, StorageMode};
+use crate::models::{Blob, Event, SavedFilter, UndoStack};
+use crate::search::SearchIndex;
 use crate::store::TicketStore;
 use directories::ProjectDirs;
-use std::fs::read_to_string;
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fs::{read_to_string, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
 
 // `PROJECT_NAME`, `ORGANISATION_NAME` and `QUALIFIER` are used to determine
 // where to store configuration files and secrets for an application
@@ -13,8 +21,13 @@ const ORGANISATION_NAME: &str = "RustLDNUserGroup";
 const QUALIFIER: &str = "";
 
 const TICKET_STORE: &str = "ticket_store.yaml";
+const EVENT_LOG: &str = "events.ndjson";
+const SEARCH_INDEX: &str = "search_index.json";
+const UNDO_STACK: &str = "undo.yaml";
+const CONFIG: &str = "config.yaml";
+const ATTACHMENTS: &str = "attachments";
 
-fn data_store_filename() -> PathBuf {
+fn data_dir() -> PathBuf {
     // Get the directory where we are supposed to store data
     // according to the convention of the underlying operating system.
     //
@@ -27,20 +40,50 @@ fn data_store_filename() -> PathBuf {
     // Create the data directory, if missing.
     // It also takes care of creating intermediate sub-directory, if necessary.
     std::fs::create_dir_all(data_dir).expect("Failed to create data directory.");
+    data_dir.to_path_buf()
+}
+
+fn config_filename() -> PathBuf {
+    let project_dir = ProjectDirs::from(QUALIFIER, ORGANISATION_NAME, PROJECT_NAME)
+        .expect("Failed to determine path of the configuration directory.");
+    project_dir.config_dir().join(CONFIG)
+}
 
-    // Path to the file storing our tickets
-    data_dir.join(TICKET_STORE)
+/// Fetch the user settings from the configuration file, if available.
+pub fn load_config() -> Config {
+    let filename = config_filename();
+    match read_to_string(&filename) {
+        Ok(data) => {
+            println!("Reading configuration from {:?}", filename);
+            let config: Config =
+                serde_yaml::from_str(&data).expect("Failed to parse configuration.");
+            if let Err(e) = config.workflow.validate() {
+                panic!("Invalid workflow in configuration: {}", e);
+            }
+            config
+        }
+        Err(e) => match e.kind() {
+            // No configuration file - fall back to the defaults.
+            std::io::ErrorKind::NotFound => Config::default(),
+            _ => panic!("Failed to read configuration."),
+        },
+    }
 }
 
-/// Fetch authentication parameters from a configuration file, if available.
+/// Fetch the tickets from the latest snapshot, then replay the events logged since then.
 pub fn load() -> TicketStore {
-    let filename = data_store_filename();
+    let data_dir = data_dir();
+    let filename = data_dir.join(TICKET_STORE);
     // Read the data in memory, storing the value in a string
     println!("Reading data from {:?}", filename);
-    match read_to_string(filename) {
+    let mut ticket_store = match read_to_string(filename) {
         Ok(data) => {
             // Deserialize configuration from YAML format
-            serde_yaml::from_str(&data).expect("Failed to parse serialised data.")
+            let mut ticket_store: TicketStore =
+                serde_yaml::from_str(&data).expect("Failed to parse serialised data.");
+            // Bring data saved by older versions up to date
+            ticket_store.upgrade();
+            ticket_store
         }
         Err(e) => match e.kind() {
             // The file is missing - this is the first time you are using IronJira!
@@ -51,15 +94,215 @@ pub fn load() -> TicketStore {
             // Something went wrong - crash the CLI with an error message.
             _ => panic!("Failed to read data."),
         },
+    };
+    // The search index is saved with the revision of the snapshot it matches: replayed events keep it
+    // up to date. It is only rebuilt if it is missing or stale, e.g. after a crash.
+    match read_index(&data_dir.join(SEARCH_INDEX)) {
+        Some(saved) if saved.revision == ticket_store.revision() => {
+            ticket_store.set_index(saved.index)
+        }
+        _ => {
+            println!("Rebuilding the search index");
+            ticket_store.build_index()
+        }
+    }
+    for event in read_events(&data_dir.join(EVENT_LOG)) {
+        let sequence = event.sequence;
+        ticket_store
+            .replay(event)
+            .unwrap_or_else(|| panic!("Failed to replay event {} of the event log.", sequence));
+    }
+    ticket_store
+}
+
+/// The search index on disk, with the [revision](TicketStore::revision) of the snapshot it matches.
+#[derive(Serialize, Deserialize)]
+struct SavedIndex<I> {
+    revision: u64,
+    index: I,
+}
+
+/// Read the saved search index. A missing or unreadable index is rebuilt, so it is `None`.
+fn read_index(filename: &Path) -> Option<SavedIndex<SearchIndex>> {
+    let data = read_to_string(filename).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+fn save_index(ticket_store: &TicketStore, filename: &Path) {
+    let saved = SavedIndex {
+        revision: ticket_store.revision(),
+        index: ticket_store.index(),
+    };
+    let content = serde_json::to_string(&saved).expect("Failed to serialize the search index");
+    let temporary = filename.with_extension("json.tmp");
+    std::fs::write(&temporary, content).expect("Failed to write the search index to disk.");
+    std::fs::rename(temporary, filename).expect("Failed to write the search index to disk.")
+}
+
+/// Read the event log, one JSON event per line. A missing log has no events.
+fn read_events(filename: &Path) -> Vec<Event> {
+    match read_to_string(filename) {
+        Ok(data) => data
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).expect("Failed to parse the event log."))
+            .collect(),
+        Err(e) => match e.kind() {
+            std::io::ErrorKind::NotFound => Vec::new(),
+            _ => panic!("Failed to read the event log."),
+        },
     }
 }
 
-/// Save tickets on disk in the right file.
-pub fn save(ticket_store: &TicketStore) {
-    let filename = data_store_filename();
+/// Save tickets on disk in the right file(s), according to the [Storage] settings.
+///
+/// In [event-log](StorageMode::EventLog) mode, the events applied by the command are appended to the log.
+/// A snapshot of the whole store is saved instead, and the log is emptied, when the log gets longer
+/// than `snapshot_every` events or when the store was changed in a way events do not capture.
+///
+/// The search index is saved with every snapshot. Otherwise, it is only saved if it was rebuilt:
+/// the logged events bring it up to date when the store is loaded.
+pub fn save(ticket_store: &mut TicketStore, storage: &Storage) {
+    let data_dir = data_dir();
+    let log_filename = data_dir.join(EVENT_LOG);
+    let events = ticket_store.take_events();
+    if storage.mode == StorageMode::EventLog && !ticket_store.needs_snapshot() {
+        let logged = read_events(&log_filename).len();
+        if logged + events.len() < storage.snapshot_every {
+            append_events(&log_filename, &events);
+            if ticket_store.index_rebuilt() {
+                save_index(ticket_store, &data_dir.join(SEARCH_INDEX));
+            }
+            return;
+        }
+    }
+    ticket_store.next_revision();
+    save_snapshot(ticket_store, &data_dir.join(TICKET_STORE));
+    save_index(ticket_store, &data_dir.join(SEARCH_INDEX));
+    // Every logged event is part of the snapshot: compact the log.
+    if log_filename.exists() {
+        std::fs::remove_file(log_filename).expect("Failed to compact the event log.");
+    }
+}
+
+fn append_events(filename: &Path, events: &[Event]) {
+    if events.is_empty() {
+        return;
+    }
+    println!("Appending {} event(s) to {:?}", events.len(), filename);
+    let mut log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(filename)
+        .expect("Failed to open the event log.");
+    for event in events {
+        let line = serde_json::to_string(event).expect("Failed to serialize event");
+        writeln!(log, "{}", line).expect("Failed to write the event log.");
+    }
+}
+
+fn save_snapshot(ticket_store: &TicketStore, filename: &Path) {
     // Serialize data to YAML format
     let content = serde_yaml::to_string(ticket_store).expect("Failed to serialize tickets");
-    // Save to disk
+    // Save to disk: write a temporary file first, so that a crash cannot leave a truncated snapshot.
     println!("Saving tickets to {:?}", filename);
-    std::fs::write(filename, content).expect("Failed to write tickets to disk.")
+    let temporary = filename.with_extension("yaml.tmp");
+    std::fs::write(&temporary, content).expect("Failed to write tickets to disk.");
+    std::fs::rename(temporary, filename).expect("Failed to write tickets to disk.")
+}
+
+/// Fetch the commands that can be undone and redone, if any.
+pub fn load_undo_stack() -> UndoStack {
+    let filename = data_dir().join(UNDO_STACK);
+    match read_to_string(filename) {
+        Ok(data) => serde_yaml::from_str(&data).expect("Failed to parse the undo stack."),
+        Err(e) => match e.kind() {
+            // Nothing was done yet - there is nothing to undo.
+            std::io::ErrorKind::NotFound => UndoStack::default(),
+            _ => panic!("Failed to read the undo stack."),
+        },
+    }
+}
+
+/// Save the commands that can be undone and redone, next to the tickets.
+pub fn save_undo_stack(undo_stack: &UndoStack) {
+    let filename = data_dir().join(UNDO_STACK);
+    let content = serde_yaml::to_string(undo_stack).expect("Failed to serialize the undo stack");
+    std::fs::write(filename, content).expect("Failed to write the undo stack to disk.")
+}
+
+/// Write filter definitions to a YAML file, for other people to import them.
+pub fn export_filters(filters: &[&SavedFilter], destination: &Path) -> Result<(), Box<dyn Error>> {
+    let content = serde_yaml::to_string(filters)?;
+    std::fs::write(destination, content)?;
+    Ok(())
+}
+
+/// Read filter definitions written by [export_filters].
+pub fn import_filters(source: &Path) -> Result<Vec<SavedFilter>, Box<dyn Error>> {
+    let content = read_to_string(source)?;
+    Ok(serde_yaml::from_str(&content)?)
+}
+
+/// The directory attached files are saved in, next to the tickets.
+fn attachment_dir() -> PathBuf {
+    let attachment_dir = data_dir().join(ATTACHMENTS);
+    std::fs::create_dir_all(&attachment_dir).expect("Failed to create the attachment directory.");
+    attachment_dir
+}
+
+fn hash(content: &[u8]) -> String {
+    Sha256::digest(content)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Copy a file to the attachment directory, unless a file with the same content is already there.
+pub fn save_blob(file: &Path) -> std::io::Result<Blob> {
+    let content = std::fs::read(file)?;
+    let blob = Blob {
+        hash: hash(&content),
+        size: content.len() as u64,
+    };
+    let filename = attachment_dir().join(&blob.hash);
+    if !filename.exists() {
+        println!("Saving attachment to {:?}", filename);
+        let temporary = filename.with_extension("tmp");
+        std::fs::write(&temporary, content)?;
+        std::fs::rename(temporary, filename)?;
+    }
+    Ok(blob)
+}
+
+/// Copy the content of a [Blob] out of the attachment directory to `destination`.
+/// Fails if the content is missing or was corrupted since it was saved.
+pub fn extract_blob(blob: &Blob, destination: &Path) -> std::io::Result<()> {
+    let content = std::fs::read(attachment_dir().join(&blob.hash))?;
+    if hash(&content) != blob.hash {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("The attachment {} is corrupted", blob.hash),
+        ));
+    }
+    std::fs::write(destination, content)
+}
+
+/// Delete the files of the attachment directory whose hash is not `referenced`,
+/// and return their hashes.
+pub fn collect_garbage(referenced: &BTreeSet<&str>) -> std::io::Result<Vec<String>> {
+    let mut removed = Vec::new();
+    for entry in std::fs::read_dir(attachment_dir())? {
+        let path = entry?.path();
+        let name = match path.file_name().and_then(|n| n.to_str()) {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+        if !referenced.contains(name.as_str()) {
+            std::fs::remove_file(&path)?;
+            removed.push(name);
+        }
+    }
+    removed.sort();
+    Ok(removed)
 }
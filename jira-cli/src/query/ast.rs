@@ -0,0 +1,347 @@
+use crate::models::{CustomField, CustomFieldKind, CustomFieldName};
+use std::fmt;
+
+/// A field of tickets that can be used in a query.
+#[derive(PartialEq, Debug, Clone, Hash, Eq)]
+pub enum Field {
+    /// The id of the ticket, e.g. `CORE-1`. Also known as `id`.
+    Key,
+    Project,
+    /// Also known as `summary`.
+    Title,
+    Description,
+    Status,
+    Priority,
+    /// Also known as `type`.
+    Kind,
+    Assignee,
+    Reporter,
+    /// Also known as `label`.
+    Labels,
+    Sprint,
+    Parent,
+    /// Also known as `watcher`.
+    Watchers,
+    /// A [CustomField] of the store, e.g. `story-points`.
+    Custom {
+        name: CustomFieldName,
+        kind: CustomFieldKind,
+    },
+}
+
+impl Field {
+    pub const ALL: [Field; 13] = [
+        Field::Key,
+        Field::Project,
+        Field::Title,
+        Field::Description,
+        Field::Status,
+        Field::Priority,
+        Field::Kind,
+        Field::Assignee,
+        Field::Reporter,
+        Field::Labels,
+        Field::Sprint,
+        Field::Parent,
+        Field::Watchers,
+    ];
+
+    /// The name of the field in queries, e.g. `labels`.
+    pub fn name(&self) -> &str {
+        match self {
+            Field::Key => "key",
+            Field::Project => "project",
+            Field::Title => "title",
+            Field::Description => "description",
+            Field::Status => "status",
+            Field::Priority => "priority",
+            Field::Kind => "kind",
+            Field::Assignee => "assignee",
+            Field::Reporter => "reporter",
+            Field::Labels => "labels",
+            Field::Sprint => "sprint",
+            Field::Parent => "parent",
+            Field::Watchers => "watchers",
+            Field::Custom { name, .. } => name.as_str(),
+        }
+    }
+
+    /// The names of the built-in fields and of `custom_fields`, e.g. for error messages.
+    pub fn names(custom_fields: &[&CustomField]) -> String {
+        let names: Vec<&str> = Field::ALL
+            .iter()
+            .map(|f| f.name())
+            .chain(custom_fields.iter().map(|f| f.name.as_str()))
+            .collect();
+        names.join(", ")
+    }
+
+    /// The built-in field or custom field with this name, if any.
+    pub fn resolve(name: &str, custom_fields: &[&CustomField]) -> Option<Field> {
+        Field::from_name(name).or_else(|| {
+            custom_fields
+                .iter()
+                .find(|f| f.name.as_str().eq_ignore_ascii_case(name))
+                .map(|f| Field::Custom {
+                    name: f.name.clone(),
+                    kind: f.kind,
+                })
+        })
+    }
+
+    /// The built-in field with this name or alias, e.g. `Field::Labels` for `label`, if any.
+    pub fn from_name(name: &str) -> Option<Field> {
+        let field = match name.to_lowercase().as_str() {
+            "key" | "id" => Field::Key,
+            "project" => Field::Project,
+            "title" | "summary" => Field::Title,
+            "description" => Field::Description,
+            "status" => Field::Status,
+            "priority" => Field::Priority,
+            "kind" | "type" => Field::Kind,
+            "assignee" => Field::Assignee,
+            "reporter" => Field::Reporter,
+            "labels" | "label" => Field::Labels,
+            "sprint" => Field::Sprint,
+            "parent" => Field::Parent,
+            "watchers" | "watcher" => Field::Watchers,
+            _ => return None,
+        };
+        Some(field)
+    }
+
+    /// Whether the field is free text, that can be searched with `~`.
+    pub fn is_text(&self) -> bool {
+        matches!(
+            self,
+            Field::Title
+                | Field::Description
+                | Field::Custom {
+                    kind: CustomFieldKind::Text,
+                    ..
+                }
+        )
+    }
+
+    /// Whether the values of the field are ordered, i.e. can be compared with `<` or `>`.
+    pub fn is_ordered(&self) -> bool {
+        match self {
+            Field::Priority => true,
+            Field::Custom { kind, .. } => kind.is_ordered(),
+            _ => false,
+        }
+    }
+
+    /// Whether the field refers to users, i.e. can be compared with `me`.
+    pub fn is_user(&self) -> bool {
+        matches!(
+            self,
+            Field::Assignee
+                | Field::Reporter
+                | Field::Watchers
+                | Field::Custom {
+                    kind: CustomFieldKind::User,
+                    ..
+                }
+        )
+    }
+}
+
+/// How a [Field] is compared with a [Value].
+#[derive(PartialEq, Debug, Copy, Clone, Hash, Eq)]
+pub enum Operator {
+    /// `=`
+    Equals,
+    /// `!=`
+    NotEquals,
+    /// `<`
+    Less,
+    /// `<=`
+    LessOrEqual,
+    /// `>`
+    Greater,
+    /// `>=`
+    GreaterOrEqual,
+    /// `~`: the text contains the value, ignoring case.
+    Contains,
+    /// `!~`
+    NotContains,
+}
+
+impl Operator {
+    pub fn from_symbol(symbol: &str) -> Option<Operator> {
+        let operator = match symbol {
+            "=" => Operator::Equals,
+            "!=" => Operator::NotEquals,
+            "<" => Operator::Less,
+            "<=" => Operator::LessOrEqual,
+            ">" => Operator::Greater,
+            ">=" => Operator::GreaterOrEqual,
+            "~" => Operator::Contains,
+            "!~" => Operator::NotContains,
+            _ => return None,
+        };
+        Some(operator)
+    }
+}
+
+/// What a [Field] is compared with.
+#[derive(PartialEq, Debug, Clone, Hash, Eq)]
+pub enum Value {
+    Text(String),
+    /// `me` or `currentUser()`: the user running the query.
+    CurrentUser,
+}
+
+/// A condition on tickets.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Expression {
+    /// e.g. `priority >= high`
+    Comparison {
+        field: Field,
+        operator: Operator,
+        value: Value,
+    },
+    /// e.g. `label in (backend, api)` or `status not in (Done)`
+    In {
+        field: Field,
+        values: Vec<Value>,
+        negated: bool,
+    },
+    /// e.g. `assignee is empty` or `sprint is not empty`
+    Empty {
+        field: Field,
+        negated: bool,
+    },
+    Not(Box<Expression>),
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+}
+
+/// A key to sort tickets by, e.g. `priority DESC`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct OrderBy {
+    pub field: Field,
+    pub descending: bool,
+}
+
+/// A parsed query: which tickets to keep and how to sort them.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Query {
+    /// `None` if the query only sorts tickets, e.g. `ORDER BY key`.
+    pub filter: Option<Expression>,
+    /// Tickets are sorted by the first key, then by the second one, and so on.
+    pub order_by: Vec<OrderBy>,
+}
+
+/// Queries are displayed as text that parses back to the same query, e.g. to save them.
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(filter) = &self.filter {
+            write!(f, "{}", filter)?;
+            if !self.order_by.is_empty() {
+                write!(f, " ")?;
+            }
+        }
+        if !self.order_by.is_empty() {
+            let keys: Vec<String> = self.order_by.iter().map(|k| k.to_string()).collect();
+            write!(f, "ORDER BY {}", keys.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // `OR` binds more loosely than `AND` and `NOT`: it is put in parentheses below them.
+        let operand = |expression: &Expression| match expression {
+            Expression::Or(..) => format!("({})", expression),
+            _ => expression.to_string(),
+        };
+        match self {
+            Expression::Comparison {
+                field,
+                operator,
+                value,
+            } => write!(f, "{} {} {}", field.name(), operator, value),
+            Expression::In {
+                field,
+                values,
+                negated,
+            } => {
+                let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+                let not = if *negated { "NOT " } else { "" };
+                write!(f, "{} {}IN ({})", field.name(), not, values.join(", "))
+            }
+            Expression::Empty { field, negated } => {
+                let not = if *negated { "NOT " } else { "" };
+                write!(f, "{} IS {}EMPTY", field.name(), not)
+            }
+            Expression::Not(expression) => match **expression {
+                Expression::Or(..) | Expression::And(..) => write!(f, "NOT ({})", expression),
+                _ => write!(f, "NOT {}", expression),
+            },
+            Expression::And(left, right) => write!(f, "{} AND {}", operand(left), operand(right)),
+            Expression::Or(left, right) => write!(f, "{} OR {}", left, right),
+        }
+    }
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            Operator::Equals => "=",
+            Operator::NotEquals => "!=",
+            Operator::Less => "<",
+            Operator::LessOrEqual => "<=",
+            Operator::Greater => ">",
+            Operator::GreaterOrEqual => ">=",
+            Operator::Contains => "~",
+            Operator::NotContains => "!~",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+/// Values are quoted unless they are a single word that is not a keyword.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const KEYWORDS: [&str; 13] = [
+            "and",
+            "or",
+            "not",
+            "in",
+            "is",
+            "empty",
+            "null",
+            "order",
+            "by",
+            "asc",
+            "desc",
+            "me",
+            "currentuser",
+        ];
+        match self {
+            Value::CurrentUser => write!(f, "me"),
+            Value::Text(text) => {
+                let word = !text.is_empty()
+                    && text
+                        .chars()
+                        .all(|c| c.is_alphanumeric() || "-_.@".contains(c))
+                    && !KEYWORDS.contains(&text.to_lowercase().as_str());
+                if word {
+                    write!(f, "{}", text)
+                } else {
+                    write!(f, "\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for OrderBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let direction = if self.descending { "DESC" } else { "ASC" };
+        write!(f, "{} {}", self.field.name(), direction)
+    }
+}
@@ -0,0 +1,190 @@
+use crate::models::{Priority, Ticket, UserId};
+use crate::query::{Expression, Field, Operator, OrderBy, Query, Value};
+use std::cmp::Ordering;
+
+impl Query {
+    /// Whether `ticket` satisfies the filter of the query, `me` being the user running it.
+    ///
+    /// Comparisons with `me` never match if there is no current user.
+    /// As in JQL, negative comparisons (`!=`, `!~`, `NOT IN`) do not match empty fields:
+    /// use `IS EMPTY` to find them.
+    pub fn matches(&self, ticket: &Ticket, me: Option<&UserId>) -> bool {
+        self.filter
+            .as_ref()
+            .is_none_or(|filter| filter.matches(ticket, me))
+    }
+}
+
+impl OrderBy {
+    /// Sort tickets by `keys`: by the first key, then by the second one, and so on, then by id.
+    pub fn sort(keys: &[OrderBy], tickets: &mut [&Ticket]) {
+        tickets.sort_by(|a, b| {
+            keys.iter()
+                .map(|key| key.compare(a, b))
+                .find(|ordering| *ordering != Ordering::Equal)
+                .unwrap_or(Ordering::Equal)
+                .then(a.id.cmp(&b.id))
+        })
+    }
+
+    /// Compare two tickets by this key. Tickets with an empty field come first in ascending order.
+    pub fn compare(&self, a: &Ticket, b: &Ticket) -> Ordering {
+        let ordering = compare(&self.field, a, b);
+        if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
+impl Field {
+    /// The value of the field for `ticket`, as text: values of multi-valued fields (e.g. labels)
+    /// are separated by commas, and fields that are not set are empty.
+    pub fn value(&self, ticket: &Ticket) -> String {
+        values(self, ticket).join(", ")
+    }
+}
+
+impl Expression {
+    pub fn matches(&self, ticket: &Ticket, me: Option<&UserId>) -> bool {
+        match self {
+            Expression::And(left, right) => left.matches(ticket, me) && right.matches(ticket, me),
+            Expression::Or(left, right) => left.matches(ticket, me) || right.matches(ticket, me),
+            Expression::Not(expression) => !expression.matches(ticket, me),
+            Expression::Empty { field, negated } => values(field, ticket).is_empty() != *negated,
+            Expression::In {
+                field,
+                values: expected,
+                negated,
+            } => {
+                let actual = values(field, ticket);
+                let any = expected
+                    .iter()
+                    .filter_map(|value| resolve(value, me))
+                    .any(|value| actual.iter().any(|a| equals(field, a, &value)));
+                if *negated {
+                    !actual.is_empty() && !any
+                } else {
+                    any
+                }
+            }
+            Expression::Comparison {
+                field,
+                operator,
+                value,
+            } => {
+                let value = match resolve(value, me) {
+                    Some(value) => value,
+                    None => return false,
+                };
+                let actual = values(field, ticket);
+                let any = |predicate: &dyn Fn(&str) -> bool| actual.iter().any(|a| predicate(a));
+                match operator {
+                    Operator::Equals => any(&|a| equals(field, a, &value)),
+                    Operator::NotEquals => {
+                        !actual.is_empty() && !any(&|a| equals(field, a, &value))
+                    }
+                    Operator::Contains => any(&|a| contains(a, &value)),
+                    Operator::NotContains => !actual.is_empty() && !any(&|a| contains(a, &value)),
+                    ordered => {
+                        let holds = |ordering: Ordering| match ordered {
+                            Operator::Less => ordering == Ordering::Less,
+                            Operator::LessOrEqual => ordering != Ordering::Greater,
+                            Operator::Greater => ordering == Ordering::Greater,
+                            _ => ordering != Ordering::Less,
+                        };
+                        match field {
+                            // Values of ordered fields are checked by the parser.
+                            Field::Priority => match value.parse::<Priority>() {
+                                Ok(priority) => holds(ticket.priority.cmp(&priority)),
+                                Err(_) => false,
+                            },
+                            Field::Custom { kind, .. } => any(&|a| holds(kind.compare(a, &value))),
+                            _ => false,
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn resolve(value: &Value, me: Option<&UserId>) -> Option<String> {
+    match value {
+        Value::Text(text) => Some(text.clone()),
+        Value::CurrentUser => me.map(|me| me.to_string()),
+    }
+}
+
+/// The values of a field of `ticket`, as text: empty if the field is not set.
+fn values(field: &Field, ticket: &Ticket) -> Vec<String> {
+    match field {
+        Field::Key => vec![ticket.id.to_string()],
+        Field::Project => vec![ticket.id.project.to_string()],
+        Field::Title => vec![ticket.title.to_string()],
+        Field::Description if ticket.description.is_empty() => vec![],
+        Field::Description => vec![ticket.description.clone()],
+        Field::Status => vec![ticket.status.to_string()],
+        Field::Priority => vec![format!("{:?}", ticket.priority)],
+        Field::Kind => vec![format!("{:?}", ticket.kind)],
+        Field::Assignee => ticket.assignee.iter().map(|u| u.to_string()).collect(),
+        Field::Reporter => ticket.reporter.iter().map(|u| u.to_string()).collect(),
+        Field::Labels => ticket.labels.iter().map(|l| l.to_string()).collect(),
+        Field::Sprint => ticket.sprint.iter().map(|s| s.to_string()).collect(),
+        Field::Parent => ticket.parent.iter().map(|p| p.to_string()).collect(),
+        Field::Watchers => ticket.watchers.iter().map(|u| u.to_string()).collect(),
+        Field::Custom { name, .. } => ticket
+            .custom_fields
+            .get(name)
+            .cloned()
+            .into_iter()
+            .collect(),
+    }
+}
+
+/// Enumerated values are compared ignoring case, whitespace, `-` and `_` (`in-progress` is `InProgress`),
+/// values of custom fields according to their kind (`3` is `3.0`), other values only ignoring case.
+fn equals(field: &Field, actual: &str, expected: &str) -> bool {
+    match field {
+        Field::Status | Field::Priority | Field::Kind => normalise(actual) == normalise(expected),
+        Field::Custom { kind, .. } => kind.compare(actual, expected) == Ordering::Equal,
+        _ => actual.to_lowercase() == expected.to_lowercase(),
+    }
+}
+
+fn contains(actual: &str, expected: &str) -> bool {
+    actual.to_lowercase().contains(&expected.to_lowercase())
+}
+
+fn normalise(s: &str) -> String {
+    s.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn compare(field: &Field, a: &Ticket, b: &Ticket) -> Ordering {
+    match field {
+        Field::Key => a.id.cmp(&b.id),
+        Field::Project => a.id.project.cmp(&b.id.project),
+        Field::Priority => a.priority.cmp(&b.priority),
+        Field::Sprint => a.sprint.cmp(&b.sprint),
+        Field::Parent => a.parent.cmp(&b.parent),
+        Field::Custom { name, kind } => {
+            match (a.custom_fields.get(name), b.custom_fields.get(name)) {
+                (Some(a), Some(b)) => kind.compare(a, b),
+                (a, b) => a.is_some().cmp(&b.is_some()),
+            }
+        }
+        _ => {
+            let key = |ticket| {
+                values(field, ticket)
+                    .iter()
+                    .map(|v| v.to_lowercase())
+                    .collect::<Vec<_>>()
+            };
+            key(a).cmp(&key(b))
+        }
+    }
+}
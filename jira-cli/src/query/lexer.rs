@@ -0,0 +1,156 @@
+use crate::query::QueryError;
+
+/// The smallest meaningful parts of a query.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Token {
+    /// A keyword, a field name or an unquoted value, e.g. `AND`, `status` or `CORE-1`.
+    Word(String),
+    /// A value between double quotes, e.g. `"In Progress"`.
+    Quoted(String),
+    /// A comparison operator: `=`, `!=`, `<`, `<=`, `>`, `>=`, `~` or `!~`.
+    Operator(String),
+    OpenParenthesis,
+    CloseParenthesis,
+    Comma,
+}
+
+impl Token {
+    /// The token as written in a query, for error messages.
+    pub fn describe(&self) -> String {
+        match self {
+            Token::Word(word) => format!("`{}`", word),
+            Token::Quoted(value) => format!("\"{}\"", value),
+            Token::Operator(operator) => format!("`{}`", operator),
+            Token::OpenParenthesis => "`(`".to_string(),
+            Token::CloseParenthesis => "`)`".to_string(),
+            Token::Comma => "`,`".to_string(),
+        }
+    }
+}
+
+/// A [Token] and the column (starting from 1) of its first character.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Spanned {
+    pub token: Token,
+    pub column: usize,
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_' || c == '.' || c == '@'
+}
+
+/// Split a query into [Token]s.
+/// Fails on characters that cannot start a token and on unterminated quoted values.
+pub fn tokenize(query: &str) -> Result<Vec<Spanned>, QueryError> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+        let token = match c {
+            _ if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '(' => Token::OpenParenthesis,
+            ')' => Token::CloseParenthesis,
+            ',' => Token::Comma,
+            '=' | '~' => Token::Operator(c.to_string()),
+            '!' | '<' | '>' => {
+                let next = chars.get(i + 1).copied();
+                match (c, next) {
+                    ('!', Some('=')) | ('!', Some('~')) | ('<', Some('=')) | ('>', Some('=')) => {
+                        i += 1;
+                        Token::Operator(format!("{}{}", c, next.unwrap()))
+                    }
+                    ('!', _) => {
+                        return Err(QueryError::new(
+                            column,
+                            "`!` must be followed by `=` or `~`",
+                        ))
+                    }
+                    _ => Token::Operator(c.to_string()),
+                }
+            }
+            '"' | '\'' => {
+                let mut value = String::new();
+                let mut j = i + 1;
+                loop {
+                    match chars.get(j) {
+                        None => {
+                            return Err(QueryError::new(column, "this quoted value is not closed"))
+                        }
+                        Some('\\') if j + 1 < chars.len() => {
+                            value.push(chars[j + 1]);
+                            j += 2;
+                        }
+                        Some(&quote) if quote == c => break,
+                        Some(&other) => {
+                            value.push(other);
+                            j += 1;
+                        }
+                    }
+                }
+                i = j;
+                Token::Quoted(value)
+            }
+            _ if is_word_char(c) => {
+                let mut j = i;
+                while j < chars.len() && is_word_char(chars[j]) {
+                    j += 1;
+                }
+                let word = chars[i..j].iter().collect();
+                i = j - 1;
+                Token::Word(word)
+            }
+            _ => {
+                return Err(QueryError::new(
+                    column,
+                    format!("unexpected character `{}`", c),
+                ))
+            }
+        };
+        tokens.push(Spanned { token, column });
+        i += 1;
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod lexer_tests {
+    use crate::query::lexer::{tokenize, Token};
+
+    #[test]
+    fn tokens_are_recorded_with_their_column() {
+        //arrange
+        //act
+        let tokens = tokenize("title ~ \"log in\" AND priority>=high").unwrap();
+
+        //assert
+        let columns: Vec<(Token, usize)> =
+            tokens.into_iter().map(|t| (t.token, t.column)).collect();
+        assert_eq!(
+            columns,
+            vec![
+                (Token::Word("title".to_string()), 1),
+                (Token::Operator("~".to_string()), 7),
+                (Token::Quoted("log in".to_string()), 9),
+                (Token::Word("AND".to_string()), 18),
+                (Token::Word("priority".to_string()), 22),
+                (Token::Operator(">=".to_string()), 30),
+                (Token::Word("high".to_string()), 32),
+            ]
+        )
+    }
+
+    #[test]
+    fn unterminated_quotes_are_reported_where_they_start() {
+        //arrange
+        //act
+        let error = tokenize("title = \"oops").unwrap_err();
+
+        //assert
+        assert_eq!(error.column, 9)
+    }
+}
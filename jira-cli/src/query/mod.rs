@@ -0,0 +1,51 @@
+//! A query language to find tickets, modelled after Jira's JQL, e.g.
+//! `status = InProgress AND assignee = me AND label in (backend) ORDER BY priority DESC`.
+//!
+//! A query is a list of clauses comparing a [Field] of tickets with values, combined with `AND`,
+//! `OR`, `NOT` and parentheses, optionally followed by an `ORDER BY` list.
+//! Keywords, field names and enumerated values (statuses, priorities, kinds) are case-insensitive.
+//!
+//! Queries are [tokenized](lexer), [parsed](parser) into a [Query] and then evaluated on tickets,
+//! see [Query::matches].
+mod ast;
+mod evaluate;
+mod lexer;
+mod parser;
+
+pub use ast::*;
+pub use parser::{parse, parse_with_custom_fields};
+
+use std::error::Error;
+use std::fmt;
+
+#[derive(PartialEq, Debug, Clone)]
+/// Error if a query cannot be parsed, with the column (starting from 1) where parsing failed.
+pub struct QueryError {
+    pub column: usize,
+    pub details: String,
+}
+
+impl QueryError {
+    fn new(column: usize, details: impl Into<String>) -> QueryError {
+        QueryError {
+            column,
+            details: details.into(),
+        }
+    }
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid query at column {}: {}",
+            self.column, self.details
+        )
+    }
+}
+
+impl Error for QueryError {
+    fn description(&self) -> &str {
+        &self.details
+    }
+}
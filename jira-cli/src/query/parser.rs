@@ -0,0 +1,386 @@
+use crate::models::{CustomField, Priority, TicketKind};
+use crate::query::lexer::{tokenize, Spanned, Token};
+use crate::query::{Expression, Field, Operator, OrderBy, Query, QueryError, Value};
+
+/// Parse a query, e.g. `status = InProgress AND assignee = me ORDER BY priority DESC`.
+///
+/// The grammar, from the lowest to the highest precedence:
+///
+/// ```text
+/// query      := [or] [ORDER BY order (, order)*]
+/// or         := and (OR and)*
+/// and        := not (AND not)*
+/// not        := NOT not | ( or ) | clause
+/// clause     := field operator value
+///             | field [NOT] IN ( value (, value)* )
+///             | field IS [NOT] EMPTY
+/// order      := field [ASC | DESC]
+/// ```
+pub fn parse(query: &str) -> Result<Query, QueryError> {
+    parse_with_custom_fields(query, &[])
+}
+
+/// Parse a query that can refer to `custom_fields` as well as to the built-in fields.
+/// Values compared with custom fields are checked and put in their canonical form, e.g. `3.5` for `3.50`.
+pub fn parse_with_custom_fields(
+    query: &str,
+    custom_fields: &[&CustomField],
+) -> Result<Query, QueryError> {
+    let mut parser = Parser {
+        tokens: tokenize(query)?,
+        position: 0,
+        end: query.chars().count() + 1,
+        custom_fields,
+    };
+    let filter = if parser.peek_keyword("ORDER") || parser.peek().is_none() {
+        None
+    } else {
+        Some(parser.or()?)
+    };
+    let mut order_by = Vec::new();
+    if parser.eat_keyword("ORDER") {
+        parser.expect_keyword("BY")?;
+        loop {
+            order_by.push(parser.order()?);
+            if !parser.eat(&Token::Comma) {
+                break;
+            }
+        }
+    }
+    if let Some(token) = parser.peek() {
+        return Err(QueryError::new(
+            token.column,
+            format!(
+                "unexpected {} - expected AND, OR or ORDER BY",
+                token.token.describe()
+            ),
+        ));
+    }
+    Ok(Query { filter, order_by })
+}
+
+struct Parser<'a> {
+    tokens: Vec<Spanned>,
+    position: usize,
+    /// The column just after the end of the query, where unexpected ends are reported.
+    end: usize,
+    custom_fields: &'a [&'a CustomField],
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Spanned> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self, expected: &str) -> Result<Spanned, QueryError> {
+        let token = self.tokens.get(self.position).cloned().ok_or_else(|| {
+            QueryError::new(
+                self.end,
+                format!("the query ended unexpectedly - expected {}", expected),
+            )
+        })?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek().map(|t| &t.token) == Some(token) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Spanned { token: Token::Word(word), .. }) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if self.peek_keyword(keyword) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &Token) -> Result<(), QueryError> {
+        let next = self.next(&token.describe())?;
+        if &next.token != token {
+            return Err(unexpected(&next, &token.describe()));
+        }
+        Ok(())
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), QueryError> {
+        let next = self.next(keyword)?;
+        match &next.token {
+            Token::Word(word) if word.eq_ignore_ascii_case(keyword) => Ok(()),
+            _ => Err(unexpected(&next, keyword)),
+        }
+    }
+
+    fn or(&mut self) -> Result<Expression, QueryError> {
+        let mut expression = self.and()?;
+        while self.eat_keyword("OR") {
+            expression = Expression::Or(Box::new(expression), Box::new(self.and()?));
+        }
+        Ok(expression)
+    }
+
+    fn and(&mut self) -> Result<Expression, QueryError> {
+        let mut expression = self.not()?;
+        while self.eat_keyword("AND") {
+            expression = Expression::And(Box::new(expression), Box::new(self.not()?));
+        }
+        Ok(expression)
+    }
+
+    fn not(&mut self) -> Result<Expression, QueryError> {
+        if self.eat_keyword("NOT") {
+            return Ok(Expression::Not(Box::new(self.not()?)));
+        }
+        if self.eat(&Token::OpenParenthesis) {
+            let expression = self.or()?;
+            self.expect(&Token::CloseParenthesis)?;
+            return Ok(expression);
+        }
+        self.clause()
+    }
+
+    fn field(&mut self) -> Result<Field, QueryError> {
+        let next = self.next("a field")?;
+        match &next.token {
+            Token::Word(name) => Field::resolve(name, self.custom_fields).ok_or_else(|| {
+                QueryError::new(
+                    next.column,
+                    format!(
+                        "unknown field `{}` - fields are {}",
+                        name,
+                        Field::names(self.custom_fields)
+                    ),
+                )
+            }),
+            _ => Err(unexpected(&next, "a field")),
+        }
+    }
+
+    fn clause(&mut self) -> Result<Expression, QueryError> {
+        let field = self.field()?;
+        if self.eat_keyword("IS") {
+            let negated = self.eat_keyword("NOT");
+            let next = self.next("EMPTY")?;
+            return match &next.token {
+                Token::Word(word)
+                    if word.eq_ignore_ascii_case("EMPTY") || word.eq_ignore_ascii_case("NULL") =>
+                {
+                    Ok(Expression::Empty { field, negated })
+                }
+                _ => Err(unexpected(&next, "EMPTY")),
+            };
+        }
+        let negated = self.eat_keyword("NOT");
+        if negated || self.peek_keyword("IN") {
+            self.expect_keyword("IN")?;
+            self.expect(&Token::OpenParenthesis)?;
+            let mut values = vec![self.value(&field)?];
+            while self.eat(&Token::Comma) {
+                values.push(self.value(&field)?);
+            }
+            self.expect(&Token::CloseParenthesis)?;
+            return Ok(Expression::In {
+                field,
+                values,
+                negated,
+            });
+        }
+        let next = self.next("an operator")?;
+        let operator = match &next.token {
+            Token::Operator(symbol) => Operator::from_symbol(symbol).unwrap(),
+            _ => return Err(unexpected(&next, "an operator, IN or IS")),
+        };
+        let supported = match operator {
+            Operator::Equals | Operator::NotEquals => true,
+            Operator::Contains | Operator::NotContains => field.is_text(),
+            _ => field.is_ordered(),
+        };
+        if !supported {
+            return Err(QueryError::new(
+                next.column,
+                format!(
+                    "{} cannot be compared with {}",
+                    field.name(),
+                    next.token.describe()
+                ),
+            ));
+        }
+        let value = self.value(&field)?;
+        Ok(Expression::Comparison {
+            field,
+            operator,
+            value,
+        })
+    }
+
+    /// A value compared with `field`: enumerated values are checked here, to report mistakes early.
+    fn value(&mut self, field: &Field) -> Result<Value, QueryError> {
+        let next = self.next("a value")?;
+        let text = match &next.token {
+            Token::Word(word) => {
+                if field.is_user()
+                    && (word.eq_ignore_ascii_case("me") || word.eq_ignore_ascii_case("currentUser"))
+                {
+                    // `currentUser()` is the JQL spelling of `me`.
+                    if self.eat(&Token::OpenParenthesis) {
+                        self.expect(&Token::CloseParenthesis)?;
+                    }
+                    return Ok(Value::CurrentUser);
+                }
+                word.clone()
+            }
+            Token::Quoted(value) => value.clone(),
+            _ => return Err(unexpected(&next, "a value")),
+        };
+        let invalid = |e: &dyn std::fmt::Display| QueryError::new(next.column, e.to_string());
+        match field {
+            Field::Priority => {
+                text.parse::<Priority>().map_err(|e| invalid(&e))?;
+            }
+            Field::Kind => {
+                text.parse::<TicketKind>().map_err(|e| invalid(&e))?;
+            }
+            Field::Custom { name, .. } => {
+                let definition = self.custom_fields.iter().find(|f| &f.name == name);
+                if let Some(definition) = definition {
+                    let text = definition.validate(&text).map_err(|e| invalid(&e))?;
+                    return Ok(Value::Text(text));
+                }
+            }
+            _ => {}
+        }
+        Ok(Value::Text(text))
+    }
+
+    fn order(&mut self) -> Result<OrderBy, QueryError> {
+        let field = self.field()?;
+        let descending = if self.eat_keyword("DESC") {
+            true
+        } else {
+            self.eat_keyword("ASC");
+            false
+        };
+        Ok(OrderBy { field, descending })
+    }
+}
+
+fn unexpected(token: &Spanned, expected: &str) -> QueryError {
+    QueryError::new(
+        token.column,
+        format!(
+            "unexpected {} - expected {}",
+            token.token.describe(),
+            expected
+        ),
+    )
+}
+
+#[cfg(test)]
+mod parser_tests {
+    use crate::query::{parse, Expression, Field, Operator, OrderBy, Value};
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        //arrange
+        //act
+        let query = parse("status = Done OR assignee = me AND label in (backend)").unwrap();
+
+        //assert
+        let status = Expression::Comparison {
+            field: Field::Status,
+            operator: Operator::Equals,
+            value: Value::Text("Done".to_string()),
+        };
+        let mine = Expression::Comparison {
+            field: Field::Assignee,
+            operator: Operator::Equals,
+            value: Value::CurrentUser,
+        };
+        let backend = Expression::In {
+            field: Field::Labels,
+            values: vec![Value::Text("backend".to_string())],
+            negated: false,
+        };
+        assert_eq!(
+            query.filter,
+            Some(Expression::Or(
+                Box::new(status),
+                Box::new(Expression::And(Box::new(mine), Box::new(backend)))
+            ))
+        )
+    }
+
+    #[test]
+    fn queries_can_only_sort() {
+        //arrange
+        //act
+        let query = parse("order by priority desc, key").unwrap();
+
+        //assert
+        assert_eq!(query.filter, None);
+        assert_eq!(
+            query.order_by,
+            vec![
+                OrderBy {
+                    field: Field::Priority,
+                    descending: true
+                },
+                OrderBy {
+                    field: Field::Key,
+                    descending: false
+                }
+            ]
+        )
+    }
+
+    #[test]
+    fn displayed_queries_parse_back_to_the_same_query() {
+        //arrange
+        let queries = vec![
+            "NOT (status = Done OR status = \"in review\") AND assignee = me",
+            "labels not in (backend, \"and\") AND sprint is not empty ORDER BY priority DESC",
+            "title ~ 'say \"hi\"' OR NOT reporter is empty",
+        ];
+
+        for text in queries {
+            let query = parse(text).unwrap();
+
+            //act
+            let displayed = query.to_string();
+
+            //assert
+            assert_eq!(parse(&displayed).unwrap(), query, "{}", displayed);
+        }
+    }
+
+    #[test]
+    fn errors_report_the_column_where_parsing_failed() {
+        //arrange
+        let queries = vec![
+            ("status = Done AND", 18),
+            ("status = Done assignee = me", 15),
+            ("colour = red", 1),
+            ("priority = urgent", 12),
+            ("status > Done", 8),
+            ("label in (backend", 18),
+        ];
+
+        for (query, column) in queries {
+            //act
+            let error = parse(query).unwrap_err();
+
+            //assert
+            assert_eq!(error.column, column, "{}: {}", query, error);
+        }
+    }
+}
@@ -0,0 +1,230 @@
+//! Full-text search over the title, the description and the comments of tickets.
+//!
+//! Text is split into lowercase words (runs of letters and digits) and the words of each ticket are
+//! kept in an inverted index, which the [TicketStore](crate::store::TicketStore) updates whenever
+//! a ticket is created, edited, commented or deleted.
+//! Results are ranked with [BM25](https://en.wikipedia.org/wiki/Okapi_BM25): tickets that use
+//! the searched words often, and especially rare words, come first.
+use crate::models::{Ticket, TicketId};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// How quickly the score of a ticket saturates as a word occurs again and again in it.
+const K1: f64 = 1.2;
+/// How much long tickets are penalised: 0 ignores the length, 1 normalises it fully.
+const B: f64 = 0.75;
+/// How many words are shown around a match in a snippet, on each side.
+const CONTEXT_WORDS: usize = 5;
+
+/// The words of a ticket, with the number of times each of them occurs.
+#[derive(Default, Serialize, Deserialize)]
+struct Document {
+    frequencies: HashMap<String, usize>,
+    length: usize,
+}
+
+/// Inverted index from words to the tickets using them.
+#[derive(Default, Serialize, Deserialize)]
+pub struct SearchIndex {
+    documents: HashMap<TicketId, Document>,
+    postings: HashMap<String, HashSet<TicketId>>,
+    total_length: usize,
+}
+
+impl SearchIndex {
+    /// Index `ticket`, replacing what was indexed for it before.
+    pub fn insert(&mut self, ticket: &Ticket) {
+        self.remove(&ticket.id);
+        let mut document = Document::default();
+        for text in searchable_text(ticket) {
+            for word in tokenize(&text) {
+                *document.frequencies.entry(word).or_insert(0) += 1;
+                document.length += 1;
+            }
+        }
+        for word in document.frequencies.keys() {
+            self.postings
+                .entry(word.clone())
+                .or_default()
+                .insert(ticket.id.clone());
+        }
+        self.total_length += document.length;
+        self.documents.insert(ticket.id.clone(), document);
+    }
+
+    /// Forget a ticket, e.g. because it was deleted.
+    pub fn remove(&mut self, id: &TicketId) {
+        let document = match self.documents.remove(id) {
+            Some(document) => document,
+            None => return,
+        };
+        for word in document.frequencies.keys() {
+            if let Some(tickets) = self.postings.get_mut(word) {
+                tickets.remove(id);
+                if tickets.is_empty() {
+                    self.postings.remove(word);
+                }
+            }
+        }
+        self.total_length -= document.length;
+    }
+
+    /// The tickets using at least one of `words`, with their BM25 score, the best match first.
+    pub fn rank(&self, words: &[String]) -> Vec<(&TicketId, f64)> {
+        let count = self.documents.len() as f64;
+        let average_length = self.total_length as f64 / count.max(1.0);
+        let mut scores: HashMap<&TicketId, f64> = HashMap::new();
+        let distinct: HashSet<&String> = words.iter().collect();
+        for word in distinct {
+            let tickets = match self.postings.get(word) {
+                Some(tickets) => tickets,
+                None => continue,
+            };
+            let matching = tickets.len() as f64;
+            let idf = (1.0 + (count - matching + 0.5) / (matching + 0.5)).ln();
+            for id in tickets {
+                let document = &self.documents[id];
+                let frequency = document.frequencies[word] as f64;
+                let length = document.length as f64 / average_length;
+                *scores.entry(id).or_insert(0.0) +=
+                    idf * frequency * (K1 + 1.0) / (frequency + K1 * (1.0 - B + B * length));
+            }
+        }
+        let mut ranked: Vec<(&TicketId, f64)> = scores.into_iter().collect();
+        ranked.sort_by(|(a, a_score), (b, b_score)| b_score.total_cmp(a_score).then(a.cmp(b)));
+        ranked
+    }
+}
+
+/// The text of a ticket that can be searched.
+fn searchable_text(ticket: &Ticket) -> impl Iterator<Item = String> + '_ {
+    std::iter::once(ticket.title.to_string())
+        .chain(std::iter::once(ticket.description.clone()))
+        .chain(ticket.comments.iter().map(|c| c.body.to_string()))
+}
+
+/// The byte ranges of the words of `text`, i.e. of its runs of letters and digits.
+fn words(text: &str) -> Vec<(usize, usize)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices() {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                words.push((s, i));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        words.push((s, text.len()));
+    }
+    words
+}
+
+/// Split text into lowercase words, e.g. `["connection", "timeout"]` for `Connection time-out`.
+pub fn tokenize(text: &str) -> Vec<String> {
+    words(text)
+        .into_iter()
+        .map(|(start, end)| text[start..end].to_lowercase())
+        .collect()
+}
+
+/// An excerpt of `text` around the first of `words` it contains, with the searched words
+/// highlighted like `**this**`. Returns `None` if `text` does not contain any of them.
+pub fn snippet(text: &str, words: &[String]) -> Option<String> {
+    let spans = self::words(text);
+    let is_match =
+        |&(start, end): &(usize, usize)| words.contains(&text[start..end].to_lowercase());
+    let first = spans.iter().position(is_match)?;
+    let from = first.saturating_sub(CONTEXT_WORDS);
+    let to = (first + CONTEXT_WORDS + 1).min(spans.len());
+    let mut snippet = String::new();
+    if from > 0 {
+        snippet.push('…');
+    }
+    let mut position = spans[from].0;
+    for span in &spans[from..to] {
+        snippet.push_str(&text[position..span.0]);
+        let word = &text[span.0..span.1];
+        if is_match(span) {
+            snippet.push_str(&format!("**{}**", word));
+        } else {
+            snippet.push_str(word);
+        }
+        position = span.1;
+    }
+    if to < spans.len() {
+        snippet.push('…');
+    }
+    Some(snippet.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// A ticket found by a full-text search, with excerpts of its text showing the searched words.
+pub struct SearchHit<'a> {
+    pub ticket: &'a Ticket,
+    pub score: f64,
+    /// Where the words were found (`title`, `description` or `comment <id>`) and the excerpt.
+    pub snippets: Vec<(String, String)>,
+}
+
+impl<'a> SearchHit<'a> {
+    pub fn new(ticket: &'a Ticket, score: f64, words: &[String]) -> SearchHit<'a> {
+        let mut snippets = Vec::new();
+        if let Some(snippet) = snippet(&ticket.title.to_string(), words) {
+            snippets.push(("title".to_string(), snippet));
+        }
+        if let Some(snippet) = snippet(&ticket.description, words) {
+            snippets.push(("description".to_string(), snippet));
+        }
+        for comment in ticket.comments.iter() {
+            if let Some(snippet) = snippet(&comment.body.to_string(), words) {
+                snippets.push((format!("comment {}", comment.id), snippet));
+            }
+        }
+        SearchHit {
+            ticket,
+            score,
+            snippets,
+        }
+    }
+}
+
+impl fmt::Display for SearchHit<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} [{}] {} (score {:.2})",
+            self.ticket.id, self.ticket.status, self.ticket.title, self.score
+        )?;
+        for (place, snippet) in self.snippets.iter() {
+            write!(f, "\n    {}: {}", place, snippet)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod search_tests {
+    use crate::search::{snippet, tokenize};
+
+    #[test]
+    fn snippets_highlight_the_searched_words_in_context() {
+        //arrange
+        let text = "After the upgrade, every request to the billing service fails with a \
+                    connection timeout once the pool is exhausted.";
+        let words = tokenize("Connection TIMEOUT");
+
+        //act
+        let snippet = snippet(text, &words).unwrap();
+
+        //assert
+        assert_eq!(
+            snippet,
+            "…billing service fails with a **connection** **timeout** once the pool is…"
+        );
+        assert_eq!(super::snippet(text, &tokenize("database")), None);
+    }
+}
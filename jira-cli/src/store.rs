@@ -1,308 +1,4194 @@
-use crate::models::{Comment, DeletedTicket, Status, Ticket, TicketDraft, TicketId, TicketPatch};
+use crate::models::{
+    mentions, mime_type, Age, Attachment, Blob, CarryOver, Change, ChangeContext, Checkpoint,
+    Comment, CommentId, CommentRevision, CreateError, CustomField, CustomFieldError,
+    CustomFieldKind, CustomFieldName, DeletedTicket, Event, FilterName, HierarchyError, Label,
+    LinkError, LinkKind, Notification, NotificationId, NotificationReason, Operation, Project,
+    ProjectKey, ReplyError, RequiredField, SavedFilter, SelectionError, Sprint, SprintDraft,
+    SprintError, SprintId, SprintState, SprintSummary, Status, StatusCategory, Ticket,
+    TicketComment, TicketDraft, TicketId, TicketKind, TicketLink, TicketPatch, TicketSelection,
+    TransitionError, UndoStep, User, UserId, WorkDuration, Workflow, Worklog, WorklogError,
+};
+use crate::query::{self, Field, OrderBy, Query, QueryError};
+use crate::search::{self, SearchHit, SearchIndex};
+use chrono::{NaiveDate, TimeZone};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 /// In-memory database where we store the saved [`Ticket`]s.
 #[derive(Serialize, Deserialize)]
 pub struct TicketStore {
-    /// Current state of the internal sequence, used for id generation in generate_id.
+    /// Current state of the internal sequence of each project, used for id generation in generate_id.
+    #[serde(default)]
+    sequences: HashMap<ProjectKey, u64>,
+    /// The sequence shared by all tickets before projects were introduced.
+    /// It is only read from old data files, see [upgrade](TicketStore::upgrade).
+    #[serde(default, skip_serializing)]
     current_id: u64,
     /// The collection of stored tickets.
     data: HashMap<TicketId, Ticket>,
+    /// The projects tickets can be filed in, indexed by their key.
+    #[serde(default)]
+    projects: BTreeMap<ProjectKey, Project>,
+    /// The people who can file and work on tickets, indexed by their handle.
+    #[serde(default)]
+    users: HashMap<UserId, User>,
+    /// Current state of the internal sequence of sprints, used for id generation in create_sprint.
+    #[serde(default)]
+    sprint_id: SprintId,
+    /// The planned, active and completed sprints, indexed by their id.
+    #[serde(default)]
+    sprints: BTreeMap<SprintId, Sprint>,
+    /// The deleted tickets, until they are restored or purged, indexed by their id.
+    #[serde(default)]
+    trash: BTreeMap<TicketId, DeletedTicket>,
+    /// Current state of the internal sequence of notifications, used for id generation in notify_mentions.
+    #[serde(default)]
+    notification_id: NotificationId,
+    /// The notifications of each user, the oldest first.
+    #[serde(default)]
+    inboxes: BTreeMap<UserId, Vec<Notification>>,
+    /// The saved filters, indexed by their name.
+    #[serde(default)]
+    filters: BTreeMap<FilterName, SavedFilter>,
+    /// The schema of the custom fields tickets can have, indexed by their name.
+    #[serde(default)]
+    custom_fields: BTreeMap<CustomFieldName, CustomField>,
+    /// The rules for status transitions. It comes from the configuration, it is not persisted.
+    #[serde(skip)]
+    workflow: Workflow,
+    /// The user making changes, recorded in the history of tickets. It is not persisted.
+    #[serde(skip)]
+    context: ChangeContext,
+    /// The sequence number of the last [Event] applied to the store.
+    #[serde(default)]
+    event_sequence: u64,
+    /// The number of snapshots of the store saved so far. The search index is saved with the revision
+    /// of the snapshot it matches, to tell whether it is stale.
+    #[serde(default)]
+    revision: u64,
+    /// The events applied since the store was loaded, not saved to the event log yet.
+    #[serde(skip)]
+    events: Vec<Event>,
+    /// Whether the store was changed by an operation that is not recorded as an [Event]:
+    /// the event log cannot rebuild the store anymore, a snapshot must be saved.
+    #[serde(skip)]
+    needs_snapshot: bool,
+    /// The words used in tickets, for full-text search. It is saved in its own file, next to the
+    /// snapshot (see [persistence](crate::persistence)), and kept up to date as tickets change.
+    #[serde(skip)]
+    index: SearchIndex,
+    /// Whether the index was rebuilt since the store was loaded: it must be saved again.
+    #[serde(skip)]
+    index_rebuilt: bool,
 }
 
 impl TicketStore {
     /// Create a new empty [`TicketStore`] instance.
     pub fn new() -> Self {
         Self {
+            sequences: HashMap::new(),
             current_id: 0,
             data: HashMap::new(),
+            projects: BTreeMap::new(),
+            users: HashMap::new(),
+            sprint_id: 0,
+            sprints: BTreeMap::new(),
+            trash: BTreeMap::new(),
+            notification_id: 0,
+            inboxes: BTreeMap::new(),
+            filters: BTreeMap::new(),
+            custom_fields: BTreeMap::new(),
+            workflow: Workflow::default(),
+            context: ChangeContext::default(),
+            event_sequence: 0,
+            events: Vec::new(),
+            needs_snapshot: false,
+            revision: 0,
+            index: SearchIndex::default(),
+            index_rebuilt: false,
         }
     }
 
+    /// Set the [User] the changes made from now on are attributed to in the history of tickets.
+    pub fn set_actor(&mut self, actor: Option<UserId>) {
+        self.context.actor = actor;
+    }
+
     /// Given a ticket draft, it generates a unique identifier, it persists
-    /// the new ticket in the store (assigning it a [ToDo status](Status::ToDo)) and returns
+    /// the new ticket in the store (assigning it the [initial status](Workflow::initial_status)) and returns
     /// the ticket identifier.
-    pub fn create(&mut self, draft: TicketDraft) -> TicketId {
-        let id = self.generate_id();
-        let ticket = Ticket {
-            id,
+    /// Fails if the draft's project does not exist or if its parent violates the hierarchy rules
+    /// (see [TicketKind]).
+    pub fn create(&mut self, draft: TicketDraft) -> Result<TicketId, CreateError> {
+        let status = self.workflow.initial_status();
+        let operation = Operation::Create {
+            draft: draft.clone(),
+            status: status.clone(),
+        };
+        self.logged(operation, |store| store.apply_create(draft, status))
+    }
+
+    fn apply_create(
+        &mut self,
+        draft: TicketDraft,
+        status: Status,
+    ) -> Result<TicketId, CreateError> {
+        if !self.projects.contains_key(&draft.project) {
+            return Err(CreateError::UnknownProject(draft.project));
+        }
+        if let Some(parent) = &draft.parent {
+            self.check_parent(None, draft.kind, parent)?;
+        }
+        for (name, value) in draft.custom_fields.iter() {
+            self.check_custom_value(name, value)?;
+        }
+        if let Some(field) = self
+            .custom_fields
+            .values()
+            .find(|f| f.required && !draft.custom_fields.contains_key(&f.name))
+        {
+            return Err(CustomFieldError::MissingValue(field.name.clone()).into());
+        }
+        let id = self.generate_id(&draft.project);
+        let mut ticket = Ticket {
+            id: id.clone(),
             description: draft.description,
             title: draft.title,
-            status: Status::ToDo,
+            status,
+            priority: draft.priority,
+            kind: draft.kind,
+            parent: draft.parent,
             comments: Vec::new(),
+            comment_sequence: 0,
+            reporter: draft.reporter.clone(),
+            assignee: draft.assignee,
+            labels: BTreeSet::new(),
+            links: BTreeSet::new(),
+            sprint: None,
+            estimate: None,
+            remaining: None,
+            worklogs: Vec::new(),
+            // As in Jira, people watch the tickets they file.
+            watchers: draft.reporter.iter().cloned().collect(),
+            attachments: Vec::new(),
+            custom_fields: draft.custom_fields,
+            history: Vec::new(),
+        };
+        ticket.record_change(
+            &self.context,
+            "created",
+            None,
+            Some(ticket.title.to_string()),
+        );
+        let description = ticket.description.clone();
+        self.data.insert(ticket.id.clone(), ticket);
+        self.reindex(&id);
+        self.notify_mentions(
+            &id,
+            NotificationReason::MentionedInDescription,
+            &description,
+            "",
+        );
+        Ok(id)
+    }
+
+    /// Move a [Ticket] to the trash, removing all the links pointing to it.
+    /// Its children are detached from it and become top-level tickets.
+    /// Returns None if the [Ticket](Ticket) is not there or [DeletedTicket](DeletedTicket) if there was one.
+    pub fn delete(&mut self, ticket_id: &TicketId) -> Option<DeletedTicket> {
+        let operation = Operation::Delete {
+            id: ticket_id.clone(),
+        };
+        self.logged(operation, |store| store.apply_delete(ticket_id).ok_or(()))
+            .ok()
+    }
+
+    fn apply_delete(&mut self, ticket_id: &TicketId) -> Option<DeletedTicket> {
+        let ticket = self.data.remove(ticket_id)?;
+        self.reindex(ticket_id);
+        for link in ticket.links.iter() {
+            if let Some(target) = self.data.get_mut(&link.target) {
+                let inverse = TicketLink {
+                    kind: link.kind.inverse(),
+                    target: ticket_id.clone(),
+                };
+                target.links.remove(&inverse);
+                target.record_change(&self.context, "links", Some(inverse.to_string()), None);
+            }
+        }
+        for child in self.data.values_mut() {
+            if child.parent.as_ref() == Some(ticket_id) {
+                child.parent = None;
+                child.record_change(&self.context, "parent", Some(ticket_id.to_string()), None);
+            }
+        }
+        let deleted = DeletedTicket {
+            ticket,
+            deleted_at: self.context.now(),
+            deleted_by: self.context.actor.clone(),
+        };
+        self.trash.insert(ticket_id.clone(), deleted.clone());
+        Some(deleted)
+    }
+
+    /// Returns the tickets in the trash, the most recently deleted first.
+    pub fn trash(&self) -> Vec<&DeletedTicket> {
+        let mut trash: Vec<&DeletedTicket> = self.trash.values().collect();
+        trash.sort_by(|a, b| {
+            b.deleted_at
+                .cmp(&a.deleted_at)
+                .then(a.ticket.id.cmp(&b.ticket.id))
+        });
+        trash
+    }
+
+    /// Take a [Ticket] out of the trash, with the id it had before it was deleted.
+    /// Its links are restored on both ends, except those to tickets that do not exist anymore,
+    /// and so is its parent if it still exists.
+    /// Returns `None` if there is no ticket with such an identifier in the trash.
+    pub fn restore(&mut self, ticket_id: &TicketId) -> Option<()> {
+        self.needs_snapshot = true;
+        let mut ticket = self.trash.remove(ticket_id)?.ticket;
+        let links = std::mem::take(&mut ticket.links);
+        for link in links {
+            let target = match self.data.get_mut(&link.target) {
+                Some(target) => target,
+                None => {
+                    ticket.record_change(&self.context, "links", Some(link.to_string()), None);
+                    continue;
+                }
+            };
+            let inverse = TicketLink {
+                kind: link.kind.inverse(),
+                target: ticket_id.clone(),
+            };
+            target.record_change(&self.context, "links", None, Some(inverse.to_string()));
+            target.links.insert(inverse);
+            ticket.links.insert(link);
+        }
+        if let Some(parent) = &ticket.parent {
+            if !self.data.contains_key(parent) {
+                ticket.record_change(&self.context, "parent", Some(parent.to_string()), None);
+                ticket.parent = None;
+            }
+        }
+        if let Some(sprint) = ticket.sprint {
+            if !self.sprints.contains_key(&sprint) {
+                ticket.record_change(&self.context, "sprint", Some(sprint.to_string()), None);
+                ticket.sprint = None;
+            }
+        }
+        ticket.record_change(&self.context, "restored", None, Some(ticket_id.to_string()));
+        self.data.insert(ticket_id.clone(), ticket);
+        self.reindex(ticket_id);
+        Some(())
+    }
+
+    /// Delete for good the tickets that were moved to the trash more than `older_than` ago.
+    /// Returns the ids of the purged tickets: none if `older_than` goes back further than any date.
+    pub fn purge(&mut self, older_than: Age) -> Vec<TicketId> {
+        self.needs_snapshot = true;
+        let limit = match self
+            .context
+            .now()
+            .checked_sub_signed(older_than.to_duration())
+        {
+            Some(limit) => limit,
+            None => return Vec::new(),
+        };
+        let purged: Vec<TicketId> = self
+            .trash
+            .values()
+            .filter(|deleted| deleted.deleted_at < limit)
+            .map(|deleted| deleted.ticket.id.clone())
+            .collect();
+        for id in purged.iter() {
+            self.trash.remove(id);
+        }
+        purged
+    }
+
+    /// Returns list off all inserted [Ticket](Ticket), the most urgent first
+    /// (see [by_priority](TicketStore::by_priority)).
+    /// Returns an empty list of tickets is there are no tickets in the store
+    pub fn list(&self) -> Vec<&Ticket> {
+        Self::by_priority(self.data.values().collect())
+    }
+
+    /// Returns the [Ticket]s matching a [Query], `me` being the user running it.
+    /// Tickets are sorted as the query says, the most urgent first if it has no `ORDER BY` clause.
+    pub fn search(&self, query: &Query, me: Option<&UserId>) -> Vec<&Ticket> {
+        let tickets: Vec<&Ticket> = self
+            .data
+            .values()
+            .filter(|t| query.matches(t, me))
+            .collect();
+        Self::sort_by(tickets, &query.order_by)
+    }
+
+    /// Sort tickets by `keys`, then by id (see [OrderBy::sort]), the most urgent first if there are no keys.
+    pub fn sort_by<'a>(mut tickets: Vec<&'a Ticket>, keys: &[OrderBy]) -> Vec<&'a Ticket> {
+        if keys.is_empty() {
+            Self::by_priority(tickets)
+        } else {
+            OrderBy::sort(keys, &mut tickets);
+            tickets
+        }
+    }
+
+    /// Returns the [Ticket]s whose title, description or comments use the words of `text`,
+    /// the most relevant first (see [search](crate::search)).
+    pub fn search_text(&self, text: &str) -> Vec<SearchHit<'_>> {
+        let words = search::tokenize(text);
+        self.index
+            .rank(&words)
+            .into_iter()
+            .map(|(id, score)| SearchHit::new(&self.data[id], score, &words))
+            .collect()
+    }
+
+    /// The index used by [search_text](TicketStore::search_text).
+    pub fn index(&self) -> &SearchIndex {
+        &self.index
+    }
+
+    /// Use an index read from disk. It must match the tickets of the store, i.e. it was saved
+    /// with the same [revision](TicketStore::revision).
+    pub fn set_index(&mut self, index: SearchIndex) {
+        self.index = index;
+    }
+
+    /// Index all the tickets for full-text search, e.g. when the saved index is missing or stale.
+    pub fn build_index(&mut self) {
+        self.index = SearchIndex::default();
+        for ticket in self.data.values() {
+            self.index.insert(ticket);
+        }
+        self.index_rebuilt = true;
+    }
+
+    /// Whether the index was rebuilt since the store was loaded, see [build_index](TicketStore::build_index).
+    pub fn index_rebuilt(&self) -> bool {
+        self.index_rebuilt
+    }
+
+    /// The number of snapshots of the store saved so far.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Move to the next revision, before a new snapshot of the store is saved.
+    pub fn next_revision(&mut self) {
+        self.revision += 1;
+    }
+
+    /// Bring the search index up to date with a [Ticket] that was just changed, created or removed.
+    fn reindex(&mut self, id: &TicketId) {
+        match self.data.get(id) {
+            Some(ticket) => self.index.insert(ticket),
+            None => self.index.remove(id),
+        }
+    }
+
+    /// Sort tickets by descending [Priority](crate::models::Priority), breaking ties by ascending id.
+    fn by_priority(mut tickets: Vec<&Ticket>) -> Vec<&Ticket> {
+        tickets.sort_by(|a, b| b.priority.cmp(&a.priority).then(a.id.cmp(&b.id)));
+        tickets
+    }
+
+    /// Generate a unique id by incrementing monotonically the private counter of a project.
+    fn generate_id(&mut self, project: &ProjectKey) -> TicketId {
+        let sequence = self.sequences.entry(project.clone()).or_insert(0);
+        *sequence += 1;
+        TicketId::new(project.clone(), *sequence)
+    }
+
+    /// Bring data saved by older versions of IronJira up to date: tickets created before projects
+    /// were introduced are part of the [legacy project](ProjectKey::legacy), which continues their sequence.
+    pub fn upgrade(&mut self) {
+        self.upgrade_comments();
+        if self.current_id == 0 {
+            return;
+        }
+        self.needs_snapshot = true;
+        let legacy = ProjectKey::legacy();
+        self.projects
+            .entry(legacy.clone())
+            .or_insert_with(|| Project {
+                key: legacy.clone(),
+                name: "IronJira".to_string(),
+                lead: None,
+            });
+        let sequence = self.sequences.entry(legacy).or_insert(0);
+        *sequence = (*sequence).max(self.current_id);
+        self.current_id = 0;
+    }
+
+    /// Number the comments saved before they had an id. Their author and creation time are taken
+    /// from the history of the ticket, when it recorded them.
+    fn upgrade_comments(&mut self) {
+        for ticket in self.data.values_mut() {
+            if ticket.comments.iter().all(|c| c.id != 0) {
+                continue;
+            }
+            self.needs_snapshot = true;
+            let mut posted = ticket.history.iter().filter(|r| r.field == "comment");
+            for comment in ticket.comments.iter_mut().filter(|c| c.id == 0) {
+                ticket.comment_sequence += 1;
+                comment.id = ticket.comment_sequence;
+                let text = comment.body.to_string();
+                if let Some(record) =
+                    posted.find(|r| r.old.is_none() && r.new == Some(text.clone()))
+                {
+                    comment.author = record.actor.clone();
+                    comment.created_at = record.at;
+                }
+            }
+        }
+    }
+
+    /// Register a new [Project]. Returns `None` if there is already a project with the same key.
+    pub fn create_project(&mut self, project: Project) -> Option<()> {
+        self.needs_snapshot = true;
+        if self.projects.contains_key(&project.key) {
+            return None;
+        }
+        self.projects.insert(project.key.clone(), project);
+        Some(())
+    }
+
+    /// Retrieve a [Project] given its key. Returns `None` if there is no project with such a key.
+    pub fn get_project(&self, key: &ProjectKey) -> Option<&Project> {
+        self.projects.get(key)
+    }
+
+    /// Returns all [Project]s, sorted by key.
+    pub fn list_projects(&self) -> Vec<&Project> {
+        self.projects.values().collect()
+    }
+
+    /// Save a [SavedFilter], replacing the filter with the same name if there was one.
+    /// Returns the replaced filter, if any.
+    pub fn save_filter(&mut self, filter: SavedFilter) -> Option<SavedFilter> {
+        self.needs_snapshot = true;
+        self.filters.insert(filter.name.clone(), filter)
+    }
+
+    /// The ids of the tickets a bulk operation applies to, `me` being the user running it: the listed
+    /// tickets without duplicates, in that order, or those matching the filter or the query,
+    /// in the order [search](TicketStore::search) returns them.
+    pub fn select(
+        &self,
+        selection: &TicketSelection,
+        me: Option<&UserId>,
+    ) -> Result<Vec<TicketId>, SelectionError> {
+        let query = match selection {
+            TicketSelection::Ids(ids) => {
+                let mut selected = Vec::new();
+                for id in ids {
+                    if !selected.contains(id) {
+                        selected.push(id.clone());
+                    }
+                }
+                return Ok(selected);
+            }
+            TicketSelection::Filter(name) => {
+                &self
+                    .get_filter(name)
+                    .ok_or_else(|| SelectionError::FilterNotFound(name.clone()))?
+                    .query
+            }
+            TicketSelection::Query(query) => query,
+        };
+        let query = self
+            .parse_query(query)
+            .map_err(SelectionError::InvalidQuery)?;
+        Ok(self
+            .search(&query, me)
+            .into_iter()
+            .map(|t| t.id.clone())
+            .collect())
+    }
+
+    /// Retrieve a [SavedFilter] given its name. Returns `None` if there is no filter with such a name.
+    pub fn get_filter(&self, name: &FilterName) -> Option<&SavedFilter> {
+        self.filters.get(name)
+    }
+
+    /// Returns all [SavedFilter]s, sorted by name.
+    pub fn list_filters(&self) -> Vec<&SavedFilter> {
+        self.filters.values().collect()
+    }
+
+    /// Delete a [SavedFilter]. Returns `None` if there is no filter with such a name.
+    pub fn delete_filter(&mut self, name: &FilterName) -> Option<SavedFilter> {
+        self.needs_snapshot = true;
+        self.filters.remove(name)
+    }
+
+    /// Add a [CustomField] to the schema. It fails if there is already a field with the same name,
+    /// custom or built-in, or if allowed values are missing (enum fields) or pointless (other fields).
+    /// Required fields are only enforced for the tickets created from then on.
+    pub fn define_custom_field(&mut self, field: CustomField) -> Result<(), CustomFieldError> {
+        let builtin = Field::from_name(field.name.as_str()).is_some();
+        if builtin || self.custom_fields.contains_key(&field.name) {
+            return Err(CustomFieldError::AlreadyDefined(field.name));
+        }
+        let values = &field.allowed_values;
+        // Values are matched ignoring case, so `Prod` and `prod` could not be told apart.
+        let ambiguous = values.iter().enumerate().any(|(i, value)| {
+            value.trim().is_empty() || values[..i].iter().any(|v| v.eq_ignore_ascii_case(value))
+        });
+        if ambiguous || (field.kind == CustomFieldKind::Enum) == values.is_empty() {
+            return Err(CustomFieldError::InvalidAllowedValues(field.name));
+        }
+        self.needs_snapshot = true;
+        self.custom_fields.insert(field.name.clone(), field);
+        Ok(())
+    }
+
+    /// Remove a [CustomField] from the schema, and its values from all tickets.
+    /// Returns `None` if there is no custom field with such a name.
+    pub fn remove_custom_field(&mut self, name: &CustomFieldName) -> Option<CustomField> {
+        let field = self.custom_fields.remove(name)?;
+        self.needs_snapshot = true;
+        for ticket in self.data.values_mut() {
+            if let Some(value) = ticket.custom_fields.remove(name) {
+                ticket.record_change(&self.context, name.as_str(), Some(value), None);
+            }
+        }
+        Some(field)
+    }
+
+    /// Returns the schema of custom fields, sorted by name.
+    pub fn custom_fields(&self) -> Vec<&CustomField> {
+        self.custom_fields.values().collect()
+    }
+
+    /// Validate values of custom fields, as typed by a user, e.g. `3` for `story-points`,
+    /// and return them in their canonical form, ready for a [TicketDraft] or a [TicketPatch].
+    /// Empty values clear a field (`None`), unless it is required.
+    pub fn custom_field_values(
+        &self,
+        values: Vec<(CustomFieldName, String)>,
+    ) -> Result<BTreeMap<CustomFieldName, Option<String>>, CustomFieldError> {
+        let mut checked = BTreeMap::new();
+        for (name, value) in values {
+            let value = if value.trim().is_empty() {
+                match self.custom_fields.get(&name) {
+                    Some(field) if field.required => {
+                        return Err(CustomFieldError::MissingValue(name))
+                    }
+                    Some(_) => None,
+                    None => return Err(CustomFieldError::UnknownField(name)),
+                }
+            } else {
+                Some(self.check_custom_value(&name, &value)?)
+            };
+            checked.insert(name, value);
+        }
+        Ok(checked)
+    }
+
+    /// Check a value of a custom field, and return it in its canonical form (see [CustomField::validate]).
+    fn check_custom_value(
+        &self,
+        name: &CustomFieldName,
+        value: &str,
+    ) -> Result<String, CustomFieldError> {
+        let field = self
+            .custom_fields
+            .get(name)
+            .ok_or_else(|| CustomFieldError::UnknownField(name.clone()))?;
+        let value = field.validate(value)?;
+        if field.kind == CustomFieldKind::User {
+            let registered = UserId::new(value.clone()).is_ok_and(|u| self.users.contains_key(&u));
+            if !registered {
+                return Err(CustomFieldError::InvalidValue {
+                    field: name.clone(),
+                    value,
+                    reason: "there is no user with this handle".to_string(),
+                });
+            }
+        }
+        Ok(value)
+    }
+
+    /// Parse a [Query], which can refer to the custom fields of the store.
+    pub fn parse_query(&self, query: &str) -> Result<Query, QueryError> {
+        query::parse_with_custom_fields(query, &self.custom_fields())
+    }
+
+    /// Retrieve a [Ticket] given an identifier. Returns `None` if there is no ticket with such an identifier.
+    pub fn get(&self, id: &TicketId) -> Option<&Ticket> {
+        self.data.get(id)
+    }
+
+    // Update a [Ticket] given an identifier and new [TicketPatch]. Returns `None` if there is no ticket with such an identifier.
+    pub fn update_ticket(&mut self, id: &TicketId, patch: TicketPatch) -> Option<()> {
+        let operation = Operation::UpdateTicket {
+            id: id.clone(),
+            patch: patch.clone(),
+        };
+        self.logged(operation, |store| store.apply_update(id, patch).ok_or(()))
+            .ok()
+    }
+
+    fn apply_update(&mut self, id: &TicketId, patch: TicketPatch) -> Option<()> {
+        let previous = self.data.get(id)?;
+        let previous_description = previous.description.clone();
+        let previous_changes = previous.history.len();
+        let context = &self.context;
+        self.data.get_mut(id).map(|t| {
+            if let Some(title) = patch.title {
+                t.record_change(
+                    context,
+                    "title",
+                    Some(t.title.to_string()),
+                    Some(title.to_string()),
+                );
+                t.title = title;
+            }
+            if let Some(description) = patch.description {
+                t.record_change(
+                    context,
+                    "description",
+                    Some(t.description.clone()),
+                    Some(description.clone()),
+                );
+                t.description = description;
+            }
+            if let Some(priority) = patch.priority {
+                t.record_change(
+                    context,
+                    "priority",
+                    Some(format!("{:?}", t.priority)),
+                    Some(format!("{:?}", priority)),
+                );
+                t.priority = priority;
+            }
+            if let Some(reporter) = patch.reporter {
+                t.record_change(
+                    context,
+                    "reporter",
+                    t.reporter.as_ref().map(UserId::to_string),
+                    Some(reporter.to_string()),
+                );
+                t.reporter = Some(reporter);
+            }
+            if let Some(assignee) = patch.assignee {
+                t.record_change(
+                    context,
+                    "assignee",
+                    t.assignee.as_ref().map(UserId::to_string),
+                    Some(assignee.to_string()),
+                );
+                t.assignee = Some(assignee);
+            }
+            let remaining = t.remaining;
+            if let Some(estimate) = patch.estimate {
+                t.record_change(
+                    context,
+                    "estimate",
+                    t.estimate.map(|e| e.to_string()),
+                    Some(estimate.to_string()),
+                );
+                t.estimate = Some(estimate);
+                t.remaining = t.remaining.or(Some(estimate));
+            }
+            if let Some(new_remaining) = patch.remaining {
+                t.remaining = Some(new_remaining);
+            }
+            t.record_change(
+                context,
+                "remaining",
+                remaining.map(|r| r.to_string()),
+                t.remaining.map(|r| r.to_string()),
+            );
+            for (name, value) in patch.custom_fields {
+                let old = match &value {
+                    Some(value) => t.custom_fields.insert(name.clone(), value.clone()),
+                    None => t.custom_fields.remove(&name),
+                };
+                t.record_change(context, name.as_str(), old, value);
+            }
+        })?;
+        self.reindex(id);
+        let ticket = &self.data[id];
+        let description = ticket.description.clone();
+        let edited = ticket.history.len() > previous_changes;
+        let mentioned = self.notify_mentions(
+            id,
+            NotificationReason::MentionedInDescription,
+            &description,
+            &previous_description,
+        );
+        if edited {
+            self.notify_watchers(id, NotificationReason::Edited, &mentioned);
+        }
+        Some(())
+    }
+
+    /// Replace the [Workflow] enforced by [update_ticket_status](TicketStore::update_ticket_status).
+    pub fn set_workflow(&mut self, workflow: Workflow) {
+        self.workflow = workflow;
+    }
+
+    /// Returns the statuses a [Ticket] can be moved to according to the current [Workflow].
+    /// Returns `None` if there is no ticket with such an identifier.
+    pub fn next_statuses(&self, id: &TicketId) -> Option<Vec<Status>> {
+        self.data
+            .get(id)
+            .map(|t| self.workflow.next_statuses(&t.status))
+    }
+
+    /// Find the configured [Status] `input` refers to, e.g. `in-review` for `In Review`.
+    /// Returns `None` if no status of the current [Workflow] matches.
+    pub fn resolve_status(&self, input: &str) -> Option<Status> {
+        self.workflow.resolve(input)
+    }
+
+    /// Returns the statuses of the current [Workflow], in board order.
+    pub fn statuses(&self) -> Vec<&Status> {
+        self.workflow.statuses.iter().map(|s| &s.name).collect()
+    }
+
+    /// Returns the [StatusCategory] of a [Status] in the current [Workflow].
+    /// Returns `None` if the status is not part of the workflow.
+    pub fn status_category(&self, status: &Status) -> Option<StatusCategory> {
+        self.workflow.category(status)
+    }
+
+    // Update a [Ticket] [Status] given an identifier and new [Status], following the rules of the current [Workflow].
+    // `comment`, if provided, is added to the ticket comments - some transitions require it.
+    pub fn update_ticket_status(
+        &mut self,
+        id: &TicketId,
+        status: Status,
+        comment: Option<Comment>,
+    ) -> Result<(), TransitionError> {
+        let ticket = self
+            .data
+            .get(id)
+            .ok_or_else(|| TransitionError::TicketNotFound(id.clone()))?;
+        if self.workflow.category(&status).is_none() {
+            return Err(TransitionError::UnknownStatus(status));
+        }
+        if !self.workflow.allows(&ticket.status, &status) {
+            return Err(TransitionError::NotAllowed {
+                from: ticket.status.clone(),
+                to: status,
+                allowed: self.workflow.next_statuses(&ticket.status),
+            });
+        }
+        for field in self.workflow.required_fields(&status) {
+            let is_missing = match field {
+                RequiredField::Comment => comment.is_none(),
+                RequiredField::Assignee => ticket.assignee.is_none(),
+            };
+            if is_missing {
+                return Err(TransitionError::MissingField {
+                    status,
+                    field: *field,
+                });
+            }
+        }
+        let operation = Operation::UpdateTicketStatus {
+            id: id.clone(),
+            status: status.clone(),
+            comment: comment.clone(),
+        };
+        self.logged(operation, |store| {
+            store.apply_status(id, status, comment);
+            Ok(())
+        })
+    }
+
+    /// Move a [Ticket] to a new [Status], without checking the [Workflow].
+    fn apply_status(
+        &mut self,
+        id: &TicketId,
+        status: Status,
+        comment: Option<Comment>,
+    ) -> Option<()> {
+        let ticket = self.data.get_mut(id)?;
+        ticket.record_change(
+            &self.context,
+            "status",
+            Some(ticket.status.to_string()),
+            Some(status.to_string()),
+        );
+        let moved = ticket.status != status;
+        ticket.status = status.clone();
+        let mut mentioned = BTreeSet::new();
+        if let Some(comment) = comment {
+            let text = comment.to_string();
+            let comment_id = ticket.post_comment(&self.context, comment, None);
+            self.reindex(id);
+            mentioned = self.notify_mentions(
+                id,
+                NotificationReason::MentionedInComment(comment_id),
+                &text,
+                "",
+            );
+        }
+        if moved {
+            self.notify_watchers(id, NotificationReason::StatusChanged(status), &mentioned);
+        }
+        Some(())
+    }
+
+    /// Post a comment on a [Ticket], on behalf of the current actor (see [set_actor](TicketStore::set_actor)),
+    /// starting a new thread or replying to the comment `reply_to` of the same ticket.
+    /// Returns the id of the new comment.
+    pub fn add_comment_to_ticket(
+        &mut self,
+        id: &TicketId,
+        comment: Comment,
+        reply_to: Option<CommentId>,
+    ) -> Result<CommentId, ReplyError> {
+        let operation = Operation::AddComment {
+            id: id.clone(),
+            comment: comment.clone(),
+            reply_to,
+        };
+        self.logged(operation, |store| {
+            store.apply_comment(id, comment, reply_to)
+        })
+    }
+
+    fn apply_comment(
+        &mut self,
+        id: &TicketId,
+        comment: Comment,
+        reply_to: Option<CommentId>,
+    ) -> Result<CommentId, ReplyError> {
+        let ticket = self
+            .data
+            .get_mut(id)
+            .ok_or_else(|| ReplyError::TicketNotFound(id.clone()))?;
+        if let Some(parent) = reply_to {
+            if !ticket.comments.iter().any(|c| c.id == parent) {
+                return Err(ReplyError::CommentNotFound {
+                    ticket: id.clone(),
+                    comment: parent,
+                });
+            }
+        }
+        let text = comment.to_string();
+        let comment_id = ticket.post_comment(&self.context, comment, reply_to);
+        self.reindex(id);
+        let mentioned = self.notify_mentions(
+            id,
+            NotificationReason::MentionedInComment(comment_id),
+            &text,
+            "",
+        );
+        self.notify_watchers(id, NotificationReason::Commented(comment_id), &mentioned);
+        Ok(comment_id)
+    }
+
+    /// Replace the text of a comment, keeping the previous version in its revisions.
+    /// Returns `None` if there is no such ticket or no such comment on it.
+    pub fn edit_comment(
+        &mut self,
+        id: &TicketId,
+        comment_id: CommentId,
+        body: Comment,
+    ) -> Option<()> {
+        let operation = Operation::EditComment {
+            id: id.clone(),
+            comment_id,
+            body: body.clone(),
+        };
+        self.logged(operation, |store| {
+            store.apply_edit_comment(id, comment_id, body).ok_or(())
+        })
+        .ok()
+    }
+
+    fn apply_edit_comment(
+        &mut self,
+        id: &TicketId,
+        comment_id: CommentId,
+        body: Comment,
+    ) -> Option<()> {
+        let ticket = self.data.get_mut(id)?;
+        let comment = ticket.comments.iter_mut().find(|c| c.id == comment_id)?;
+        let at = self.context.now();
+        let previous = std::mem::replace(&mut comment.body, body);
+        let new = comment.body.to_string();
+        comment.edited_at = Some(at);
+        comment.revisions.push(CommentRevision {
+            body: previous.clone(),
+            replaced_at: at,
+            replaced_by: self.context.actor.clone(),
+        });
+        ticket.record_change(
+            &self.context,
+            "comment",
+            Some(previous.to_string()),
+            Some(new.clone()),
+        );
+        self.reindex(id);
+        self.notify_mentions(
+            id,
+            NotificationReason::MentionedInComment(comment_id),
+            &new,
+            &previous.to_string(),
+        );
+        Some(())
+    }
+
+    /// Notify the registered users mentioned in `text` who were not mentioned in `previous`,
+    /// the text it replaces, and return who was notified.
+    fn notify_mentions(
+        &mut self,
+        ticket: &TicketId,
+        reason: NotificationReason,
+        text: &str,
+        previous: &str,
+    ) -> BTreeSet<UserId> {
+        let already_mentioned = mentions(previous);
+        mentions(text)
+            .into_iter()
+            .filter(|user| !already_mentioned.contains(user))
+            .filter(|user| self.notify(user, ticket, reason.clone()))
+            .collect()
+    }
+
+    /// Notify the watchers of a ticket of a change, except those in `notified`:
+    /// they already received a notification for it.
+    fn notify_watchers(
+        &mut self,
+        ticket: &TicketId,
+        reason: NotificationReason,
+        notified: &BTreeSet<UserId>,
+    ) {
+        let watchers = self.data[ticket].watchers.clone();
+        for watcher in watchers.difference(notified) {
+            self.notify(watcher, ticket, reason.clone());
+        }
+    }
+
+    /// Add a [Notification] to the inbox of a registered user, unless they caused it.
+    /// Returns whether the user was notified.
+    fn notify(&mut self, user: &UserId, ticket: &TicketId, reason: NotificationReason) -> bool {
+        if !self.users.contains_key(user) || self.context.actor.as_ref() == Some(user) {
+            return false;
+        }
+        self.notification_id += 1;
+        let notification = Notification {
+            id: self.notification_id,
+            ticket: ticket.clone(),
+            reason,
+            actor: self.context.actor.clone(),
+            at: self.context.now(),
+            read: false,
+        };
+        self.inboxes
+            .entry(user.clone())
+            .or_default()
+            .push(notification);
+        true
+    }
+
+    /// The id of the last [Notification] sent, to find out which ones a command sends.
+    pub fn last_notification_id(&self) -> NotificationId {
+        self.notification_id
+    }
+
+    /// Returns the notifications sent after the one with id `after`, with their recipient, the oldest first.
+    pub fn notifications_since(&self, after: NotificationId) -> Vec<(&UserId, &Notification)> {
+        let mut notifications: Vec<(&UserId, &Notification)> = self
+            .inboxes
+            .iter()
+            .flat_map(|(user, inbox)| inbox.iter().map(move |n| (user, n)))
+            .filter(|(_, n)| n.id > after)
+            .collect();
+        notifications.sort_by_key(|(_, n)| n.id);
+        notifications
+    }
+
+    /// Add a [User] to the watchers of a [Ticket]: they are notified when it is moved, edited or commented.
+    /// Returns `None` if there is no ticket with such an identifier.
+    pub fn watch(&mut self, id: &TicketId, user: UserId) -> Option<()> {
+        self.needs_snapshot = true;
+        let ticket = self.data.get_mut(id)?;
+        if !ticket.watchers.contains(&user) {
+            ticket.record_change(&self.context, "watchers", None, Some(user.to_string()));
+            ticket.watchers.insert(user);
+        }
+        Some(())
+    }
+
+    /// Remove a [User] from the watchers of a [Ticket].
+    /// Returns `None` if there is no ticket with such an identifier or if the user was not watching it.
+    pub fn unwatch(&mut self, id: &TicketId, user: &UserId) -> Option<()> {
+        self.needs_snapshot = true;
+        let ticket = self.data.get_mut(id)?;
+        if !ticket.watchers.remove(user) {
+            return None;
+        }
+        ticket.record_change(&self.context, "watchers", Some(user.to_string()), None);
+        Some(())
+    }
+
+    /// Returns the notifications of a [User], the most recent first.
+    pub fn inbox(&self, user: &UserId) -> Vec<&Notification> {
+        self.inboxes
+            .get(user)
+            .map_or_else(Vec::new, |inbox| inbox.iter().rev().collect())
+    }
+
+    /// Mark a notification of a [User] as read.
+    /// Returns `None` if there is no notification with such an identifier in their inbox.
+    pub fn mark_as_read(&mut self, user: &UserId, id: NotificationId) -> Option<()> {
+        self.needs_snapshot = true;
+        self.inboxes
+            .get_mut(user)?
+            .iter_mut()
+            .find(|n| n.id == id)
+            .map(|n| n.read = true)
+    }
+
+    /// Mark all the notifications of a [User] as read and return how many were unread.
+    pub fn mark_all_as_read(&mut self, user: &UserId) -> usize {
+        self.needs_snapshot = true;
+        let mut unread = 0;
+        for notification in self.inboxes.get_mut(user).into_iter().flatten() {
+            if !notification.read {
+                notification.read = true;
+                unread += 1;
+            }
+        }
+        unread
+    }
+
+    /// Remove a comment from a [Ticket] and return it. Its replies are moved up one level,
+    /// to the comment it replied to.
+    /// Returns `None` if there is no such ticket or no such comment on it.
+    pub fn delete_comment(
+        &mut self,
+        id: &TicketId,
+        comment_id: CommentId,
+    ) -> Option<TicketComment> {
+        let operation = Operation::DeleteComment {
+            id: id.clone(),
+            comment_id,
         };
-        self.data.insert(ticket.id, ticket);
-        id
+        self.logged(operation, |store| {
+            store.apply_delete_comment(id, comment_id).ok_or(())
+        })
+        .ok()
+    }
+
+    fn apply_delete_comment(
+        &mut self,
+        id: &TicketId,
+        comment_id: CommentId,
+    ) -> Option<TicketComment> {
+        let ticket = self.data.get_mut(id)?;
+        let position = ticket.comments.iter().position(|c| c.id == comment_id)?;
+        let comment = ticket.comments.remove(position);
+        for reply in ticket.comments.iter_mut() {
+            if reply.reply_to == Some(comment_id) {
+                reply.reply_to = comment.reply_to;
+            }
+        }
+        ticket.record_change(
+            &self.context,
+            "comment",
+            Some(comment.body.to_string()),
+            None,
+        );
+        self.reindex(id);
+        Some(comment)
+    }
+
+    /// Register a [User] in the store, replacing the previous details if the handle was already taken.
+    pub fn add_user(&mut self, user: User) -> Option<User> {
+        self.needs_snapshot = true;
+        self.users.insert(user.id.clone(), user)
     }
 
-    /// Remove a [Ticket] from the store.
-    /// Returns None if the [Ticket](Ticket) is not there or [DeletedTicket](DeletedTicket) if there was one.
-    pub fn delete(&mut self, ticket_id: TicketId) -> Option<DeletedTicket> {
-        self.data.remove(&ticket_id).map(DeletedTicket)
+    /// Retrieve a [User] given their handle. Returns `None` if nobody is registered with that handle.
+    pub fn get_user(&self, id: &UserId) -> Option<&User> {
+        self.users.get(id)
+    }
+
+    /// Returns all registered [User]s, sorted by handle.
+    pub fn list_users(&self) -> Vec<&User> {
+        let mut users: Vec<&User> = self.users.values().collect();
+        users.sort_by(|a, b| a.id.cmp(&b.id));
+        users
+    }
+
+    /// Assign a [Ticket] to a [User]. Returns `None` if there is no ticket with such an identifier.
+    pub fn assign(&mut self, id: &TicketId, assignee: UserId) -> Option<()> {
+        self.needs_snapshot = true;
+        let context = &self.context;
+        self.data.get_mut(id).map(|t| {
+            t.record_change(
+                context,
+                "assignee",
+                t.assignee.as_ref().map(UserId::to_string),
+                Some(assignee.to_string()),
+            );
+            t.assignee = Some(assignee)
+        })
+    }
+
+    /// Remove the assignee of a [Ticket]. Returns `None` if there is no ticket with such an identifier.
+    pub fn unassign(&mut self, id: &TicketId) -> Option<()> {
+        self.needs_snapshot = true;
+        let context = &self.context;
+        self.data.get_mut(id).map(|t| {
+            t.record_change(
+                context,
+                "assignee",
+                t.assignee.as_ref().map(UserId::to_string),
+                None,
+            );
+            t.assignee = None
+        })
+    }
+
+    /// Returns all [Ticket]s assigned to a [User], the most urgent first.
+    pub fn list_by_assignee(&self, assignee: &UserId) -> Vec<&Ticket> {
+        Self::by_priority(
+            self.data
+                .values()
+                .filter(|t| t.assignee.as_ref() == Some(assignee))
+                .collect(),
+        )
+    }
+
+    /// Attach a [Label] to a [Ticket]. Returns `None` if there is no ticket with such an identifier,
+    /// `Some(false)` if the ticket already had that label.
+    pub fn add_label(&mut self, id: &TicketId, label: Label) -> Option<bool> {
+        self.needs_snapshot = true;
+        let context = &self.context;
+        self.data.get_mut(id).map(|t| {
+            let added = !t.labels.contains(&label);
+            if added {
+                t.record_change(context, "labels", None, Some(label.to_string()));
+                t.labels.insert(label);
+            }
+            added
+        })
+    }
+
+    /// Detach a [Label] from a [Ticket]. Returns `None` if there is no ticket with such an identifier,
+    /// `Some(false)` if the ticket did not have that label.
+    pub fn remove_label(&mut self, id: &TicketId, label: &Label) -> Option<bool> {
+        self.needs_snapshot = true;
+        let context = &self.context;
+        self.data.get_mut(id).map(|t| {
+            let removed = t.labels.remove(label);
+            if removed {
+                t.record_change(context, "labels", Some(label.to_string()), None);
+            }
+            removed
+        })
+    }
+
+    /// Returns all [Ticket]s tagged with a [Label], the most urgent first.
+    pub fn tickets_with_label(&self, label: &Label) -> Vec<&Ticket> {
+        Self::by_priority(
+            self.data
+                .values()
+                .filter(|t| t.labels.contains(label))
+                .collect(),
+        )
+    }
+
+    /// Link two [Ticket]s, e.g. `link(CORE-1, LinkKind::Blocks, CORE-2)` to record that CORE-1 blocks CORE-2.
+    /// The inverse link is added to the target ticket (CORE-2 is blocked by CORE-1).
+    pub fn link(
+        &mut self,
+        from: &TicketId,
+        kind: LinkKind,
+        to: &TicketId,
+    ) -> Result<(), LinkError> {
+        self.needs_snapshot = true;
+        if from == to {
+            return Err(LinkError::SelfLink(from.clone()));
+        }
+        for id in [from, to].iter() {
+            if !self.data.contains_key(id) {
+                return Err(LinkError::TicketNotFound((*id).clone()));
+            }
+        }
+        let links = [
+            (
+                from,
+                TicketLink {
+                    kind,
+                    target: to.clone(),
+                },
+            ),
+            (
+                to,
+                TicketLink {
+                    kind: kind.inverse(),
+                    target: from.clone(),
+                },
+            ),
+        ];
+        for (id, link) in links.iter() {
+            let ticket = self.data.get_mut(id).unwrap();
+            if ticket.links.insert(link.clone()) {
+                ticket.record_change(&self.context, "links", None, Some(link.to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove a link between two [Ticket]s, together with its inverse.
+    /// Returns `None` if there was no such link.
+    pub fn unlink(&mut self, from: &TicketId, kind: LinkKind, to: &TicketId) -> Option<()> {
+        self.needs_snapshot = true;
+        let link = TicketLink {
+            kind,
+            target: to.clone(),
+        };
+        let ticket = self.data.get_mut(from)?;
+        if !ticket.links.remove(&link) {
+            return None;
+        }
+        ticket.record_change(&self.context, "links", Some(link.to_string()), None);
+        if let Some(target) = self.data.get_mut(to) {
+            let inverse = TicketLink {
+                kind: kind.inverse(),
+                target: from.clone(),
+            };
+            target.links.remove(&inverse);
+            target.record_change(&self.context, "links", Some(inverse.to_string()), None);
+        }
+        Some(())
+    }
+
+    /// Make `parent` the parent of a [Ticket], or detach the ticket from its current parent
+    /// if `parent` is `None`.
+    /// Fails if the ticket does not exist or if the new parent violates the hierarchy rules (see [TicketKind]).
+    pub fn set_parent(
+        &mut self,
+        id: &TicketId,
+        parent: Option<TicketId>,
+    ) -> Result<(), HierarchyError> {
+        self.needs_snapshot = true;
+        let kind = self
+            .data
+            .get(id)
+            .ok_or_else(|| HierarchyError::TicketNotFound(id.clone()))?
+            .kind;
+        if let Some(parent) = &parent {
+            self.check_parent(Some(id), kind, parent)?;
+        }
+        let ticket = self.data.get_mut(id).unwrap();
+        ticket.record_change(
+            &self.context,
+            "parent",
+            ticket.parent.as_ref().map(TicketId::to_string),
+            parent.as_ref().map(TicketId::to_string),
+        );
+        ticket.parent = parent;
+        Ok(())
+    }
+
+    /// Returns the direct children of a [Ticket], the most urgent first.
+    pub fn children(&self, id: &TicketId) -> Vec<&Ticket> {
+        Self::by_priority(
+            self.data
+                .values()
+                .filter(|t| t.parent.as_ref() == Some(id))
+                .collect(),
+        )
+    }
+
+    /// Returns how many of the descendants of a [Ticket] are [done](StatusCategory::Done), out of its total
+    /// number of descendants. Returns `None` if there is no ticket with such an identifier.
+    pub fn progress(&self, id: &TicketId) -> Option<(usize, usize)> {
+        self.data.get(id)?;
+        let mut done = 0;
+        let mut total = 0;
+        let mut to_visit = vec![id];
+        while let Some(current) = to_visit.pop() {
+            for child in self.children(current) {
+                total += 1;
+                if self.status_category(&child.status) == Some(StatusCategory::Done) {
+                    done += 1;
+                }
+                to_visit.push(&child.id);
+            }
+        }
+        Some((done, total))
+    }
+
+    /// Check that a ticket of the specified kind can be a child of `parent`.
+    /// `ticket` is `None` if the ticket has not been created yet.
+    fn check_parent(
+        &self,
+        ticket: Option<&TicketId>,
+        kind: TicketKind,
+        parent: &TicketId,
+    ) -> Result<(), HierarchyError> {
+        let parent_ticket = self
+            .data
+            .get(parent)
+            .ok_or_else(|| HierarchyError::TicketNotFound(parent.clone()))?;
+        if kind == TicketKind::Epic {
+            return Err(HierarchyError::EpicCannotHaveParent);
+        }
+        if parent_ticket.kind == TicketKind::SubTask {
+            return Err(HierarchyError::SubTaskCannotHaveChildren(parent.clone()));
+        }
+        if let Some(ticket) = ticket {
+            // Walk up the hierarchy: we must not meet the ticket itself on the way to the top.
+            let mut ancestor = Some(parent);
+            while let Some(current) = ancestor {
+                if current == ticket {
+                    return Err(HierarchyError::Cycle {
+                        ticket: ticket.clone(),
+                        parent: parent.clone(),
+                    });
+                }
+                ancestor = self.data.get(current).and_then(|t| t.parent.as_ref());
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns every [Label] in use, with the number of [Ticket]s carrying it.
+    pub fn labels(&self) -> BTreeMap<&Label, usize> {
+        let mut labels = BTreeMap::new();
+        for label in self.data.values().flat_map(|t| t.labels.iter()) {
+            *labels.entry(label).or_insert(0) += 1;
+        }
+        labels
+    }
+
+    /// Plan a new [Sprint]: it starts empty, in the [planned](SprintState::Planned) state.
+    /// Fails if the sprint would end before it starts.
+    pub fn create_sprint(&mut self, draft: SprintDraft) -> Result<SprintId, SprintError> {
+        self.needs_snapshot = true;
+        if draft.end < draft.start {
+            return Err(SprintError::EndBeforeStart);
+        }
+        self.sprint_id += 1;
+        let sprint = Sprint {
+            id: self.sprint_id,
+            name: draft.name,
+            goal: draft.goal,
+            start: draft.start,
+            end: draft.end,
+            state: SprintState::Planned,
+            carried_over: BTreeSet::new(),
+        };
+        self.sprints.insert(sprint.id, sprint);
+        Ok(self.sprint_id)
+    }
+
+    pub fn get_sprint(&self, id: SprintId) -> Option<&Sprint> {
+        self.sprints.get(&id)
+    }
+
+    /// Returns all sprints, the oldest first.
+    pub fn list_sprints(&self) -> Vec<&Sprint> {
+        self.sprints.values().collect()
+    }
+
+    /// Move a [Ticket] into a planned or active [Sprint], taking it out of the backlog
+    /// or of the sprint it was part of.
+    pub fn add_to_sprint(&mut self, id: &TicketId, sprint: SprintId) -> Result<(), SprintError> {
+        self.needs_snapshot = true;
+        let state = self
+            .sprints
+            .get(&sprint)
+            .ok_or(SprintError::SprintNotFound(sprint))?
+            .state;
+        if state == SprintState::Completed {
+            return Err(SprintError::Completed(sprint));
+        }
+        let ticket = self
+            .data
+            .get_mut(id)
+            .ok_or_else(|| SprintError::TicketNotFound(id.clone()))?;
+        ticket.record_change(
+            &self.context,
+            "sprint",
+            ticket.sprint.map(|s| s.to_string()),
+            Some(sprint.to_string()),
+        );
+        ticket.sprint = Some(sprint);
+        Ok(())
+    }
+
+    /// Move a [Ticket] back to the backlog.
+    /// Returns `None` if there is no ticket with such an identifier.
+    pub fn remove_from_sprint(&mut self, id: &TicketId) -> Option<()> {
+        self.needs_snapshot = true;
+        let context = &self.context;
+        self.data.get_mut(id).map(|t| {
+            t.record_change(context, "sprint", t.sprint.map(|s| s.to_string()), None);
+            t.sprint = None
+        })
+    }
+
+    /// Returns the tickets of a [Sprint], the most urgent first.
+    pub fn sprint_tickets(&self, sprint: SprintId) -> Vec<&Ticket> {
+        Self::by_priority(
+            self.data
+                .values()
+                .filter(|t| t.sprint == Some(sprint))
+                .collect(),
+        )
+    }
+
+    /// Returns the tickets that are not part of any sprint, the most urgent first.
+    pub fn backlog(&self) -> Vec<&Ticket> {
+        Self::by_priority(self.data.values().filter(|t| t.sprint.is_none()).collect())
+    }
+
+    /// Start a planned [Sprint]. Only one sprint can be active at a time.
+    pub fn start_sprint(&mut self, id: SprintId) -> Result<(), SprintError> {
+        self.needs_snapshot = true;
+        if let Some(active) = self
+            .sprints
+            .values()
+            .find(|s| s.state == SprintState::Active)
+        {
+            return Err(SprintError::AlreadyActive(active.id));
+        }
+        let sprint = self
+            .sprints
+            .get_mut(&id)
+            .ok_or(SprintError::SprintNotFound(id))?;
+        if sprint.state != SprintState::Planned {
+            return Err(SprintError::NotPlanned(id));
+        }
+        sprint.state = SprintState::Active;
+        Ok(())
+    }
+
+    /// Complete the active [Sprint]: its tickets that are not [done](StatusCategory::Done) are
+    /// carried over to the backlog or to another sprint that has not been completed yet.
+    /// Returns the ids of the tickets that were carried over.
+    pub fn complete_sprint(
+        &mut self,
+        id: SprintId,
+        carry_over: CarryOver,
+    ) -> Result<Vec<TicketId>, SprintError> {
+        self.needs_snapshot = true;
+        let state = self
+            .sprints
+            .get(&id)
+            .ok_or(SprintError::SprintNotFound(id))?
+            .state;
+        if state != SprintState::Active {
+            return Err(SprintError::NotActive(id));
+        }
+        let destination = match carry_over {
+            CarryOver::Backlog => None,
+            CarryOver::Sprint(next) => {
+                let next_state = self
+                    .sprints
+                    .get(&next)
+                    .ok_or(SprintError::SprintNotFound(next))?
+                    .state;
+                if next == id {
+                    return Err(SprintError::CarryOverToSelf(id));
+                }
+                if next_state == SprintState::Completed {
+                    return Err(SprintError::Completed(next));
+                }
+                Some(next)
+            }
+        };
+        let unfinished: Vec<TicketId> = self
+            .sprint_tickets(id)
+            .into_iter()
+            .filter(|t| self.status_category(&t.status) != Some(StatusCategory::Done))
+            .map(|t| t.id.clone())
+            .collect();
+        for ticket_id in unfinished.iter() {
+            let ticket = self.data.get_mut(ticket_id).unwrap();
+            ticket.record_change(
+                &self.context,
+                "sprint",
+                ticket.sprint.map(|s| s.to_string()),
+                destination.map(|s| s.to_string()),
+            );
+            ticket.sprint = destination;
+        }
+        let sprint = self.sprints.get_mut(&id).unwrap();
+        sprint.state = SprintState::Completed;
+        sprint.carried_over = unfinished.iter().cloned().collect();
+        Ok(unfinished)
+    }
+
+    /// Count the tickets of a [Sprint] by [category](StatusCategory) of their status.
+    /// Returns `None` if there is no sprint with such an identifier.
+    pub fn sprint_summary(&self, id: SprintId) -> Option<SprintSummary> {
+        let sprint = self.sprints.get(&id)?;
+        let mut summary = SprintSummary {
+            carried_over: sprint.carried_over.len(),
+            ..SprintSummary::default()
+        };
+        for ticket in self.sprint_tickets(id) {
+            match self.status_category(&ticket.status) {
+                Some(StatusCategory::Done) => summary.done += 1,
+                Some(StatusCategory::InProgress) => summary.in_progress += 1,
+                Some(StatusCategory::ToDo) | None => summary.to_do += 1,
+            }
+        }
+        Some(summary)
+    }
+
+    /// Record time spent on a [Ticket]. Its remaining estimate is lowered by the time spent,
+    /// unless a new `remaining` estimate is specified.
+    /// Fails if there is no ticket with such an identifier, or if the total time spent on the ticket
+    /// would overflow.
+    pub fn log_work(
+        &mut self,
+        id: &TicketId,
+        worklog: Worklog,
+        remaining: Option<WorkDuration>,
+    ) -> Result<(), WorklogError> {
+        let ticket = self
+            .data
+            .get_mut(id)
+            .ok_or_else(|| WorklogError::TicketNotFound(id.clone()))?;
+        ticket
+            .worklogs
+            .iter()
+            .try_fold(worklog.time_spent, |total, w| {
+                total.checked_add(w.time_spent)
+            })
+            .ok_or_else(|| WorklogError::TooLong(id.clone()))?;
+        self.needs_snapshot = true;
+        let previous = ticket.remaining;
+        ticket.remaining = remaining.or_else(|| {
+            ticket
+                .remaining
+                .or(ticket.estimate)
+                .map(|left| left.saturating_sub(worklog.time_spent))
+        });
+        ticket.record_change(&self.context, "worklog", None, Some(worklog.to_string()));
+        ticket.record_change(
+            &self.context,
+            "remaining",
+            previous.map(|r| r.to_string()),
+            ticket.remaining.map(|r| r.to_string()),
+        );
+        ticket.worklogs.push(worklog);
+        Ok(())
+    }
+
+    /// Attach a file saved as `blob` to a [Ticket], replacing the attachment with the same name if any.
+    /// Returns `None` if there is no ticket with such an identifier.
+    pub fn attach(&mut self, id: &TicketId, name: String, blob: Blob) -> Option<()> {
+        self.needs_snapshot = true;
+        let ticket = self.data.get_mut(id)?;
+        let previous = ticket
+            .attachments
+            .iter()
+            .position(|a| a.name == name)
+            .map(|position| ticket.attachments.remove(position));
+        let attachment = Attachment {
+            mime_type: mime_type(&name).to_string(),
+            name,
+            blob,
+            attached_at: self.context.now(),
+            attached_by: self.context.actor.clone(),
+        };
+        ticket.record_change(
+            &self.context,
+            "attachments",
+            previous.map(|a| a.to_string()),
+            Some(attachment.to_string()),
+        );
+        ticket.attachments.push(attachment);
+        Some(())
+    }
+
+    /// Returns the hashes of the files attached to tickets, including those in the trash.
+    pub fn attachment_hashes(&self) -> BTreeSet<&str> {
+        self.data
+            .values()
+            .chain(self.trash.values().map(|deleted| &deleted.ticket))
+            .flat_map(|t| t.attachments.iter())
+            .map(|a| a.blob.hash.as_str())
+            .collect()
+    }
+
+    /// Sum the time logged between `from` and `to` (both included) by author and by day.
+    /// Days are computed in the `timezone` of the reader.
+    pub fn timesheet<Tz: TimeZone>(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+        timezone: &Tz,
+    ) -> BTreeMap<&UserId, BTreeMap<NaiveDate, WorkDuration>> {
+        let mut timesheet: BTreeMap<&UserId, BTreeMap<NaiveDate, WorkDuration>> = BTreeMap::new();
+        for worklog in self.data.values().flat_map(|t| t.worklogs.iter()) {
+            let day = worklog
+                .started_at
+                .with_timezone(timezone)
+                .naive_local()
+                .date();
+            if day < from || day > to {
+                continue;
+            }
+            *timesheet
+                .entry(&worklog.author)
+                .or_default()
+                .entry(day)
+                .or_default() += worklog.time_spent;
+        }
+        timesheet
+    }
+
+    /// Apply `apply` and, if it succeeds, record `operation` as a new [Event].
+    /// All the changes made by `apply` share the timestamp of the event.
+    fn logged<T, E>(
+        &mut self,
+        operation: Operation,
+        apply: impl FnOnce(&mut Self) -> Result<T, E>,
+    ) -> Result<T, E> {
+        let at = self.context.now();
+        let previous = self.context.at.replace(at);
+        let result = apply(self);
+        self.context.at = previous;
+        if result.is_ok() {
+            self.event_sequence += 1;
+            self.events.push(Event {
+                sequence: self.event_sequence,
+                at,
+                actor: self.context.actor.clone(),
+                operation,
+            });
+        }
+        result
+    }
+
+    /// Apply an [Event] read from the event log, as its actor did at its time.
+    /// Events that are already part of the store, i.e. of the snapshot it was loaded from, are skipped.
+    /// Returns `None` if the event cannot be applied.
+    pub fn replay(&mut self, event: Event) -> Option<()> {
+        if event.sequence <= self.event_sequence {
+            return Some(());
+        }
+        let context = std::mem::replace(
+            &mut self.context,
+            ChangeContext {
+                actor: event.actor,
+                at: Some(event.at),
+            },
+        );
+        let result = match event.operation {
+            Operation::Create { draft, status } => {
+                self.apply_create(draft, status).ok().map(|_| ())
+            }
+            Operation::UpdateTicket { id, patch } => self.apply_update(&id, patch),
+            Operation::UpdateTicketStatus {
+                id,
+                status,
+                comment,
+            } => self.apply_status(&id, status, comment),
+            Operation::AddComment {
+                id,
+                comment,
+                reply_to,
+            } => self.apply_comment(&id, comment, reply_to).ok().map(|_| ()),
+            Operation::EditComment {
+                id,
+                comment_id,
+                body,
+            } => self.apply_edit_comment(&id, comment_id, body),
+            Operation::DeleteComment { id, comment_id } => {
+                self.apply_delete_comment(&id, comment_id).map(|_| ())
+            }
+            Operation::Delete { id } => self.apply_delete(&id).map(|_| ()),
+        };
+        self.context = context;
+        self.event_sequence = event.sequence;
+        result
+    }
+
+    /// Returns the events applied since the store was loaded, leaving none behind.
+    pub fn take_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Whether the store was changed in a way the event log cannot reproduce,
+    /// e.g. a label was added: the whole store must be saved.
+    pub fn needs_snapshot(&self) -> bool {
+        self.needs_snapshot
+    }
+
+    /// Copy the collections of the store that commands can change, to find out what a command changes.
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            tickets: self.data.clone(),
+            sprints: self.sprints.clone(),
+            trash: self.trash.clone(),
+            users: self.users.clone(),
+            projects: self.projects.clone(),
+            filters: self.filters.clone(),
+            custom_fields: self.custom_fields.clone(),
+            inboxes: self.inboxes.clone(),
+        }
+    }
+
+    /// The changes made to the store since `checkpoint` was taken, by the `command` line.
+    /// Returns `None` if nothing changed.
+    pub fn changes_since(&self, checkpoint: &Checkpoint, command: String) -> Option<UndoStep> {
+        let step = UndoStep {
+            command,
+            at: self.context.now(),
+            tickets: Change::between(&checkpoint.tickets, &self.data),
+            sprints: Change::between(&checkpoint.sprints, &self.sprints),
+            trash: Change::between(&checkpoint.trash, &self.trash),
+            users: Change::between(&checkpoint.users, &self.users),
+            projects: Change::between(&checkpoint.projects, &self.projects),
+            filters: Change::between(&checkpoint.filters, &self.filters),
+            custom_fields: Change::between(&checkpoint.custom_fields, &self.custom_fields),
+            inboxes: Change::between(&checkpoint.inboxes, &self.inboxes),
+        };
+        if step.is_empty() {
+            None
+        } else {
+            Some(step)
+        }
+    }
+
+    /// Revert the changes of an [UndoStep]: everything it changed goes back to its previous state,
+    /// e.g. a deleted ticket is restored as it was, comments and links included.
+    pub fn undo(&mut self, step: &UndoStep) {
+        self.reset(step, false);
+    }
+
+    /// Apply the changes of an [UndoStep] again, after they were undone.
+    pub fn redo(&mut self, step: &UndoStep) {
+        self.reset(step, true);
+    }
+
+    /// Put everything an [UndoStep] changed in its state before the command, or after it if `redo`.
+    /// The restored tickets record the action in their history.
+    fn reset(&mut self, step: &UndoStep, redo: bool) {
+        self.needs_snapshot = true;
+        let action = if redo { "redo" } else { "undo" };
+        for change in step.tickets.iter() {
+            match change.version(redo) {
+                Some(ticket) => {
+                    let mut ticket = ticket.clone();
+                    ticket.record_change(
+                        &self.context,
+                        action,
+                        None,
+                        Some(step.command.to_string()),
+                    );
+                    self.data.insert(change.id.clone(), ticket);
+                }
+                None => {
+                    self.data.remove(&change.id);
+                }
+            }
+            self.reindex(&change.id);
+        }
+        for change in step.sprints.iter() {
+            change.reset(&mut self.sprints, redo);
+        }
+        for change in step.trash.iter() {
+            change.reset(&mut self.trash, redo);
+        }
+        for change in step.users.iter() {
+            change.reset(&mut self.users, redo);
+        }
+        for change in step.projects.iter() {
+            change.reset(&mut self.projects, redo);
+        }
+        for change in step.filters.iter() {
+            change.reset(&mut self.filters, redo);
+        }
+        for change in step.custom_fields.iter() {
+            change.reset(&mut self.custom_fields, redo);
+        }
+        for change in step.inboxes.iter() {
+            change.reset(&mut self.inboxes, redo);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{
+        Age, Blob, CarryOver, Comment, CommentId, CreateError, CustomField, CustomFieldError,
+        CustomFieldKind, CustomFieldName, FilterName, HierarchyError, Label, LinkError, LinkKind,
+        NotificationReason, Priority, Project, ProjectKey, ReplyError, RequiredField, SavedFilter,
+        SelectionError, SprintDraft, SprintError, SprintId, SprintState, SprintSummary, Status,
+        StatusCategory, Ticket, TicketDraft, TicketId, TicketKind, TicketLink, TicketPatch,
+        TicketSelection, Title, TransitionError, UndoStack, User, UserId, Workflow, Worklog,
+        WorklogError,
+    };
+    use crate::query::{self, Field, OrderBy};
+    use crate::store::TicketStore;
+    use chrono::{Duration, NaiveDate, Utc};
+    use fake::{Fake, Faker};
+    use std::collections::{BTreeMap, HashSet};
+
+    fn project() -> ProjectKey {
+        ProjectKey::new("TEST".to_string()).expect("Failed to create a project key")
+    }
+
+    /// A store with a single project, the one returned by [project].
+    fn test_store() -> TicketStore {
+        let mut ticket_store = TicketStore::new();
+        ticket_store.create_project(Project {
+            key: project(),
+            name: "Test project".to_string(),
+            lead: None,
+        });
+        ticket_store
+    }
+
+    /// An id no ticket of the store is associated to.
+    fn missing_id() -> TicketId {
+        TicketId::new(project(), 999)
+    }
+
+    #[test]
+    fn create_ticket_test() {
+        //arrange
+        let draft = TicketDraft {
+            project: project(),
+            title: Title::new(Faker.fake()).expect("Title should exist"),
+            description: Faker.fake(),
+            priority: Priority::Medium,
+            kind: TicketKind::Task,
+            parent: None,
+            reporter: None,
+            assignee: None,
+            custom_fields: BTreeMap::new(),
+        };
+
+        let mut ticket_store = test_store();
+
+        //act
+        let ticket_id = ticket_store
+            .create(draft.clone())
+            .expect("Failed to create ticket");
+
+        //assert
+        let ticket = ticket_store
+            .get(&ticket_id)
+            .expect("Failed to retrieve ticket.");
+        assert_eq!(ticket.title, draft.title);
+        assert_eq!(ticket.description, draft.description);
+        assert_eq!(ticket.status, Status::to_do());
+    }
+
+    #[test]
+    fn delete_ticket_test() {
+        //arrange
+        let draft = TicketDraft {
+            project: project(),
+            title: Title::new(Faker.fake()).expect("Title should exist"),
+            description: Faker.fake(),
+            priority: Priority::Medium,
+            kind: TicketKind::Task,
+            parent: None,
+            reporter: None,
+            assignee: None,
+            custom_fields: BTreeMap::new(),
+        };
+
+        let mut ticket_store = test_store();
+        let ticket_id = ticket_store
+            .create(draft.clone())
+            .expect("Failed to create ticket");
+        let inserted_ticket = ticket_store
+            .get(&ticket_id)
+            .expect("Failed to retrieve ticket")
+            .to_owned();
+
+        //act
+        let deleted_ticket = ticket_store
+            .delete(&ticket_id)
+            .expect("There was no ticket to delete.");
+
+        //assert
+        assert_eq!(deleted_ticket.ticket, inserted_ticket);
+        let ticket = ticket_store.get(&ticket_id);
+        assert_eq!(ticket, None);
+    }
+
+    #[test]
+    fn deleting_a_ticket_that_does_not_exist_returns_none() {
+        //arrange
+        let mut ticket_store = test_store();
+
+        //act
+        let deleted_ticket = ticket_store.delete(&missing_id());
+
+        //assert
+        assert_eq!(deleted_ticket, None);
+    }
+
+    #[test]
+    fn listing_tickets_of_an_empty_store_returns_an_empty_collection() {
+        // Arrange
+        let ticket_store = test_store();
+
+        // Act
+        let tickets = ticket_store.list();
+
+        // Assert
+        assert!(tickets.is_empty())
+    }
+
+    #[test]
+    fn listing_tickets_should_return_them_all() {
+        // Arrange
+        let mut ticket_store = test_store();
+        let n_tickets = Faker.fake::<u16>() as usize;
+        let tickets: HashSet<_> = (0..n_tickets)
+            .map(|_| generate_and_persist_ticket(&mut ticket_store))
+            .collect();
+
+        // Act
+        let retrieved_tickets = ticket_store.list();
+
+        // Assert
+        assert_eq!(retrieved_tickets.len(), n_tickets);
+        let retrieved_tickets: HashSet<_> = retrieved_tickets
+            .into_iter()
+            .map(|t| t.to_owned())
+            .collect();
+        assert_eq!(tickets, retrieved_tickets);
+    }
+
+    fn generate_and_persist_ticket(store: &mut TicketStore) -> Ticket {
+        // arrange
+        let draft = TicketDraft {
+            project: project(),
+            title: Title::new(Faker.fake()).expect("Failed to get a title"),
+            description: Faker.fake(),
+            priority: Priority::Medium,
+            kind: TicketKind::Task,
+            parent: None,
+            reporter: None,
+            assignee: None,
+            custom_fields: BTreeMap::new(),
+        };
+        let ticket_id = store.create(draft).expect("Failed to create ticket");
+        store
+            .get(&ticket_id)
+            .expect("Failed to retrieve ticket")
+            .to_owned()
+    }
+
+    #[test]
+    fn updating_ticket_info_via_patch_should_update_ticket() {
+        // arrange
+        let mut ticket_store = test_store();
+
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+
+        let patch = TicketPatch {
+            title: Some(Title::new(Faker.fake()).expect("Failed to get a title")),
+            description: Some(Faker.fake()),
+            priority: Some(Priority::High),
+            reporter: None,
+            assignee: None,
+            estimate: None,
+            remaining: None,
+            custom_fields: BTreeMap::new(),
+        };
+
+        let expected = patch.clone();
+
+        //act
+        ticket_store.update_ticket(&ticket.id, patch);
+
+        //assert
+        let updated_ticket = ticket_store
+            .get(&ticket.id)
+            .expect("Failed to retrieve ticket.");
+
+        assert_eq!(
+            updated_ticket.title,
+            expected.title.expect("Failed to get a title")
+        );
+
+        assert_eq!(
+            updated_ticket.description,
+            expected.description.expect("Failed to get a Description")
+        );
+
+        assert_eq!(updated_ticket.priority, Priority::High);
+    }
+
+    #[test]
+    fn updating_ticket_with_no_patch_values_should_not_fail_or_change_values() {
+        //arrange
+        let draft = TicketDraft {
+            project: project(),
+            title: Title::new(Faker.fake()).expect("Failed to get a title"),
+            description: Faker.fake(),
+            priority: Priority::Medium,
+            kind: TicketKind::Task,
+            parent: None,
+            reporter: None,
+            assignee: None,
+            custom_fields: BTreeMap::new(),
+        };
+
+        let mut ticket_store = test_store();
+
+        let ticket_id = ticket_store
+            .create(draft.clone())
+            .expect("Failed to create ticket");
+
+        let patch = TicketPatch {
+            title: None,
+            description: None,
+            priority: None,
+            reporter: None,
+            assignee: None,
+            estimate: None,
+            remaining: None,
+            custom_fields: BTreeMap::new(),
+        };
+
+        //act
+        ticket_store.update_ticket(&ticket_id, patch);
+
+        //assert
+        let updated_ticket = ticket_store
+            .get(&ticket_id)
+            .expect("Failed to retrieve ticket.");
+
+        assert_eq!(updated_ticket.title, draft.title);
+
+        assert_eq!(updated_ticket.description, draft.description);
+    }
+
+    #[test]
+    fn updating_ticket_status_should_change_ticket_to_new_status() {
+        //arrange
+        let mut ticket_store = test_store();
+
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+
+        //act
+        ticket_store
+            .update_ticket_status(&ticket.id, Status::done(), None)
+            .expect("Failed to update status");
+
+        //assert
+        let updated_ticket = ticket_store
+            .get(&ticket.id)
+            .expect("Failed to retrieve ticket.");
+
+        assert_eq!(updated_ticket.status, Status::done())
+    }
+
+    #[test]
+    fn add_comment_to_ticket() {
+        //arrange
+        let mut ticket_store = test_store();
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+        let comment = Comment::new("Test Comment".to_string()).unwrap();
+        let expected = comment.clone();
+
+        //act
+        let result = ticket_store.add_comment_to_ticket(&ticket.id, comment, None);
+        //assert
+        assert_eq!(result, Ok(1));
+        let ticket = ticket_store.get(&ticket.id).unwrap();
+        let bodies: Vec<&Comment> = ticket.comments.iter().map(|c| &c.body).collect();
+        assert_eq!(bodies, vec![&expected]);
+    }
+
+    #[test]
+    fn comments_record_their_author() {
+        //arrange
+        let mut ticket_store = test_store();
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+        ticket_store.set_actor(Some(user("alice")));
+
+        //act
+        ticket_store
+            .add_comment_to_ticket(&ticket.id, Comment::new(Faker.fake()).unwrap(), None)
+            .unwrap();
+
+        //assert
+        let comment = &ticket_store.get(&ticket.id).unwrap().comments[0];
+        assert_eq!(comment.author, Some(user("alice")));
+        assert_eq!(comment.edited_at, None);
+    }
+
+    #[test]
+    fn edited_comments_keep_their_previous_versions() {
+        //arrange
+        let mut ticket_store = test_store();
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+        let typo = Comment::new("Fixed in 1.2.O".to_string()).unwrap();
+        let fixed = Comment::new("Fixed in 1.2.0".to_string()).unwrap();
+        let comment_id = ticket_store
+            .add_comment_to_ticket(&ticket.id, typo.clone(), None)
+            .unwrap();
+
+        //act
+        let result = ticket_store.edit_comment(&ticket.id, comment_id, fixed.clone());
+
+        //assert
+        assert_eq!(result, Some(()));
+        let comment = &ticket_store.get(&ticket.id).unwrap().comments[0];
+        assert_eq!(comment.body, fixed);
+        assert!(comment.edited_at.is_some());
+        assert_eq!(comment.revisions.len(), 1);
+        assert_eq!(comment.revisions[0].body, typo);
+    }
+
+    #[test]
+    fn editing_a_missing_comment_returns_none() {
+        //arrange
+        let mut ticket_store = test_store();
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+
+        //act
+        let result = ticket_store.edit_comment(&ticket.id, 1, Comment::new(Faker.fake()).unwrap());
+
+        //assert
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn ids_of_deleted_comments_are_not_reused() {
+        //arrange
+        let mut ticket_store = test_store();
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+        let first = ticket_store
+            .add_comment_to_ticket(&ticket.id, Comment::new(Faker.fake()).unwrap(), None)
+            .unwrap();
+
+        //act
+        let deleted = ticket_store.delete_comment(&ticket.id, first);
+        let second = ticket_store
+            .add_comment_to_ticket(&ticket.id, Comment::new(Faker.fake()).unwrap(), None)
+            .unwrap();
+
+        //assert
+        assert_eq!(deleted.map(|c| c.id), Some(first));
+        assert_ne!(first, second);
+        assert_eq!(ticket_store.get(&ticket.id).unwrap().comments.len(), 1);
+    }
+
+    #[test]
+    fn comments_saved_without_id_are_numbered() {
+        //arrange
+        let mut ticket_store = test_store();
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+        ticket_store
+            .add_comment_to_ticket(&ticket.id, Comment::new(Faker.fake()).unwrap(), None)
+            .unwrap();
+        ticket_store
+            .add_comment_to_ticket(&ticket.id, Comment::new(Faker.fake()).unwrap(), None)
+            .unwrap();
+        let ticket = ticket_store.data.get_mut(&ticket.id).unwrap();
+        ticket.comment_sequence = 0;
+        for comment in ticket.comments.iter_mut() {
+            comment.id = 0;
+        }
+        let id = ticket.id.clone();
+
+        //act
+        ticket_store.upgrade();
+
+        //assert
+        let ids: Vec<CommentId> = ticket_store
+            .get(&id)
+            .unwrap()
+            .comments
+            .iter()
+            .map(|c| c.id)
+            .collect();
+        assert_eq!(ids, vec![1, 2]);
+    }
+
+    #[test]
+    fn add_comment_to_invalid_ticket_id_fails() {
+        //arrange
+        let mut ticket_store = test_store();
+        let comment = Comment::new("Test comment".to_string()).unwrap();
+
+        //act
+        let result = ticket_store.add_comment_to_ticket(&missing_id(), comment, None);
+
+        //assert
+        assert_eq!(result, Err(ReplyError::TicketNotFound(missing_id())));
+    }
+
+    #[test]
+    fn replies_must_be_to_a_comment_of_the_ticket() {
+        //arrange
+        let mut ticket_store = test_store();
+        let first = generate_and_persist_ticket(&mut ticket_store);
+        let second = generate_and_persist_ticket(&mut ticket_store);
+        let other_comment = ticket_store
+            .add_comment_to_ticket(&first.id, Comment::new(Faker.fake()).unwrap(), None)
+            .unwrap();
+
+        //act
+        let result = ticket_store.add_comment_to_ticket(
+            &second.id,
+            Comment::new(Faker.fake()).unwrap(),
+            Some(other_comment),
+        );
+
+        //assert
+        assert_eq!(
+            result,
+            Err(ReplyError::CommentNotFound {
+                ticket: second.id,
+                comment: other_comment
+            })
+        );
+    }
+
+    #[test]
+    fn replies_are_displayed_below_the_comment_they_reply_to() {
+        //arrange
+        let mut ticket_store = test_store();
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+        let comment = |text: &str| Comment::new(text.to_string()).unwrap();
+        let question = ticket_store
+            .add_comment_to_ticket(&ticket.id, comment("Which version?"), None)
+            .unwrap();
+        ticket_store
+            .add_comment_to_ticket(&ticket.id, comment("Unrelated"), None)
+            .unwrap();
+        ticket_store
+            .add_comment_to_ticket(&ticket.id, comment("1.2.0"), Some(question))
+            .unwrap();
+
+        //act
+        let displayed = ticket_store.get(&ticket.id).unwrap().to_string();
+
+        //assert
+        let comments: Vec<&str> = displayed
+            .lines()
+            .skip_while(|l| *l != "\tComments:")
+            .skip(1)
+            .collect();
+        assert!(comments[0].starts_with("\t- [1]") && comments[0].ends_with("Which version?"));
+        assert!(comments[1].starts_with("\t  - [3]") && comments[1].ends_with("1.2.0"));
+        assert!(comments[2].starts_with("\t- [2]") && comments[2].ends_with("Unrelated"));
+    }
+
+    #[test]
+    fn replies_to_a_deleted_comment_move_up_one_level() {
+        //arrange
+        let mut ticket_store = test_store();
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+        let mut reply_to = None;
+        for _ in 0..3 {
+            reply_to = Some(
+                ticket_store
+                    .add_comment_to_ticket(
+                        &ticket.id,
+                        Comment::new(Faker.fake()).unwrap(),
+                        reply_to,
+                    )
+                    .unwrap(),
+            );
+        }
+
+        //act
+        ticket_store.delete_comment(&ticket.id, 2);
+
+        //assert
+        let ticket = ticket_store.get(&ticket.id).unwrap();
+        assert_eq!(ticket.comments[1].id, 3);
+        assert_eq!(ticket.comments[1].reply_to, Some(1));
+    }
+
+    fn user(handle: &str) -> UserId {
+        UserId::new(handle.to_string()).expect("Failed to create a user handle")
+    }
+
+    #[test]
+    fn registered_users_can_be_retrieved_by_handle() {
+        //arrange
+        let mut ticket_store = test_store();
+        let alice = User {
+            id: user("alice"),
+            display_name: "Alice Liddell".to_string(),
+        };
+
+        //act
+        ticket_store.add_user(alice.clone());
+
+        //assert
+        assert_eq!(ticket_store.get_user(&alice.id), Some(&alice));
+        assert_eq!(ticket_store.list_users(), vec![&alice]);
+    }
+
+    #[test]
+    fn assigning_a_ticket_sets_its_assignee() {
+        //arrange
+        let mut ticket_store = test_store();
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+
+        //act
+        let result = ticket_store.assign(&ticket.id, user("alice"));
+
+        //assert
+        assert!(result.is_some());
+        let ticket = ticket_store.get(&ticket.id).unwrap();
+        assert_eq!(ticket.assignee, Some(user("alice")));
+    }
+
+    #[test]
+    fn unassigning_a_ticket_clears_its_assignee() {
+        //arrange
+        let mut ticket_store = test_store();
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+        ticket_store.assign(&ticket.id, user("alice"));
+
+        //act
+        let result = ticket_store.unassign(&ticket.id);
+
+        //assert
+        assert!(result.is_some());
+        let ticket = ticket_store.get(&ticket.id).unwrap();
+        assert_eq!(ticket.assignee, None);
+    }
+
+    #[test]
+    fn assigning_an_invalid_ticket_id_returns_none() {
+        //arrange
+        let mut ticket_store = test_store();
+
+        //act
+        let result = ticket_store.assign(&missing_id(), user("alice"));
+
+        //assert
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn listing_by_assignee_only_returns_their_tickets() {
+        //arrange
+        let mut ticket_store = test_store();
+        let alice_ticket = generate_and_persist_ticket(&mut ticket_store);
+        let bob_ticket = generate_and_persist_ticket(&mut ticket_store);
+        generate_and_persist_ticket(&mut ticket_store);
+        ticket_store.assign(&alice_ticket.id, user("alice"));
+        ticket_store.assign(&bob_ticket.id, user("bob"));
+
+        //act
+        let tickets = ticket_store.list_by_assignee(&user("alice"));
+
+        //assert
+        assert_eq!(tickets.len(), 1);
+        assert_eq!(tickets[0].id, alice_ticket.id);
+    }
+
+    fn label(label: &str) -> Label {
+        Label::new(label.to_string()).expect("Failed to create a label")
+    }
+
+    #[test]
+    fn adding_a_label_tags_the_ticket() {
+        //arrange
+        let mut ticket_store = test_store();
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+
+        //act
+        let first = ticket_store.add_label(&ticket.id, label("backend"));
+        let second = ticket_store.add_label(&ticket.id, label("backend"));
+
+        //assert
+        assert_eq!(first, Some(true));
+        assert_eq!(second, Some(false));
+        let ticket = ticket_store.get(&ticket.id).unwrap();
+        assert!(ticket.labels.contains(&label("backend")));
+        assert_eq!(ticket.labels.len(), 1);
+    }
+
+    #[test]
+    fn removing_a_label_untags_the_ticket() {
+        //arrange
+        let mut ticket_store = test_store();
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+        ticket_store.add_label(&ticket.id, label("backend"));
+
+        //act
+        let result = ticket_store.remove_label(&ticket.id, &label("backend"));
+
+        //assert
+        assert_eq!(result, Some(true));
+        let ticket = ticket_store.get(&ticket.id).unwrap();
+        assert!(ticket.labels.is_empty());
+    }
+
+    #[test]
+    fn labelling_an_invalid_ticket_id_returns_none() {
+        //arrange
+        let mut ticket_store = test_store();
+
+        //act
+        let result = ticket_store.add_label(&missing_id(), label("backend"));
+
+        //assert
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn tickets_with_label_only_returns_tagged_tickets() {
+        //arrange
+        let mut ticket_store = test_store();
+        let backend = generate_and_persist_ticket(&mut ticket_store);
+        let docs = generate_and_persist_ticket(&mut ticket_store);
+        ticket_store.add_label(&backend.id, label("backend"));
+        ticket_store.add_label(&docs.id, label("docs"));
+
+        //act
+        let tickets = ticket_store.tickets_with_label(&label("backend"));
+
+        //assert
+        assert_eq!(tickets.len(), 1);
+        assert_eq!(tickets[0].id, backend.id);
+        assert_eq!(ticket_store.labels().len(), 2);
+    }
+
+    #[test]
+    fn listing_tickets_returns_the_most_urgent_first() {
+        // Arrange
+        let mut ticket_store = test_store();
+        let priorities = vec![
+            Priority::Low,
+            Priority::Highest,
+            Priority::Medium,
+            Priority::Highest,
+            Priority::Lowest,
+        ];
+        for priority in priorities {
+            let draft = TicketDraft {
+                project: project(),
+                title: Title::new(Faker.fake()).expect("Failed to get a title"),
+                description: Faker.fake(),
+                priority,
+                kind: TicketKind::Task,
+                parent: None,
+                reporter: None,
+                assignee: None,
+                custom_fields: BTreeMap::new(),
+            };
+            ticket_store.create(draft).expect("Failed to create ticket");
+        }
+
+        // Act
+        let tickets = ticket_store.list();
+
+        // Assert
+        let ordering: Vec<_> = tickets.iter().map(|t| (t.priority, t.id.number)).collect();
+        assert_eq!(
+            ordering,
+            vec![
+                (Priority::Highest, 2),
+                (Priority::Highest, 4),
+                (Priority::Medium, 3),
+                (Priority::Low, 1),
+                (Priority::Lowest, 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn searching_returns_the_tickets_matching_the_query() {
+        //arrange
+        let mut ticket_store = test_store();
+        let me = user("me");
+        let mine = generate_and_persist_ticket(&mut ticket_store);
+        let someone_elses = generate_and_persist_ticket(&mut ticket_store);
+        let unassigned = generate_and_persist_ticket(&mut ticket_store);
+        for ticket in [&mine, &someone_elses, &unassigned] {
+            ticket_store.add_label(&ticket.id, label("backend"));
+            ticket_store
+                .update_ticket_status(&ticket.id, Status::in_progress(), None)
+                .unwrap();
+        }
+        ticket_store.assign(&mine.id, me.clone());
+        ticket_store.assign(&someone_elses.id, user("someone"));
+        let query =
+            query::parse("status = in-progress AND assignee = me AND label in (Backend)").unwrap();
+
+        //act
+        let found = ticket_store.search(&query, Some(&me));
+        let found_without_current_user = ticket_store.search(&query, None);
+
+        //assert
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, mine.id);
+        assert!(found_without_current_user.is_empty());
+    }
+
+    #[test]
+    fn tickets_can_be_sorted_by_several_keys() {
+        //arrange
+        let mut ticket_store = test_store();
+        let todo_low = create_ticket_about(&mut ticket_store, "b", "");
+        let in_progress = create_ticket_about(&mut ticket_store, "c", "");
+        let todo_high = create_ticket_about(&mut ticket_store, "a", "");
+        ticket_store
+            .update_ticket_status(&in_progress, Status::in_progress(), None)
+            .unwrap();
+        for (id, priority) in [(&todo_low, Priority::Low), (&todo_high, Priority::High)] {
+            let patch = TicketPatch {
+                title: None,
+                description: None,
+                priority: Some(priority),
+                reporter: None,
+                assignee: None,
+                estimate: None,
+                remaining: None,
+                custom_fields: BTreeMap::new(),
+            };
+            ticket_store.update_ticket(id, patch);
+        }
+        let keys = vec![
+            OrderBy {
+                field: Field::Status,
+                descending: true,
+            },
+            OrderBy {
+                field: Field::Priority,
+                descending: true,
+            },
+        ];
+
+        //act
+        let sorted = TicketStore::sort_by(ticket_store.list(), &keys);
+
+        //assert
+        let ids: Vec<_> = sorted.iter().map(|t| t.id.clone()).collect();
+        assert_eq!(ids, vec![todo_high, todo_low, in_progress]);
+    }
+
+    #[test]
+    fn searching_sorts_tickets_as_the_query_says() {
+        //arrange
+        let mut ticket_store = test_store();
+        let priorities = vec![Priority::Low, Priority::Highest, Priority::Medium];
+        for priority in priorities {
+            let draft = TicketDraft {
+                project: project(),
+                title: Title::new(Faker.fake()).expect("Failed to get a title"),
+                description: Faker.fake(),
+                priority,
+                kind: TicketKind::Task,
+                parent: None,
+                reporter: None,
+                assignee: None,
+                custom_fields: BTreeMap::new(),
+            };
+            ticket_store.create(draft).expect("Failed to create ticket");
+        }
+        let query = query::parse("priority >= medium ORDER BY priority ASC").unwrap();
+
+        //act
+        let found = ticket_store.search(&query, None);
+
+        //assert
+        let ordering: Vec<_> = found.iter().map(|t| t.priority).collect();
+        assert_eq!(ordering, vec![Priority::Medium, Priority::Highest]);
+    }
+
+    fn create_ticket_about(store: &mut TicketStore, title: &str, description: &str) -> TicketId {
+        let draft = TicketDraft {
+            project: project(),
+            title: Title::new(title.to_string()).unwrap(),
+            description: description.to_string(),
+            priority: Priority::Medium,
+            kind: TicketKind::Task,
+            parent: None,
+            reporter: None,
+            assignee: None,
+            custom_fields: BTreeMap::new(),
+        };
+        store.create(draft).expect("Failed to create ticket")
+    }
+
+    #[test]
+    fn full_text_search_ranks_the_most_relevant_tickets_first() {
+        //arrange
+        let mut ticket_store = test_store();
+        let passing_mention = create_ticket_about(
+            &mut ticket_store,
+            "Upgrade the billing service",
+            "Check the logs for errors, e.g. a timeout, after the upgrade of the database driver",
+        );
+        let about_timeouts = create_ticket_about(
+            &mut ticket_store,
+            "Connection timeout",
+            "Requests time out when the pool is exhausted",
+        );
+        create_ticket_about(&mut ticket_store, "Dark mode", "Add a dark theme");
+
+        //act
+        let hits = ticket_store.search_text("connection timeout");
+
+        //assert
+        let found: Vec<_> = hits.iter().map(|hit| hit.ticket.id.clone()).collect();
+        assert_eq!(found, vec![about_timeouts, passing_mention]);
+        assert_eq!(
+            hits[0].snippets,
+            vec![(
+                "title".to_string(),
+                "**Connection** **timeout**".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn full_text_search_follows_the_changes_to_tickets() {
+        //arrange
+        let mut ticket_store = test_store();
+        let ticket = create_ticket_about(&mut ticket_store, "Slow dashboard", "It takes ages");
+        let deleted = create_ticket_about(&mut ticket_store, "Flaky deadlock", "Seen on CI");
+
+        //act
+        let comment_id = ticket_store
+            .add_comment_to_ticket(
+                &ticket,
+                Comment::new("A deadlock?".to_string()).unwrap(),
+                None,
+            )
+            .unwrap();
+        ticket_store.delete(&deleted);
+        let commented: Vec<_> = ticket_store
+            .search_text("deadlock")
+            .into_iter()
+            .map(|hit| (hit.ticket.id.clone(), hit.snippets[0].0.clone()))
+            .collect();
+        ticket_store.edit_comment(&ticket, comment_id, Comment::new("No".to_string()).unwrap());
+        let edited = ticket_store.search_text("deadlock");
+
+        //assert
+        assert_eq!(commented, vec![(ticket, format!("comment {}", comment_id))]);
+        assert!(edited.is_empty());
+    }
+
+    #[test]
+    fn a_saved_index_is_kept_up_to_date_by_replayed_events() {
+        //arrange
+        let mut saved = test_store();
+        let ticket = create_ticket_about(&mut saved, "Slow dashboard", "It takes ages");
+        let snapshot = serde_yaml::to_string(&saved).unwrap();
+        let index = serde_json::to_string(saved.index()).unwrap();
+        let created = create_ticket_about(&mut saved, "Slow login", "Seen on CI");
+        saved.delete(&ticket);
+        let events = saved.take_events();
+        let mut ticket_store: TicketStore = serde_yaml::from_str(&snapshot).unwrap();
+        ticket_store.set_index(serde_json::from_str(&index).unwrap());
+
+        //act
+        for event in events {
+            ticket_store.replay(event).unwrap();
+        }
+
+        //assert
+        let found: Vec<_> = ticket_store
+            .search_text("slow")
+            .into_iter()
+            .map(|hit| hit.ticket.id.clone())
+            .collect();
+        assert_eq!(found, vec![created]);
+        assert!(!ticket_store.index_rebuilt());
+    }
+
+    #[test]
+    fn saving_a_filter_replaces_the_one_with_the_same_name() {
+        //arrange
+        let mut ticket_store = test_store();
+        let name = FilterName::new("mine-open".to_string()).unwrap();
+        let filter = |query: &str| SavedFilter {
+            name: name.clone(),
+            description: None,
+            owner: Some(user("alice")),
+            query: query.to_string(),
+            saved_at: Utc::now(),
+        };
+
+        //act
+        let first = ticket_store.save_filter(filter("assignee = me"));
+        let replaced = ticket_store.save_filter(filter("assignee = me AND status = InProgress"));
+
+        //assert
+        assert_eq!(first, None);
+        assert_eq!(replaced.unwrap().query, "assignee = me");
+        assert_eq!(ticket_store.list_filters().len(), 1);
+        assert_eq!(
+            ticket_store.get_filter(&name).unwrap().query,
+            "assignee = me AND status = InProgress"
+        );
+    }
+
+    #[test]
+    fn listed_tickets_are_selected_once_in_the_given_order() {
+        //arrange
+        let mut ticket_store = test_store();
+        let first = create_ticket_about(&mut ticket_store, "First", "");
+        let second = create_ticket_about(&mut ticket_store, "Second", "");
+        let selection = TicketSelection::Ids(vec![second.clone(), first.clone(), second.clone()]);
+
+        //act
+        let selected = ticket_store.select(&selection, None).unwrap();
+
+        //assert
+        assert_eq!(selected, vec![second, first]);
+    }
+
+    #[test]
+    fn a_filter_selects_the_tickets_matching_its_query() {
+        //arrange
+        let mut ticket_store = test_store();
+        let login = create_ticket_about(&mut ticket_store, "Fix the login page", "");
+        create_ticket_about(&mut ticket_store, "Upgrade the database", "");
+        let name = FilterName::new("login".to_string()).unwrap();
+        ticket_store.save_filter(SavedFilter {
+            name: name.clone(),
+            description: None,
+            owner: None,
+            query: "title ~ login".to_string(),
+            saved_at: Utc::now(),
+        });
+
+        //act
+        let by_filter = ticket_store.select(&TicketSelection::Filter(name), None);
+        let by_query =
+            ticket_store.select(&TicketSelection::Query("title ~ login".to_string()), None);
+
+        //assert
+        assert_eq!(by_filter.unwrap(), vec![login.clone()]);
+        assert_eq!(by_query.unwrap(), vec![login]);
+    }
+
+    #[test]
+    fn unknown_filters_and_invalid_queries_select_nothing() {
+        //arrange
+        let ticket_store = test_store();
+        let name = FilterName::new("missing".to_string()).unwrap();
+
+        //act
+        let unknown = ticket_store.select(&TicketSelection::Filter(name.clone()), None);
+        let invalid = ticket_store.select(&TicketSelection::Query("title ~".to_string()), None);
+
+        //assert
+        assert_eq!(unknown, Err(SelectionError::FilterNotFound(name)));
+        assert!(matches!(invalid, Err(SelectionError::InvalidQuery(_))));
+    }
+
+    fn custom_field(name: &str, kind: CustomFieldKind, required: bool) -> CustomField {
+        CustomField {
+            name: CustomFieldName::new(name.to_string()).unwrap(),
+            kind,
+            required,
+            allowed_values: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn new_tickets_must_have_the_required_custom_fields() {
+        //arrange
+        let mut ticket_store = test_store();
+        let customer = custom_field("customer", CustomFieldKind::Text, true);
+        ticket_store.define_custom_field(customer.clone()).unwrap();
+        let draft = |custom_fields| TicketDraft {
+            project: project(),
+            title: Title::new(Faker.fake()).unwrap(),
+            description: Faker.fake(),
+            priority: Priority::Medium,
+            kind: TicketKind::Task,
+            parent: None,
+            reporter: None,
+            assignee: None,
+            custom_fields,
+        };
+        let values = ticket_store
+            .custom_field_values(vec![(customer.name.clone(), "ACME".to_string())])
+            .unwrap();
+
+        //act
+        let without_customer = ticket_store.create(draft(BTreeMap::new()));
+        let with_customer = ticket_store.create(draft(
+            values.into_iter().map(|(k, v)| (k, v.unwrap())).collect(),
+        ));
+
+        //assert
+        assert_eq!(
+            without_customer,
+            Err(CreateError::CustomField(CustomFieldError::MissingValue(
+                customer.name.clone()
+            )))
+        );
+        let ticket = ticket_store.get(&with_customer.unwrap()).unwrap();
+        assert_eq!(ticket.custom_fields[&customer.name], "ACME");
+        assert_eq!(
+            ticket_store.custom_field_values(vec![(customer.name, "".to_string())]),
+            Err(CustomFieldError::MissingValue(
+                CustomFieldName::new("customer".to_string()).unwrap()
+            ))
+        );
+    }
+
+    #[test]
+    fn enum_fields_need_distinct_non_empty_allowed_values() {
+        //arrange
+        let mut ticket_store = test_store();
+        let environment = |values: &[&str]| CustomField {
+            allowed_values: values.iter().map(|v| v.to_string()).collect(),
+            ..custom_field("environment", CustomFieldKind::Enum, false)
+        };
+        let invalid = Err(CustomFieldError::InvalidAllowedValues(
+            CustomFieldName::new("environment".to_string()).unwrap(),
+        ));
+
+        //act
+        let blank = ticket_store.define_custom_field(environment(&["staging", " "]));
+        let repeated = ticket_store.define_custom_field(environment(&["Prod", "staging", "prod"]));
+        let distinct = ticket_store.define_custom_field(environment(&["prod", "staging"]));
+
+        //assert
+        assert_eq!(blank, invalid);
+        assert_eq!(repeated, invalid);
+        assert_eq!(distinct, Ok(()));
+    }
+
+    #[test]
+    fn removing_an_unknown_custom_field_does_not_require_a_snapshot() {
+        //arrange
+        let ticket_store = test_store();
+        let mut ticket_store = reload(&ticket_store);
+        let name = CustomFieldName::new("customer".to_string()).unwrap();
+
+        //act
+        let removed = ticket_store.remove_custom_field(&name);
+
+        //assert
+        assert_eq!(removed, None);
+        assert!(!ticket_store.needs_snapshot());
+    }
+
+    #[test]
+    fn tickets_can_be_searched_and_sorted_by_custom_fields() {
+        //arrange
+        let mut ticket_store = test_store();
+        let points = custom_field("story-points", CustomFieldKind::Number, false);
+        ticket_store.define_custom_field(points.clone()).unwrap();
+        let mut ids = Vec::new();
+        for value in ["13", "2", "8", ""] {
+            let id = generate_and_persist_ticket(&mut ticket_store).id;
+            let custom_fields = ticket_store
+                .custom_field_values(vec![(points.name.clone(), value.to_string())])
+                .unwrap();
+            let patch = TicketPatch {
+                title: None,
+                description: None,
+                priority: None,
+                reporter: None,
+                assignee: None,
+                estimate: None,
+                remaining: None,
+                custom_fields,
+            };
+            ticket_store.update_ticket(&id, patch);
+            ids.push(id);
+        }
+        let query = ticket_store
+            .parse_query("story-points >= 3 ORDER BY story-points")
+            .unwrap();
+
+        //act
+        let found: Vec<_> = ticket_store
+            .search(&query, None)
+            .iter()
+            .map(|t| t.id.clone())
+            .collect();
+
+        //assert
+        assert_eq!(found, vec![ids[2].clone(), ids[0].clone()]);
+        assert!(ticket_store.parse_query("story-points = many").is_err());
+    }
+
+    #[test]
+    fn linking_tickets_adds_the_inverse_link() {
+        //arrange
+        let mut ticket_store = test_store();
+        let blocker = generate_and_persist_ticket(&mut ticket_store);
+        let blocked = generate_and_persist_ticket(&mut ticket_store);
+
+        //act
+        let result = ticket_store.link(&blocker.id, LinkKind::Blocks, &blocked.id);
+
+        //assert
+        assert_eq!(result, Ok(()));
+        let blocker = ticket_store.get(&blocker.id).unwrap();
+        assert!(blocker.links.contains(&TicketLink {
+            kind: LinkKind::Blocks,
+            target: blocked.id.clone()
+        }));
+        let blocked = ticket_store.get(&blocked.id).unwrap();
+        assert!(blocked.links.contains(&TicketLink {
+            kind: LinkKind::IsBlockedBy,
+            target: blocker.id.clone()
+        }));
+    }
+
+    #[test]
+    fn linking_a_ticket_to_itself_fails() {
+        //arrange
+        let mut ticket_store = test_store();
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+
+        //act
+        let result = ticket_store.link(&ticket.id, LinkKind::RelatesTo, &ticket.id);
+
+        //assert
+        assert_eq!(result, Err(LinkError::SelfLink(ticket.id)));
+    }
+
+    #[test]
+    fn linking_to_a_missing_ticket_fails() {
+        //arrange
+        let mut ticket_store = test_store();
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+
+        //act
+        let result = ticket_store.link(&ticket.id, LinkKind::Duplicates, &missing_id());
+
+        //assert
+        assert_eq!(result, Err(LinkError::TicketNotFound(missing_id())));
+    }
+
+    #[test]
+    fn unlinking_removes_both_directions() {
+        //arrange
+        let mut ticket_store = test_store();
+        let first = generate_and_persist_ticket(&mut ticket_store);
+        let second = generate_and_persist_ticket(&mut ticket_store);
+        ticket_store
+            .link(&first.id, LinkKind::Duplicates, &second.id)
+            .unwrap();
+
+        //act
+        let result = ticket_store.unlink(&second.id, LinkKind::IsDuplicatedBy, &first.id);
+
+        //assert
+        assert!(result.is_some());
+        assert!(ticket_store.get(&first.id).unwrap().links.is_empty());
+        assert!(ticket_store.get(&second.id).unwrap().links.is_empty());
+    }
+
+    #[test]
+    fn deleting_a_ticket_removes_links_pointing_to_it() {
+        //arrange
+        let mut ticket_store = test_store();
+        let first = generate_and_persist_ticket(&mut ticket_store);
+        let second = generate_and_persist_ticket(&mut ticket_store);
+        ticket_store
+            .link(&first.id, LinkKind::RelatesTo, &second.id)
+            .unwrap();
+
+        //act
+        ticket_store.delete(&second.id);
+
+        //assert
+        assert!(ticket_store.get(&first.id).unwrap().links.is_empty());
+    }
+
+    fn create_child(
+        store: &mut TicketStore,
+        kind: TicketKind,
+        parent: Option<&TicketId>,
+    ) -> TicketId {
+        let draft = TicketDraft {
+            project: project(),
+            title: Title::new(Faker.fake()).expect("Failed to get a title"),
+            description: Faker.fake(),
+            priority: Priority::Medium,
+            kind,
+            parent: parent.cloned(),
+            reporter: None,
+            assignee: None,
+            custom_fields: BTreeMap::new(),
+        };
+        store.create(draft).expect("Failed to create ticket")
+    }
+
+    #[test]
+    fn children_are_retrieved_through_their_parent() {
+        //arrange
+        let mut ticket_store = test_store();
+        let epic = create_child(&mut ticket_store, TicketKind::Epic, None);
+        let story = create_child(&mut ticket_store, TicketKind::Story, Some(&epic));
+        create_child(&mut ticket_store, TicketKind::SubTask, Some(&story));
+
+        //act
+        let children = ticket_store.children(&epic);
+
+        //assert
+        assert_eq!(children.len(), 1);
+        assert_eq!(children[0].id, story);
+    }
+
+    #[test]
+    fn sub_tasks_cannot_have_children() {
+        //arrange
+        let mut ticket_store = test_store();
+        let task = create_child(&mut ticket_store, TicketKind::Task, None);
+        let sub_task = create_child(&mut ticket_store, TicketKind::SubTask, Some(&task));
+        let other = create_child(&mut ticket_store, TicketKind::Task, None);
+
+        //act
+        let result = ticket_store.set_parent(&other, Some(sub_task.clone()));
+
+        //assert
+        assert_eq!(
+            result,
+            Err(HierarchyError::SubTaskCannotHaveChildren(sub_task))
+        );
+    }
+
+    #[test]
+    fn epics_cannot_have_a_parent() {
+        //arrange
+        let mut ticket_store = test_store();
+        let epic = create_child(&mut ticket_store, TicketKind::Epic, None);
+        let draft = TicketDraft {
+            project: project(),
+            title: Title::new(Faker.fake()).expect("Failed to get a title"),
+            description: Faker.fake(),
+            priority: Priority::Medium,
+            kind: TicketKind::Epic,
+            parent: Some(epic.clone()),
+            reporter: None,
+            assignee: None,
+            custom_fields: BTreeMap::new(),
+        };
+
+        //act
+        let result = ticket_store.create(draft);
+
+        //assert
+        assert_eq!(
+            result,
+            Err(CreateError::Hierarchy(HierarchyError::EpicCannotHaveParent))
+        );
+    }
+
+    #[test]
+    fn hierarchy_cycles_are_rejected() {
+        //arrange
+        let mut ticket_store = test_store();
+        let story = create_child(&mut ticket_store, TicketKind::Story, None);
+        let task = create_child(&mut ticket_store, TicketKind::Task, Some(&story));
+
+        //act
+        let result = ticket_store.set_parent(&story, Some(task.clone()));
+
+        //assert
+        assert_eq!(
+            result,
+            Err(HierarchyError::Cycle {
+                ticket: story,
+                parent: task
+            })
+        );
+    }
+
+    #[test]
+    fn progress_counts_done_descendants() {
+        //arrange
+        let mut ticket_store = test_store();
+        let epic = create_child(&mut ticket_store, TicketKind::Epic, None);
+        let story = create_child(&mut ticket_store, TicketKind::Story, Some(&epic));
+        let sub_task = create_child(&mut ticket_store, TicketKind::SubTask, Some(&story));
+        create_child(&mut ticket_store, TicketKind::Task, Some(&epic));
+        ticket_store
+            .update_ticket_status(&sub_task, Status::done(), None)
+            .expect("Failed to update status");
+
+        //act
+        let progress = ticket_store.progress(&epic);
+
+        //assert
+        assert_eq!(progress, Some((1, 3)));
+    }
+
+    #[test]
+    fn deleting_a_parent_detaches_its_children() {
+        //arrange
+        let mut ticket_store = test_store();
+        let epic = create_child(&mut ticket_store, TicketKind::Epic, None);
+        let story = create_child(&mut ticket_store, TicketKind::Story, Some(&epic));
+
+        //act
+        ticket_store.delete(&epic);
+
+        //assert
+        assert_eq!(ticket_store.get(&story).unwrap().parent, None);
+    }
+
+    fn workflow(yaml: &str) -> Workflow {
+        serde_yaml::from_str(yaml).expect("Failed to parse workflow")
+    }
+
+    #[test]
+    fn transitions_not_allowed_by_the_workflow_are_rejected() {
+        //arrange
+        let mut ticket_store = test_store();
+        ticket_store.set_workflow(workflow("transitions:\n  ToDo: [InProgress]"));
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+
+        //act
+        let result = ticket_store.update_ticket_status(&ticket.id, Status::done(), None);
+
+        //assert
+        assert_eq!(
+            result,
+            Err(TransitionError::NotAllowed {
+                from: Status::to_do(),
+                to: Status::done(),
+                allowed: vec![Status::in_progress()]
+            })
+        );
+        assert_eq!(
+            ticket_store.get(&ticket.id).unwrap().status,
+            Status::to_do()
+        );
+    }
+
+    #[test]
+    fn required_comments_must_be_provided_and_are_saved() {
+        //arrange
+        let mut ticket_store = test_store();
+        ticket_store.set_workflow(workflow("required:\n  Done: [Comment]"));
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+        let resolution = Comment::new("Fixed in 1.2.0".to_string()).unwrap();
+
+        //act
+        let without_comment = ticket_store.update_ticket_status(&ticket.id, Status::done(), None);
+        let with_comment =
+            ticket_store.update_ticket_status(&ticket.id, Status::done(), Some(resolution.clone()));
+
+        //assert
+        assert_eq!(
+            without_comment,
+            Err(TransitionError::MissingField {
+                status: Status::done(),
+                field: RequiredField::Comment
+            })
+        );
+        assert_eq!(with_comment, Ok(()));
+        let ticket = ticket_store.get(&ticket.id).unwrap();
+        assert_eq!(ticket.status, Status::done());
+        assert_eq!(ticket.comments[0].body, resolution);
+    }
+
+    #[test]
+    fn updating_the_status_of_an_invalid_ticket_id_fails() {
+        //arrange
+        let mut ticket_store = test_store();
+        let id = missing_id();
+
+        //act
+        let result = ticket_store.update_ticket_status(&id, Status::done(), None);
+
+        //assert
+        assert_eq!(result, Err(TransitionError::TicketNotFound(id)));
+    }
+
+    #[test]
+    fn custom_statuses_can_be_used_once_configured() {
+        //arrange
+        let mut ticket_store = test_store();
+        ticket_store.set_workflow(workflow(
+            "statuses:\n  - name: Open\n    category: todo\n  - name: In Review\n    category: in-progress",
+        ));
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+        let in_review = ticket_store
+            .resolve_status("in review")
+            .expect("Failed to resolve status");
+
+        //act
+        let moved = ticket_store.update_ticket_status(&ticket.id, in_review.clone(), None);
+        let done = ticket_store.update_ticket_status(&ticket.id, Status::done(), None);
+
+        //assert
+        assert_eq!(ticket.status, Status::new("Open"));
+        assert_eq!(moved, Ok(()));
+        assert_eq!(done, Err(TransitionError::UnknownStatus(Status::done())));
+        assert_eq!(ticket_store.get(&ticket.id).unwrap().status, in_review);
+    }
+
+    #[test]
+    fn stores_saved_with_the_builtin_statuses_can_still_be_loaded() {
+        //arrange
+        let yaml = "---
+current_id: 1
+data:
+  1:
+    id: 1
+    title:
+      title: A ticket
+    description: Saved before custom statuses
+    status: InProgress
+    comments: []";
+
+        //act
+        let ticket_store: TicketStore = serde_yaml::from_str(yaml).expect("Failed to load store");
+
+        //assert
+        let ticket = ticket_store
+            .get(&TicketId::new(ProjectKey::legacy(), 1))
+            .expect("Failed to retrieve ticket");
+        assert_eq!(ticket.status, Status::in_progress());
+        assert_eq!(
+            ticket_store.status_category(&ticket.status),
+            Some(StatusCategory::InProgress)
+        );
+    }
+
+    #[test]
+    fn ticket_numbers_are_sequential_within_a_project() {
+        //arrange
+        let mut ticket_store = test_store();
+        let other = ProjectKey::new("OPS".to_string()).unwrap();
+        ticket_store.create_project(Project {
+            key: other.clone(),
+            name: "Operations".to_string(),
+            lead: None,
+        });
+
+        //act
+        let first = generate_and_persist_ticket(&mut ticket_store);
+        let draft = TicketDraft {
+            project: other.clone(),
+            title: Title::new(Faker.fake()).expect("Failed to get a title"),
+            description: Faker.fake(),
+            priority: Priority::Medium,
+            kind: TicketKind::Task,
+            parent: None,
+            reporter: None,
+            assignee: None,
+            custom_fields: BTreeMap::new(),
+        };
+        let ops_ticket = ticket_store.create(draft).expect("Failed to create ticket");
+        let second = generate_and_persist_ticket(&mut ticket_store);
+
+        //assert
+        assert_eq!(first.id, TicketId::new(project(), 1));
+        assert_eq!(ops_ticket, TicketId::new(other, 1));
+        assert_eq!(second.id, TicketId::new(project(), 2));
+    }
+
+    #[test]
+    fn creating_a_ticket_in_an_unknown_project_fails() {
+        //arrange
+        let mut ticket_store = TicketStore::new();
+        let draft = TicketDraft {
+            project: project(),
+            title: Title::new(Faker.fake()).expect("Failed to get a title"),
+            description: Faker.fake(),
+            priority: Priority::Medium,
+            kind: TicketKind::Task,
+            parent: None,
+            reporter: None,
+            assignee: None,
+            custom_fields: BTreeMap::new(),
+        };
+
+        //act
+        let result = ticket_store.create(draft);
+
+        //assert
+        assert_eq!(result, Err(CreateError::UnknownProject(project())));
+    }
+
+    #[test]
+    fn upgrading_a_legacy_store_moves_its_tickets_to_the_legacy_project() {
+        //arrange
+        let yaml = "---
+current_id: 3
+data:
+  3:
+    id: 3
+    title:
+      title: A ticket
+    description: Saved before projects
+    status: ToDo
+    comments: []";
+        let mut ticket_store: TicketStore =
+            serde_yaml::from_str(yaml).expect("Failed to load store");
+
+        //act
+        ticket_store.upgrade();
+
+        //assert
+        assert!(ticket_store.get_project(&ProjectKey::legacy()).is_some());
+        let ticket = generate_legacy_ticket(&mut ticket_store);
+        assert_eq!(ticket, TicketId::new(ProjectKey::legacy(), 4));
+    }
+
+    fn generate_legacy_ticket(store: &mut TicketStore) -> TicketId {
+        let draft = TicketDraft {
+            project: ProjectKey::legacy(),
+            title: Title::new(Faker.fake()).expect("Failed to get a title"),
+            description: Faker.fake(),
+            priority: Priority::Medium,
+            kind: TicketKind::Task,
+            parent: None,
+            reporter: None,
+            assignee: None,
+            custom_fields: BTreeMap::new(),
+        };
+        store.create(draft).expect("Failed to create ticket")
+    }
+
+    fn plan_sprint(store: &mut TicketStore) -> SprintId {
+        let draft = SprintDraft {
+            name: Faker.fake(),
+            goal: None,
+            start: NaiveDate::from_ymd(2020, 3, 2),
+            end: NaiveDate::from_ymd(2020, 3, 15),
+        };
+        store.create_sprint(draft).expect("Failed to create sprint")
+    }
+
+    #[test]
+    fn sprints_cannot_end_before_they_start() {
+        //arrange
+        let mut ticket_store = test_store();
+        let draft = SprintDraft {
+            name: Faker.fake(),
+            goal: None,
+            start: NaiveDate::from_ymd(2020, 3, 15),
+            end: NaiveDate::from_ymd(2020, 3, 2),
+        };
+
+        //act
+        let result = ticket_store.create_sprint(draft);
+
+        //assert
+        assert_eq!(result, Err(SprintError::EndBeforeStart));
+    }
+
+    #[test]
+    fn only_one_sprint_can_be_active_at_a_time() {
+        //arrange
+        let mut ticket_store = test_store();
+        let first = plan_sprint(&mut ticket_store);
+        let second = plan_sprint(&mut ticket_store);
+
+        //act
+        let started = ticket_store.start_sprint(first);
+        let result = ticket_store.start_sprint(second);
+
+        //assert
+        assert_eq!(started, Ok(()));
+        assert_eq!(result, Err(SprintError::AlreadyActive(first)));
+        assert_eq!(
+            ticket_store.get_sprint(first).unwrap().state,
+            SprintState::Active
+        );
+    }
+
+    #[test]
+    fn completing_a_sprint_carries_unfinished_tickets_over() {
+        //arrange
+        let mut ticket_store = test_store();
+        let sprint = plan_sprint(&mut ticket_store);
+        let next = plan_sprint(&mut ticket_store);
+        let done = generate_and_persist_ticket(&mut ticket_store);
+        let unfinished = generate_and_persist_ticket(&mut ticket_store);
+        for ticket in [&done, &unfinished].iter() {
+            ticket_store.add_to_sprint(&ticket.id, sprint).unwrap();
+        }
+        ticket_store.start_sprint(sprint).unwrap();
+        ticket_store
+            .update_ticket_status(&done.id, Status::done(), None)
+            .unwrap();
+
+        //act
+        let result = ticket_store.complete_sprint(sprint, CarryOver::Sprint(next));
+
+        //assert
+        assert_eq!(result, Ok(vec![unfinished.id.clone()]));
+        assert_eq!(ticket_store.get(&done.id).unwrap().sprint, Some(sprint));
+        assert_eq!(ticket_store.get(&unfinished.id).unwrap().sprint, Some(next));
+        assert_eq!(
+            ticket_store.get_sprint(sprint).unwrap().state,
+            SprintState::Completed
+        );
+        assert_eq!(
+            ticket_store.sprint_summary(sprint),
+            Some(SprintSummary {
+                to_do: 0,
+                in_progress: 0,
+                done: 1,
+                carried_over: 1
+            })
+        );
+    }
+
+    #[test]
+    fn tickets_cannot_be_added_to_a_completed_sprint() {
+        //arrange
+        let mut ticket_store = test_store();
+        let sprint = plan_sprint(&mut ticket_store);
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+        ticket_store.start_sprint(sprint).unwrap();
+        ticket_store
+            .complete_sprint(sprint, CarryOver::Backlog)
+            .unwrap();
+
+        //act
+        let result = ticket_store.add_to_sprint(&ticket.id, sprint);
+
+        //assert
+        assert_eq!(result, Err(SprintError::Completed(sprint)));
+        assert_eq!(ticket_store.backlog().len(), 1);
+    }
+
+    #[test]
+    fn tickets_cannot_be_carried_over_to_the_sprint_being_completed() {
+        //arrange
+        let mut ticket_store = test_store();
+        let sprint = plan_sprint(&mut ticket_store);
+        ticket_store.start_sprint(sprint).unwrap();
+
+        //act
+        let result = ticket_store.complete_sprint(sprint, CarryOver::Sprint(sprint));
+
+        //assert
+        assert_eq!(result, Err(SprintError::CarryOverToSelf(sprint)));
+        assert_eq!(
+            ticket_store.get_sprint(sprint).unwrap().state,
+            SprintState::Active
+        );
+    }
+
+    fn worklog(author: &str, time_spent: &str, started_at: &str) -> Worklog {
+        Worklog {
+            author: user(author),
+            time_spent: time_spent.parse().expect("Failed to parse duration"),
+            started_at: started_at.parse().expect("Failed to parse date"),
+            note: None,
+        }
+    }
+
+    #[test]
+    fn logging_work_lowers_the_remaining_estimate() {
+        //arrange
+        let mut ticket_store = test_store();
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+        let patch = TicketPatch {
+            title: None,
+            description: None,
+            priority: None,
+            reporter: None,
+            assignee: None,
+            estimate: Some("1d".parse().unwrap()),
+            remaining: None,
+            custom_fields: BTreeMap::new(),
+        };
+        ticket_store.update_ticket(&ticket.id, patch);
+
+        //act
+        let result = ticket_store.log_work(
+            &ticket.id,
+            worklog("alice", "1h30m", "2020-03-02T09:00:00Z"),
+            None,
+        );
+
+        //assert
+        assert_eq!(result, Ok(()));
+        let ticket = ticket_store.get(&ticket.id).unwrap();
+        assert_eq!(ticket.estimate, Some("8h".parse().unwrap()));
+        assert_eq!(ticket.remaining, Some("6h30m".parse().unwrap()));
+        assert_eq!(ticket.time_spent(), "90m".parse().unwrap());
+    }
+
+    #[test]
+    fn worklogs_overflowing_the_time_spent_are_rejected() {
+        //arrange
+        let mut ticket_store = test_store();
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+        let long = "4000000000000000w";
+        ticket_store
+            .log_work(
+                &ticket.id,
+                worklog("alice", long, "2020-03-02T09:00:00Z"),
+                None,
+            )
+            .unwrap();
+
+        //act
+        let result = ticket_store.log_work(
+            &ticket.id,
+            worklog("alice", long, "2020-03-03T09:00:00Z"),
+            None,
+        );
+
+        //assert
+        assert_eq!(result, Err(WorklogError::TooLong(ticket.id.clone())));
+        let ticket = ticket_store.get(&ticket.id).unwrap();
+        assert_eq!(ticket.worklogs.len(), 1);
+        assert_eq!(ticket.time_spent(), long.parse().unwrap());
+    }
+
+    #[test]
+    fn timesheets_group_logged_time_by_user_and_day() {
+        //arrange
+        let mut ticket_store = test_store();
+        let first = generate_and_persist_ticket(&mut ticket_store);
+        let second = generate_and_persist_ticket(&mut ticket_store);
+        let worklogs = vec![
+            (&first, worklog("alice", "1h", "2020-03-02T09:00:00Z")),
+            (&second, worklog("alice", "30m", "2020-03-02T14:00:00Z")),
+            (&second, worklog("alice", "2h", "2020-03-03T09:00:00Z")),
+            (&first, worklog("bob", "4h", "2020-03-02T09:00:00Z")),
+            (&first, worklog("bob", "1d", "2020-03-09T09:00:00Z")),
+        ];
+        for (ticket, worklog) in worklogs {
+            ticket_store.log_work(&ticket.id, worklog, None).unwrap();
+        }
+
+        //act
+        let timesheet = ticket_store.timesheet(
+            NaiveDate::from_ymd(2020, 3, 2),
+            NaiveDate::from_ymd(2020, 3, 8),
+            &Utc,
+        );
+
+        //assert
+        let monday = NaiveDate::from_ymd(2020, 3, 2);
+        let tuesday = NaiveDate::from_ymd(2020, 3, 3);
+        assert_eq!(timesheet.len(), 2);
+        let alice = &timesheet[&user("alice")];
+        assert_eq!(alice[&monday], "1h30m".parse().unwrap());
+        assert_eq!(alice[&tuesday], "2h".parse().unwrap());
+        let bob = &timesheet[&user("bob")];
+        assert_eq!(bob.len(), 1);
+        assert_eq!(bob[&monday], "4h".parse().unwrap());
     }
 
-    /// Returns list off all inserted [Ticket](Ticket)
-    /// Returns an empty list of tickets is there are no tickets in the store
-    pub fn list(&self) -> Vec<&Ticket> {
-        self.data.iter().map(|(_, ticket)| ticket).collect()
-    }
+    #[test]
+    fn status_changes_are_recorded_with_their_actor() {
+        //arrange
+        let mut ticket_store = test_store();
+        ticket_store.set_actor(Some(user("alice")));
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
 
-    /// Generate a unique id by incrementing monotonically a private counter.
-    fn generate_id(&mut self) -> TicketId {
-        self.current_id += 1;
-        self.current_id
-    }
+        //act
+        ticket_store
+            .update_ticket_status(&ticket.id, Status::done(), None)
+            .unwrap();
 
-    /// Retrieve a [Ticket] given an identifier. Returns `None` if there is no ticket with such an identifier.
-    pub fn get(&self, id: TicketId) -> Option<&Ticket> {
-        self.data.get(&id)
+        //assert
+        let history = &ticket_store.get(&ticket.id).unwrap().history;
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].field, "created");
+        let change = &history[1];
+        assert_eq!(change.actor, Some(user("alice")));
+        assert_eq!(change.field, "status");
+        assert_eq!(change.old, Some("ToDo".to_string()));
+        assert_eq!(change.new, Some("Done".to_string()));
     }
 
-    // Update a [Ticket] given an identifier and new [TicketPatch]. Returns `None` if there is no ticket with such an identifier.
-    pub fn update_ticket(&mut self, id: TicketId, patch: TicketPatch) -> Option<()> {
-        self.data.get_mut(&id).map(|t| {
-            if let Some(title) = patch.title {
-                t.title = title;
-            }
-            if let Some(description) = patch.description {
-                t.description = description;
-            }
-        })
-    }
+    #[test]
+    fn patching_a_field_with_its_current_value_is_not_recorded() {
+        //arrange
+        let mut ticket_store = test_store();
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+        let patch = TicketPatch {
+            title: None,
+            description: Some(ticket.description.clone()),
+            priority: Some(Priority::Highest),
+            reporter: None,
+            assignee: None,
+            estimate: None,
+            remaining: None,
+            custom_fields: BTreeMap::new(),
+        };
+
+        //act
+        ticket_store.update_ticket(&ticket.id, patch);
 
-    // Update a [Ticket] [Status] given an identifier and new [Status]. Returns `None` if there is no ticket with such an identifier.
-    pub fn update_ticket_status(&mut self, id: TicketId, status: Status) -> Option<()> {
-        self.data.get_mut(&id).map(|t| t.status = status)
+        //assert
+        let history = &ticket_store.get(&ticket.id).unwrap().history;
+        let fields: Vec<&str> = history.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(fields, vec!["created", "priority"]);
+        assert_eq!(history[1].actor, None);
     }
 
-    pub fn add_comment_to_ticket(&mut self, id: TicketId, comment: Comment) -> Option<()> {
-        self.data.get_mut(&id).map(|t| t.comments.push(comment))
+    #[test]
+    fn links_are_recorded_on_both_tickets() {
+        //arrange
+        let mut ticket_store = test_store();
+        let blocker = generate_and_persist_ticket(&mut ticket_store);
+        let blocked = generate_and_persist_ticket(&mut ticket_store);
+
+        //act
+        ticket_store
+            .link(&blocker.id, LinkKind::Blocks, &blocked.id)
+            .unwrap();
+
+        //assert
+        let change = ticket_store
+            .get(&blocked.id)
+            .unwrap()
+            .history
+            .last()
+            .unwrap();
+        assert_eq!(change.field, "links");
+        assert_eq!(change.old, None);
+        assert_eq!(change.new, Some(format!("is blocked by {}", blocker.id)));
+        let change = ticket_store
+            .get(&blocker.id)
+            .unwrap()
+            .history
+            .last()
+            .unwrap();
+        assert_eq!(change.new, Some(format!("blocks {}", blocked.id)));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::models::{Comment, Status, Ticket, TicketDraft, TicketPatch, Title};
-    use crate::store::TicketStore;
-    use fake::{Fake, Faker};
-    use std::collections::HashSet;
+    /// Save a store and load it back, as the CLI does between two commands.
+    fn reload(ticket_store: &TicketStore) -> TicketStore {
+        let snapshot = serde_yaml::to_string(ticket_store).expect("Failed to save store");
+        serde_yaml::from_str(&snapshot).expect("Failed to load store")
+    }
 
     #[test]
-    fn create_ticket_test() {
+    fn replaying_events_rebuilds_the_tickets() {
         //arrange
-        let draft = TicketDraft {
-            title: Title::new(Faker.fake()).expect("Title should exist"),
-            description: Faker.fake(),
+        let mut ticket_store = test_store();
+        let snapshot = reload(&ticket_store);
+        ticket_store.set_actor(Some(user("alice")));
+        let first = generate_and_persist_ticket(&mut ticket_store);
+        let second = generate_and_persist_ticket(&mut ticket_store);
+        let patch = TicketPatch {
+            title: Some(Title::new(Faker.fake()).expect("Failed to get a title")),
+            description: None,
+            priority: Some(Priority::High),
+            reporter: None,
+            assignee: None,
+            estimate: None,
+            remaining: None,
+            custom_fields: BTreeMap::new(),
         };
-
-        let mut ticket_store = TicketStore::new();
+        ticket_store.update_ticket(&first.id, patch);
+        ticket_store
+            .update_ticket_status(&first.id, Status::done(), None)
+            .unwrap();
+        ticket_store
+            .add_comment_to_ticket(&first.id, Comment::new(Faker.fake()).unwrap(), None)
+            .unwrap();
+        ticket_store.delete(&second.id);
+        let events = ticket_store.take_events();
 
         //act
-        let ticket_id = ticket_store.create(draft.clone());
+        let mut replayed = snapshot;
+        for event in events.clone() {
+            replayed.replay(event).expect("Failed to replay event");
+        }
 
         //assert
-        let ticket = ticket_store
-            .get(ticket_id)
-            .expect("Failed to retrieve ticket.");
-        assert_eq!(ticket.title, draft.title);
-        assert_eq!(ticket.description, draft.description);
-        assert_eq!(ticket.status, Status::ToDo);
+        assert_eq!(events.len(), 6);
+        assert_eq!(replayed.list(), ticket_store.list());
     }
 
     #[test]
-    fn delete_ticket_test() {
+    fn events_saved_in_the_snapshot_are_not_replayed() {
         //arrange
-        let draft = TicketDraft {
-            title: Title::new(Faker.fake()).expect("Title should exist"),
-            description: Faker.fake(),
-        };
+        let mut ticket_store = test_store();
+        generate_and_persist_ticket(&mut ticket_store);
+        let events = ticket_store.take_events();
+        let mut snapshot = reload(&ticket_store);
 
-        let mut ticket_store = TicketStore::new();
-        let ticket_id = ticket_store.create(draft.clone());
-        let inserted_ticket = ticket_store
-            .get(ticket_id)
-            .expect("Failed to retrieve ticket")
-            .to_owned();
+        //act
+        for event in events {
+            snapshot.replay(event).expect("Failed to replay event");
+        }
+
+        //assert
+        assert_eq!(snapshot.list().len(), 1);
+    }
+
+    #[test]
+    fn changes_that_are_not_events_require_a_snapshot() {
+        //arrange
+        let mut ticket_store = test_store();
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+        let mut ticket_store = reload(&ticket_store);
 
         //act
-        let deleted_ticket = ticket_store
-            .delete(ticket_id)
-            .expect("There was no ticket to delete.");
+        ticket_store
+            .add_comment_to_ticket(&ticket.id, Comment::new(Faker.fake()).unwrap(), None)
+            .unwrap();
+        let after_event = ticket_store.needs_snapshot();
+        ticket_store.add_label(&ticket.id, label("backend"));
 
         //assert
-        assert_eq!(deleted_ticket.0, inserted_ticket);
-        let ticket = ticket_store.get(ticket_id);
-        assert_eq!(ticket, None);
+        assert!(!after_event);
+        assert!(ticket_store.needs_snapshot());
     }
 
     #[test]
-    fn deleting_a_ticket_that_does_not_exist_returns_none() {
+    fn undoing_a_delete_restores_the_ticket_and_its_links() {
         //arrange
-        let mut ticket_store = TicketStore::new();
+        let mut ticket_store = test_store();
+        let first = generate_and_persist_ticket(&mut ticket_store);
+        let second = generate_and_persist_ticket(&mut ticket_store);
+        ticket_store
+            .add_comment_to_ticket(&second.id, Comment::new(Faker.fake()).unwrap(), None)
+            .unwrap();
+        ticket_store
+            .link(&first.id, LinkKind::Blocks, &second.id)
+            .unwrap();
+        let before_delete = ticket_store.get(&second.id).unwrap().clone();
+        let checkpoint = ticket_store.checkpoint();
+        ticket_store.delete(&second.id);
+        let step = ticket_store
+            .changes_since(&checkpoint, "delete".to_string())
+            .expect("The delete was not recorded");
 
         //act
-        let deleted_ticket = ticket_store.delete(Faker.fake());
+        ticket_store.undo(&step);
 
         //assert
-        assert_eq!(deleted_ticket, None);
+        let restored = ticket_store.get(&second.id).unwrap();
+        assert_eq!(restored.comments, before_delete.comments);
+        assert_eq!(restored.links, before_delete.links);
+        assert_eq!(restored.history.last().unwrap().field, "undo");
+        assert_eq!(ticket_store.get(&first.id).unwrap().links.len(), 1);
+        assert!(ticket_store.trash().is_empty());
     }
 
     #[test]
-    fn listing_tickets_of_an_empty_store_returns_an_empty_collection() {
-        // Arrange
-        let ticket_store = TicketStore::new();
+    fn redoing_a_command_applies_it_again() {
+        //arrange
+        let mut ticket_store = test_store();
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+        let checkpoint = ticket_store.checkpoint();
+        ticket_store
+            .update_ticket_status(&ticket.id, Status::done(), None)
+            .unwrap();
+        let step = ticket_store
+            .changes_since(&checkpoint, "move".to_string())
+            .expect("The move was not recorded");
+        ticket_store.undo(&step);
+        let undone_status = ticket_store.get(&ticket.id).unwrap().status.clone();
 
-        // Act
-        let tickets = ticket_store.list();
+        //act
+        ticket_store.redo(&step);
 
-        // Assert
-        assert!(tickets.is_empty())
+        //assert
+        assert_eq!(undone_status, Status::to_do());
+        assert_eq!(ticket_store.get(&ticket.id).unwrap().status, Status::done());
     }
 
     #[test]
-    fn listing_tickets_should_return_them_all() {
-        // Arrange
-        let mut ticket_store = TicketStore::new();
-        let n_tickets = Faker.fake::<u16>() as usize;
-        let tickets: HashSet<_> = (0..n_tickets)
-            .map(|_| generate_and_persist_ticket(&mut ticket_store))
-            .collect();
+    fn undoing_a_command_that_does_not_change_tickets_only_reverts_that_command() {
+        //arrange
+        let mut ticket_store = test_store();
+        let mut undo_stack = UndoStack::default();
+        let checkpoint = ticket_store.checkpoint();
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+        undo_stack.push(
+            ticket_store
+                .changes_since(&checkpoint, "create".to_string())
+                .expect("The create was not recorded"),
+        );
+        let checkpoint = ticket_store.checkpoint();
+        let alice = register(&mut ticket_store, "alice");
+        undo_stack.push(
+            ticket_store
+                .changes_since(&checkpoint, "user add".to_string())
+                .expect("The new user was not recorded"),
+        );
 
-        // Act
-        let retrieved_tickets = ticket_store.list();
+        //act
+        let step = undo_stack.undo().unwrap().clone();
+        ticket_store.undo(&step);
 
-        // Assert
-        assert_eq!(retrieved_tickets.len(), n_tickets);
-        let retrieved_tickets: HashSet<_> = retrieved_tickets
-            .into_iter()
-            .map(|t| t.to_owned())
-            .collect();
-        assert_eq!(tickets, retrieved_tickets);
+        //assert
+        assert_eq!(step.command, "user add");
+        assert_eq!(ticket_store.get_user(&alice), None);
+        assert!(ticket_store.get(&ticket.id).is_some());
     }
 
-    fn generate_and_persist_ticket(store: &mut TicketStore) -> Ticket {
-        // arrange
-        let draft = TicketDraft {
-            title: Title::new(Faker.fake()).expect("Failed to get a title"),
-            description: Faker.fake(),
-        };
-        let ticket_id = store.create(draft);
-        store
-            .get(ticket_id)
-            .expect("Failed to retrieve ticket")
-            .to_owned()
+    #[test]
+    fn commands_that_change_nothing_cannot_be_undone() {
+        //arrange
+        let mut ticket_store = test_store();
+        generate_and_persist_ticket(&mut ticket_store);
+        let checkpoint = ticket_store.checkpoint();
+
+        //act
+        ticket_store.list();
+
+        //assert
+        assert_eq!(
+            ticket_store.changes_since(&checkpoint, "list".to_string()),
+            None
+        );
     }
 
     #[test]
-    fn updating_ticket_info_via_patch_should_update_ticket() {
-        // arrange
-        let mut ticket_store = TicketStore::new();
+    fn restored_tickets_keep_their_id_and_links() {
+        //arrange
+        let mut ticket_store = test_store();
+        let first = generate_and_persist_ticket(&mut ticket_store);
+        let second = generate_and_persist_ticket(&mut ticket_store);
+        ticket_store
+            .link(&first.id, LinkKind::Blocks, &second.id)
+            .unwrap();
+        ticket_store.delete(&second.id);
+        generate_and_persist_ticket(&mut ticket_store);
+
+        //act
+        let restored = ticket_store.restore(&second.id);
+
+        //assert
+        assert_eq!(restored, Some(()));
+        let ticket = ticket_store.get(&second.id).unwrap();
+        assert_eq!(ticket.title, second.title);
+        assert_eq!(ticket.links.len(), 1);
+        assert_eq!(ticket_store.get(&first.id).unwrap().links.len(), 1);
+        assert!(ticket_store.trash().is_empty());
+    }
 
+    #[test]
+    fn restoring_a_ticket_missing_from_the_trash_fails() {
+        //arrange
+        let mut ticket_store = test_store();
         let ticket = generate_and_persist_ticket(&mut ticket_store);
 
-        let patch = TicketPatch {
-            title: Some(Title::new(Faker.fake()).expect("Failed to get a title")),
-            description: Some(Faker.fake()),
-        };
+        //act
+        let restored = ticket_store.restore(&ticket.id);
 
-        let expected = patch.clone();
+        //assert
+        assert_eq!(restored, None);
+    }
+
+    #[test]
+    fn purge_only_deletes_old_tickets_for_good() {
+        //arrange
+        let mut ticket_store = test_store();
+        let old = generate_and_persist_ticket(&mut ticket_store);
+        let recent = generate_and_persist_ticket(&mut ticket_store);
+        ticket_store.context.at = Some(Utc::now() - Duration::days(40));
+        ticket_store.delete(&old.id);
+        ticket_store.context.at = None;
+        ticket_store.delete(&recent.id);
 
         //act
-        ticket_store.update_ticket(ticket.id, patch);
+        let purged = ticket_store.purge(Age::from_days(30));
 
         //assert
-        let updated_ticket = ticket_store
-            .get(ticket.id)
-            .expect("Failed to retrieve ticket.");
+        assert_eq!(purged, vec![old.id.clone()]);
+        assert_eq!(ticket_store.restore(&old.id), None);
+        assert_eq!(ticket_store.restore(&recent.id), Some(()));
+    }
+
+    #[test]
+    fn purging_before_the_earliest_date_deletes_nothing() {
+        //arrange
+        let mut ticket_store = test_store();
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+        ticket_store.delete(&ticket.id);
 
+        //act
+        let purged = ticket_store.purge("99999999w".parse().unwrap());
+
+        //assert
+        assert!(purged.is_empty());
+        assert_eq!(ticket_store.restore(&ticket.id), Some(()));
+    }
+
+    fn register(ticket_store: &mut TicketStore, handle: &str) -> UserId {
+        ticket_store.add_user(User {
+            id: user(handle),
+            display_name: handle.to_string(),
+        });
+        user(handle)
+    }
+
+    #[test]
+    fn mentioned_users_are_notified() {
+        //arrange
+        let mut ticket_store = test_store();
+        let alice = register(&mut ticket_store, "alice");
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+        let bob = register(&mut ticket_store, "bob");
+        ticket_store.set_actor(Some(bob));
+        let comment = Comment::new("@alice and @nobody, can you have a look?".to_string()).unwrap();
+
+        //act
+        let comment_id = ticket_store
+            .add_comment_to_ticket(&ticket.id, comment, None)
+            .unwrap();
+
+        //assert
+        let inbox = ticket_store.inbox(&alice);
+        assert_eq!(inbox.len(), 1);
+        assert_eq!(inbox[0].ticket, ticket.id);
         assert_eq!(
-            updated_ticket.title,
-            expected.title.expect("Failed to get a title")
+            inbox[0].reason,
+            NotificationReason::MentionedInComment(comment_id)
         );
+        assert_eq!(inbox[0].actor, Some(user("bob")));
+        assert!(ticket_store.inbox(&user("nobody")).is_empty());
+    }
+
+    #[test]
+    fn users_are_not_notified_twice_for_the_same_description() {
+        //arrange
+        let mut ticket_store = test_store();
+        let alice = register(&mut ticket_store, "alice");
+        let carol = register(&mut ticket_store, "carol");
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+        let patch = |description: &str| TicketPatch {
+            title: None,
+            description: Some(description.to_string()),
+            priority: None,
+            reporter: None,
+            assignee: None,
+            estimate: None,
+            remaining: None,
+            custom_fields: BTreeMap::new(),
+        };
+        ticket_store.update_ticket(&ticket.id, patch("Ask @alice"));
+
+        //act
+        ticket_store.update_ticket(&ticket.id, patch("Ask @alice or @carol"));
 
+        //assert
+        assert_eq!(ticket_store.inbox(&alice).len(), 1);
         assert_eq!(
-            updated_ticket.description,
-            expected.description.expect("Failed to get a Description")
+            ticket_store.inbox(&carol)[0].reason,
+            NotificationReason::MentionedInDescription
         );
     }
 
     #[test]
-    fn updating_ticket_with_no_patch_values_should_not_fail_or_change_values() {
+    fn users_are_not_notified_of_their_own_mentions() {
         //arrange
-        let draft = TicketDraft {
-            title: Title::new(Faker.fake()).expect("Failed to get a title"),
-            description: Faker.fake(),
-        };
+        let mut ticket_store = test_store();
+        let alice = register(&mut ticket_store, "alice");
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+        ticket_store.set_actor(Some(alice.clone()));
+        let comment = Comment::new("Note to self, @alice: check the logs".to_string()).unwrap();
 
-        let mut ticket_store = TicketStore::new();
+        //act
+        ticket_store
+            .add_comment_to_ticket(&ticket.id, comment, None)
+            .unwrap();
 
-        let ticket_id = ticket_store.create(draft.clone());
+        //assert
+        assert!(ticket_store.inbox(&alice).is_empty());
+    }
 
-        let patch = TicketPatch {
-            title: None,
-            description: None,
-        };
+    #[test]
+    fn notifications_can_be_marked_as_read() {
+        //arrange
+        let mut ticket_store = test_store();
+        let alice = register(&mut ticket_store, "alice");
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+        for _ in 0..2 {
+            let comment = Comment::new("Ping @alice".to_string()).unwrap();
+            ticket_store
+                .add_comment_to_ticket(&ticket.id, comment, None)
+                .unwrap();
+        }
+        let first = ticket_store.inbox(&alice)[1].id;
 
         //act
-        ticket_store.update_ticket(ticket_id, patch);
+        let marked = ticket_store.mark_as_read(&alice, first);
+        let others = ticket_store.mark_all_as_read(&alice);
 
         //assert
-        let updated_ticket = ticket_store
-            .get(ticket_id)
-            .expect("Failed to retrieve ticket.");
+        assert_eq!(marked, Some(()));
+        assert_eq!(others, 1);
+        assert!(ticket_store.inbox(&alice).iter().all(|n| n.read));
+    }
 
-        assert_eq!(updated_ticket.title, draft.title);
+    fn blob(hash: &str) -> Blob {
+        Blob {
+            hash: hash.to_string(),
+            size: 42,
+        }
+    }
 
-        assert_eq!(updated_ticket.description, draft.description);
+    #[test]
+    fn attaching_a_file_with_the_same_name_replaces_it() {
+        //arrange
+        let mut ticket_store = test_store();
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+        ticket_store.attach(&ticket.id, "server.log".to_string(), blob("a1"));
+        ticket_store.attach(&ticket.id, "screenshot.png".to_string(), blob("b2"));
+
+        //act
+        let attached = ticket_store.attach(&ticket.id, "server.log".to_string(), blob("c3"));
+
+        //assert
+        assert_eq!(attached, Some(()));
+        let attachments = &ticket_store.get(&ticket.id).unwrap().attachments;
+        let hashes: Vec<&str> = attachments.iter().map(|a| a.blob.hash.as_str()).collect();
+        assert_eq!(hashes, vec!["b2", "c3"]);
+        assert_eq!(attachments[0].mime_type, "image/png");
     }
 
     #[test]
-    fn updating_ticket_status_should_change_ticket_to_new_status() {
+    fn files_attached_to_deleted_tickets_are_still_referenced() {
         //arrange
-        let mut ticket_store = TicketStore::new();
+        let mut ticket_store = test_store();
+        let kept = generate_and_persist_ticket(&mut ticket_store);
+        let deleted = generate_and_persist_ticket(&mut ticket_store);
+        ticket_store.attach(&kept.id, "kept.log".to_string(), blob("a1"));
+        ticket_store.attach(&deleted.id, "deleted.log".to_string(), blob("b2"));
+        ticket_store.delete(&deleted.id);
+
+        //act
+        let hashes = ticket_store.attachment_hashes();
 
+        //assert
+        assert_eq!(hashes.into_iter().collect::<Vec<&str>>(), vec!["a1", "b2"]);
+    }
+
+    #[test]
+    fn files_attached_to_purged_tickets_are_referenced_while_the_purge_can_be_undone() {
+        //arrange
+        let mut ticket_store = test_store();
         let ticket = generate_and_persist_ticket(&mut ticket_store);
+        ticket_store.attach(&ticket.id, "purged.log".to_string(), blob("a1"));
+        ticket_store.delete(&ticket.id);
+        let checkpoint = ticket_store.checkpoint();
+        ticket_store.purge(Age::from_days(0));
+        let mut undo_stack = UndoStack::default();
+        undo_stack.push(
+            ticket_store
+                .changes_since(&checkpoint, "trash purge".to_string())
+                .expect("The purge was not recorded"),
+        );
 
         //act
-        ticket_store.update_ticket_status(ticket.id, Status::Done);
+        let hashes: Vec<&str> = undo_stack
+            .tickets()
+            .flat_map(|t| t.attachments.iter())
+            .map(|a| a.blob.hash.as_str())
+            .collect();
 
         //assert
-        let updated_ticket = ticket_store
-            .get(ticket.id)
-            .expect("Failed to retrieve ticket.");
-
-        assert_eq!(updated_ticket.status, Status::Done)
+        assert!(ticket_store.attachment_hashes().is_empty());
+        assert_eq!(hashes, vec!["a1"]);
     }
 
     #[test]
-    fn add_comment_to_ticket() {
+    fn watchers_are_notified_when_a_ticket_is_moved_edited_or_commented() {
         //arrange
-        let mut ticket_store = TicketStore::new();
+        let mut ticket_store = test_store();
+        let alice = register(&mut ticket_store, "alice");
+        let bob = register(&mut ticket_store, "bob");
         let ticket = generate_and_persist_ticket(&mut ticket_store);
-        let comment = Comment::new("Test Comment".to_string()).unwrap();
-        let expected = comment.clone();
+        ticket_store.watch(&ticket.id, alice.clone());
+        ticket_store.watch(&ticket.id, bob.clone());
+        ticket_store.set_actor(Some(bob.clone()));
 
         //act
-        let result = ticket_store.add_comment_to_ticket(ticket.id, comment);
+        ticket_store
+            .update_ticket_status(&ticket.id, Status::done(), None)
+            .unwrap();
+        ticket_store.update_ticket(
+            &ticket.id,
+            TicketPatch {
+                title: None,
+                description: None,
+                priority: Some(Priority::High),
+                reporter: None,
+                assignee: None,
+                estimate: None,
+                remaining: None,
+                custom_fields: BTreeMap::new(),
+            },
+        );
+        let comment_id = ticket_store
+            .add_comment_to_ticket(&ticket.id, Comment::new(Faker.fake()).unwrap(), None)
+            .unwrap();
+
         //assert
-        assert!(result.is_some());
-        let ticket = ticket_store.get(ticket.id).unwrap();
-        assert_eq!(ticket.comments, vec![expected]);
+        let reasons: Vec<&NotificationReason> = ticket_store
+            .inbox(&alice)
+            .iter()
+            .map(|n| &n.reason)
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                &NotificationReason::Commented(comment_id),
+                &NotificationReason::Edited,
+                &NotificationReason::StatusChanged(Status::done()),
+            ]
+        );
+        assert!(ticket_store.inbox(&bob).is_empty());
     }
 
     #[test]
-    fn add_comment_to_invalid_ticket_id_returns_none() {
-        let faker = fake::Faker;
+    fn mentioned_watchers_are_notified_once() {
+        //arrange
+        let mut ticket_store = test_store();
+        let alice = register(&mut ticket_store, "alice");
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+        ticket_store.watch(&ticket.id, alice.clone());
+        let last_notification = ticket_store.last_notification_id();
+        let comment = Comment::new("@alice, any idea?".to_string()).unwrap();
+
+        //act
+        ticket_store
+            .add_comment_to_ticket(&ticket.id, comment, None)
+            .unwrap();
 
+        //assert
+        let sent = ticket_store.notifications_since(last_notification);
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].0, &alice);
+        assert_eq!(sent[0].1.reason, NotificationReason::MentionedInComment(1));
+    }
+
+    #[test]
+    fn unwatched_tickets_do_not_notify() {
         //arrange
-        let mut ticket_store = TicketStore::new();
-        let comment = Comment::new("Test comment".to_string()).unwrap();
+        let mut ticket_store = test_store();
+        let alice = register(&mut ticket_store, "alice");
+        let ticket = generate_and_persist_ticket(&mut ticket_store);
+        ticket_store.watch(&ticket.id, alice.clone());
 
         //act
-        let result = ticket_store.add_comment_to_ticket(faker.fake(), comment);
+        let unwatched = ticket_store.unwatch(&ticket.id, &alice);
+        ticket_store
+            .add_comment_to_ticket(&ticket.id, Comment::new(Faker.fake()).unwrap(), None)
+            .unwrap();
 
         //assert
-        assert!(result.is_none());
+        assert_eq!(unwatched, Some(()));
+        assert!(ticket_store.inbox(&alice).is_empty());
     }
 }